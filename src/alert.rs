@@ -0,0 +1,179 @@
+use std::{fmt, str::FromStr};
+
+use crossterm::style::ContentStyle;
+use regex::Regex;
+
+use crate::theme::overlay;
+use crate::viewport::{Line, Span};
+
+/// A threshold on a number in the command's output, e.g. a load average above 4. Lines where it's
+/// crossed are highlighted, and when it starts being crossed the terminal beeps and a desktop
+/// notification is shown.
+#[derive(Clone, Debug)]
+pub struct AlertRule {
+    pattern: Regex,
+    comparison: Comparison,
+    threshold: f64,
+}
+
+/// How a number is compared to an [`AlertRule`]'s threshold.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Comparison {
+    Greater,
+    GreaterOrEqual,
+    Less,
+    LessOrEqual,
+    Equal,
+    NotEqual,
+}
+
+impl Comparison {
+    /// The operators, longest first so `>=` isn't taken for `>`.
+    const ALL: [(&'static str, Comparison); 6] = [
+        (">=", Comparison::GreaterOrEqual),
+        ("<=", Comparison::LessOrEqual),
+        ("==", Comparison::Equal),
+        ("!=", Comparison::NotEqual),
+        (">", Comparison::Greater),
+        ("<", Comparison::Less),
+    ];
+
+    fn holds(self, value: f64, threshold: f64) -> bool {
+        match self {
+            Comparison::Greater => value > threshold,
+            Comparison::GreaterOrEqual => value >= threshold,
+            Comparison::Less => value < threshold,
+            Comparison::LessOrEqual => value <= threshold,
+            Comparison::Equal => value == threshold,
+            Comparison::NotEqual => value != threshold,
+        }
+    }
+
+    fn operator(self) -> &'static str {
+        Comparison::ALL
+            .iter()
+            .find(|(_, comparison)| *comparison == self)
+            .map(|(operator, _)| *operator)
+            .unwrap_or_default()
+    }
+}
+
+impl AlertRule {
+    /// Whether any number `line` captures crosses the threshold.
+    pub(crate) fn is_crossed_by(&self, line: &str) -> bool {
+        self.pattern.captures_iter(line).any(|captures| {
+            // The first group if there is one, else the whole match
+            let found = captures.get(1).or_else(|| captures.get(0));
+            found
+                .and_then(|found| found.as_str().trim().parse::<f64>().ok())
+                .is_some_and(|value| self.comparison.holds(value, self.threshold))
+        })
+    }
+
+    /// The first line of `text` that crosses the threshold, if any.
+    pub(crate) fn first_crossing<'a>(&self, text: &'a str) -> Option<&'a str> {
+        text.lines().find(|line| self.is_crossed_by(line))
+    }
+}
+
+/// Parses a `"<regex> <operator> <number>"` rule, e.g. `r"load average: (\d+\.\d+) > 4"`. The
+/// number compared is the regex's first capture group, or its whole match if it has none. The
+/// operators are `>`, `>=`, `<`, `<=`, `==`, and `!=`.
+///
+/// # Examples
+///
+/// ```
+/// use watch_rs::AlertRule;
+///
+/// assert!(r"load average: (\d+\.\d+) > 4.0".parse::<AlertRule>().is_ok());
+/// assert!(r"(\d+)% used >= 90".parse::<AlertRule>().is_ok());
+/// assert!(r"(\d+)% used".parse::<AlertRule>().is_err());
+/// assert!(r"(\d+)% used > lots".parse::<AlertRule>().is_err());
+/// ```
+impl FromStr for AlertRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let malformed = || format!("`{s}` isn't in `<regex> <operator> <number>` form");
+        let (rest, threshold) = s
+            .trim()
+            .rsplit_once(char::is_whitespace)
+            .ok_or_else(malformed)?;
+        let threshold: f64 = threshold
+            .parse()
+            .map_err(|_| format!("`{threshold}` isn't a number"))?;
+        let rest = rest.trim_end();
+        let (pattern, comparison) = Comparison::ALL
+            .iter()
+            .find_map(|(operator, comparison)| Some((rest.strip_suffix(operator)?, *comparison)))
+            .ok_or_else(malformed)?;
+        let pattern = pattern.trim_end();
+        if pattern.is_empty() {
+            return Err(malformed());
+        }
+        let pattern = Regex::new(pattern).map_err(|err| err.to_string())?;
+        Ok(AlertRule {
+            pattern,
+            comparison,
+            threshold,
+        })
+    }
+}
+
+impl fmt::Display for AlertRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {}",
+            self.pattern,
+            self.comparison.operator(),
+            self.threshold
+        )
+    }
+}
+
+/// Which of the rules were crossed by the previous run, to tell when one starts being crossed.
+#[derive(Debug, Default)]
+pub(crate) struct AlertState {
+    crossed: Vec<bool>,
+}
+
+impl AlertState {
+    /// Checks `output` against `rules`, returning those it crosses that the previous run didn't,
+    /// each with the first line crossing it.
+    pub fn update<'a, 'b>(
+        &mut self,
+        rules: &'a [AlertRule],
+        output: &'b str,
+    ) -> Vec<(&'a AlertRule, &'b str)> {
+        self.crossed.resize(rules.len(), false);
+        let mut started = Vec::new();
+        for (rule, crossed) in rules.iter().zip(&mut self.crossed) {
+            let crossing = rule.first_crossing(output);
+            if let (Some(line), false) = (crossing, *crossed) {
+                started.push((rule, line));
+            }
+            *crossed = crossing.is_some();
+        }
+        started
+    }
+}
+
+/// Layers `style` over every line that crosses any of `rules`.
+pub(crate) fn apply(lines: Vec<Line>, rules: &[AlertRule], style: ContentStyle) -> Vec<Line> {
+    if rules.is_empty() {
+        return lines;
+    }
+    lines
+        .into_iter()
+        .map(|line| {
+            let text: String = line.iter().map(|span| span.text.as_str()).collect();
+            if !rules.iter().any(|rule| rule.is_crossed_by(&text)) {
+                return line;
+            }
+            line.into_iter()
+                .map(|span| Span::new(overlay(span.style, style), span.text))
+                .collect()
+        })
+        .collect()
+}
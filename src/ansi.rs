@@ -0,0 +1,206 @@
+use crossterm::style::{Attribute, Color, ContentStyle};
+
+use crate::viewport::{line_width, push_char, Line};
+
+const ESC: char = '\x1b';
+const BEL: char = '\x07';
+
+/// How many columns apart tab stops are by default.
+pub(crate) const TAB_WIDTH: usize = 8;
+
+/// How [`parse`] treats the parts of the output that aren't plain text.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Options {
+    /// Interpret SGR sequences, rather than stripping them.
+    pub color: bool,
+    /// Show what isn't interpreted escaped, rather than dropping it.
+    pub show_controls: bool,
+    /// How many columns apart tab stops are.
+    pub tab_width: usize,
+}
+
+/// Splits `text` into lines, re-emitting ANSI SGR (color/attribute) sequences as crossterm styles.
+///
+/// Without [`Options::color`] the SGR sequences are stripped instead. Other escape sequences
+/// (cursor movement, window titles, ...) and control characters (bells, backspaces, ...) would
+/// corrupt the screen, so they're dropped, or with [`Options::show_controls`] shown escaped, e.g.
+/// `^G`. Tabs are expanded to spaces up to the next tab stop, and a carriage return that doesn't
+/// end a line starts it over, like a progress bar.
+pub(crate) fn parse(text: &str, options: Options) -> Vec<Line> {
+    let Options {
+        color,
+        show_controls,
+        tab_width,
+    } = options;
+    let mut lines: Vec<Line> = Vec::new();
+    let mut line = Line::new();
+    let mut style = ContentStyle::new();
+    // Shows what wasn't interpreted, with `show_controls`
+    let show = |line: &mut Line, style: ContentStyle, raw: &str| {
+        if show_controls {
+            let mut escaped = style;
+            escaped.attributes.set(Attribute::Reverse);
+            for c in escape(raw).chars() {
+                push_char(line, escaped, c);
+            }
+        }
+    };
+
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => lines.push(std::mem::take(&mut line)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\r' if !show_controls => line.clear(),
+            '\t' => {
+                let column = line_width(&line);
+                for _ in 0..tab_width - column % tab_width {
+                    push_char(&mut line, style, ' ');
+                }
+            }
+            ESC => {
+                let mut sequence = String::from(ESC);
+                match chars.next() {
+                    // CSI: parameters and intermediates up to a final byte in '@'..='~'
+                    Some('[') => {
+                        sequence.push('[');
+                        let mut params = String::new();
+                        let mut final_byte = None;
+                        for c in chars.by_ref() {
+                            sequence.push(c);
+                            if ('@'..='~').contains(&c) {
+                                final_byte = Some(c);
+                                break;
+                            }
+                            params.push(c);
+                        }
+                        if color && final_byte == Some('m') {
+                            apply_sgr(&mut style, &params);
+                            continue;
+                        }
+                    }
+                    // OSC, DCS, SOS, PM, and APC: strings terminated by BEL or ST (ESC \\)
+                    Some(c @ (']' | 'P' | 'X' | '^' | '_')) => {
+                        sequence.push(c);
+                        while let Some(c) = chars.next() {
+                            sequence.push(c);
+                            if c == BEL {
+                                break;
+                            }
+                            if c == ESC && chars.next_if_eq(&'\\').is_some() {
+                                sequence.push('\\');
+                                break;
+                            }
+                        }
+                    }
+                    // Anything else: intermediates in ' '..='/', up to a final byte
+                    Some(c) => {
+                        sequence.push(c);
+                        let mut last = c;
+                        while (' '..='/').contains(&last) {
+                            let Some(c) = chars.next() else { break };
+                            sequence.push(c);
+                            last = c;
+                        }
+                    }
+                    None => {}
+                }
+                show(&mut line, style, &sequence);
+            }
+            c if c.is_control() => show(&mut line, style, c.encode_utf8(&mut [0; 4])),
+            _ => push_char(&mut line, style, c),
+        }
+    }
+    if !text.is_empty() && !text.ends_with('\n') {
+        lines.push(line);
+    }
+    lines
+}
+
+/// Writes the control characters in `raw` in caret notation, e.g. `^[` for ESC, and C1 controls
+/// by their code, e.g. `<9b>`.
+fn escape(raw: &str) -> String {
+    raw.chars()
+        .map(|c| match c {
+            '\0'..='\x1f' => format!("^{}", char::from(c as u8 + 0x40)),
+            '\x7f' => "^?".to_string(),
+            '\u{80}'..='\u{9f}' => format!("<{:02x}>", c as u32),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+/// The visible text of `line`, without any escape sequences.
+pub(crate) fn strip(line: &str) -> String {
+    let options = Options {
+        color: false,
+        show_controls: false,
+        tab_width: TAB_WIDTH,
+    };
+    parse(line, options)
+        .iter()
+        .flatten()
+        .map(|span| span.text.as_str())
+        .collect()
+}
+
+/// Applies the `;`-separated parameters of an SGR sequence to `style`.
+fn apply_sgr(style: &mut ContentStyle, params: &str) {
+    // An empty parameter list means reset
+    if params.is_empty() {
+        *style = ContentStyle::new();
+        return;
+    }
+    let mut codes = params
+        .split([';', ':'])
+        .map(|code| code.parse::<u8>().unwrap_or(0));
+    while let Some(code) = codes.next() {
+        match code {
+            0 => *style = ContentStyle::new(),
+            1 => style.attributes.set(Attribute::Bold),
+            2 => style.attributes.set(Attribute::Dim),
+            3 => style.attributes.set(Attribute::Italic),
+            4 => style.attributes.set(Attribute::Underlined),
+            5 => style.attributes.set(Attribute::SlowBlink),
+            6 => style.attributes.set(Attribute::RapidBlink),
+            7 => style.attributes.set(Attribute::Reverse),
+            8 => style.attributes.set(Attribute::Hidden),
+            9 => style.attributes.set(Attribute::CrossedOut),
+            22 => {
+                style.attributes.unset(Attribute::Bold);
+                style.attributes.unset(Attribute::Dim);
+            }
+            23 => style.attributes.unset(Attribute::Italic),
+            24 => style.attributes.unset(Attribute::Underlined),
+            25 => {
+                style.attributes.unset(Attribute::SlowBlink);
+                style.attributes.unset(Attribute::RapidBlink);
+            }
+            27 => style.attributes.unset(Attribute::Reverse),
+            28 => style.attributes.unset(Attribute::Hidden),
+            29 => style.attributes.unset(Attribute::CrossedOut),
+            30..=37 => style.foreground_color = Some(Color::AnsiValue(code - 30)),
+            38 => style.foreground_color = extended_color(&mut codes),
+            39 => style.foreground_color = None,
+            40..=47 => style.background_color = Some(Color::AnsiValue(code - 40)),
+            48 => style.background_color = extended_color(&mut codes),
+            49 => style.background_color = None,
+            90..=97 => style.foreground_color = Some(Color::AnsiValue(code - 90 + 8)),
+            100..=107 => style.background_color = Some(Color::AnsiValue(code - 100 + 8)),
+            _ => {}
+        }
+    }
+}
+
+/// Parses the rest of a `38;5;n` or `38;2;r;g;b` (and `48;...`) sequence.
+fn extended_color(codes: &mut impl Iterator<Item = u8>) -> Option<Color> {
+    match codes.next() {
+        Some(5) => codes.next().map(Color::AnsiValue),
+        Some(2) => Some(Color::Rgb {
+            r: codes.next()?,
+            g: codes.next()?,
+            b: codes.next()?,
+        }),
+        _ => None,
+    }
+}
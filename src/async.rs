@@ -0,0 +1,257 @@
+//! An async engine for embedding the watch in applications built on [tokio], enabled with the
+//! `async` feature.
+//!
+//! It runs the command with `tokio::process` and waits with `tokio::time`, so it never blocks a
+//! thread. It must be polled from within a tokio runtime.
+
+use std::{
+    collections::VecDeque,
+    io::{Error, Result},
+    process::{Command, Stdio},
+    time::{Duration, Instant, SystemTime},
+};
+
+use futures_util::{
+    future,
+    stream::{self, Stream},
+};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::error::WatchError;
+use crate::exec::{self, ExecutionResult, Filter, Output, Pipe, Status, Step};
+use crate::headless::{Wait, WatchStream};
+use crate::truncation::{Capture, OutputCap};
+use crate::watcher::Watcher;
+
+/// Runs `watcher`'s command on its interval, yielding each execution's result.
+///
+/// This is the async counterpart of [`Watcher::watch_iter`], which it behaves like otherwise: the
+/// first execution happens immediately, and the stream ends once an exit condition is met. A
+/// command still running when the stream is dropped is killed.
+///
+/// # Examples
+///
+/// ```no_run
+/// use futures_util::StreamExt;
+/// use watch_rs::{r#async, Watcher};
+///
+/// # async fn example() -> Result<(), watch_rs::WatchError> {
+/// let mut runs = Box::pin(r#async::watch(Watcher::new("uptime").count(Some(3))));
+/// while let Some(result) = runs.next().await {
+///     println!("{}", result?.stdout);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn watch(
+    watcher: Watcher,
+) -> impl Stream<Item = std::result::Result<ExecutionResult, WatchError>> + Send {
+    stream::unfold(watcher.watch_iter(), |mut runs| async move {
+        let result = next(&mut runs).await?;
+        Some((result, runs))
+    })
+}
+
+/// Waits for the next execution to be due, then runs it.
+async fn next(runs: &mut WatchStream) -> Option<std::result::Result<ExecutionResult, WatchError>> {
+    if let Err(err) = runs.begin()? {
+        return Some(Err(err));
+    }
+    loop {
+        match runs.poll_wait() {
+            Wait::Run => break,
+            Wait::Sleep(wait) => tokio::time::sleep(wait).await,
+            Wait::Stop => return None,
+        }
+    }
+    let scheduled = runs.scheduled();
+    let output = run(runs.steps(), runs.timeout()).await;
+    runs.complete(output, scheduled)
+}
+
+/// Runs `steps` to completion one after the other, or parallel ones at the same time, capturing
+/// their output, like [`exec::run`](crate::exec::run).
+async fn run(
+    steps: Vec<Step>,
+    timeout: Option<Duration>,
+) -> std::result::Result<Output, WatchError> {
+    let mut steps = VecDeque::from(steps);
+    let mut output: Option<Output> = None;
+    loop {
+        let batch = exec::next_batch(&mut steps);
+        if batch.is_empty() {
+            break;
+        }
+        let outputs = future::try_join_all(batch.into_iter().map(|step| async move {
+            let output = run_step(step.command, step.filter, step.cap, timeout).await?;
+            Ok::<_, WatchError>(output.headed(step.heading.as_deref(), step.parallel))
+        }))
+        .await?;
+        let next = outputs.into_iter().reduce(Output::alongside);
+        output = match (output, next) {
+            (Some(output), Some(next)) => Some(output.then(next)),
+            (output, next) => output.or(next),
+        };
+    }
+    output.ok_or_else(|| Error::other("there's no command to run").into())
+}
+
+/// Runs `command` to completion, capturing as much of its output as `cap` allows, piped through
+/// `filter` if there is one.
+///
+/// If `timeout` elapses first the command is killed, along with its process group on Unix, and
+/// whatever it printed so far is returned.
+async fn run_step(
+    command: Command,
+    filter: Option<Filter>,
+    cap: Option<OutputCap>,
+    timeout: Option<Duration>,
+) -> std::result::Result<Output, WatchError> {
+    let mut command = tokio::process::Command::from(command);
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    #[cfg(unix)]
+    command.process_group(0);
+
+    let started_at = SystemTime::now();
+    let start_time = Instant::now();
+    let mut child = command.spawn().map_err(|source| WatchError::Spawn {
+        program: command
+            .as_std()
+            .get_program()
+            .to_string_lossy()
+            .into_owned(),
+        source,
+    })?;
+    // With a filter, what's captured is its output, along with the command's stderr
+    let mut filter = match filter {
+        Some(filter) => Some(spawn_filter(filter, &mut child)?),
+        None => None,
+    };
+    let output = filter.as_mut().map_or(&mut child, |(filter, _)| filter);
+    let (Some(mut stdout_pipe), Some(mut stderr_pipe)) =
+        (output.stdout.take(), output.stderr.take())
+    else {
+        return Err(Error::other("the command's output wasn't captured").into());
+    };
+    let mut command_stderr_pipe = child.stderr.take();
+    let mut stdout = Capture::default();
+    let mut stderr = Capture::default();
+    let mut command_stderr = Capture::default();
+
+    let finished = async {
+        let (status, filter_status, ..) = tokio::try_join!(
+            child.wait(),
+            async {
+                match filter.as_mut() {
+                    Some((filter, _)) => filter.wait().await.map(Some),
+                    None => Ok(None),
+                }
+            },
+            read_all(&mut stdout_pipe, &mut stdout, cap),
+            read_all(&mut stderr_pipe, &mut stderr, cap),
+            async {
+                match command_stderr_pipe.as_mut() {
+                    Some(pipe) => read_all(pipe, &mut command_stderr, cap).await,
+                    None => Ok(()),
+                }
+            },
+        )?;
+        Ok::<_, Error>((Status::Exited(status), filter_status))
+    };
+    let (status, filter_status) = match timeout {
+        None => finished.await?,
+        Some(timeout) => match tokio::time::timeout(timeout, finished).await {
+            Ok(finished) => finished?,
+            Err(_) => {
+                kill_group(&child);
+                child.kill().await?;
+                (Status::TimedOut(timeout), None)
+            }
+        },
+    };
+    command_stderr.dropped += stderr.dropped;
+    command_stderr.push(&stderr.bytes, cap);
+    stdout.finish(cap);
+    command_stderr.finish(cap);
+    let mut output = Output {
+        status,
+        stdout: stdout.bytes,
+        stderr: command_stderr.bytes,
+        // The pipes are read separately, so there's no order to interleave them by
+        chunks: Vec::new(),
+        started_at,
+        duration: start_time.elapsed(),
+    };
+    if let Some(cap) = cap {
+        output = output
+            .truncated(Pipe::Stdout, stdout.dropped, cap.truncation)
+            .truncated(Pipe::Stderr, command_stderr.dropped, cap.truncation);
+    }
+    Ok(match &filter {
+        Some((_, command_line)) => output.piped(command_line, filter_status),
+        None => output,
+    })
+}
+
+/// Spawns `filter` reading the stdout of `command`, in the same process group on Unix.
+fn spawn_filter(
+    filter: Filter,
+    command: &mut tokio::process::Child,
+) -> std::result::Result<(tokio::process::Child, String), WatchError> {
+    let mut filter_command = tokio::process::Command::from(filter.command);
+    let stdin: Stdio = match command.stdout.take() {
+        Some(pipe) => pipe.try_into()?,
+        None => Stdio::null(),
+    };
+    filter_command
+        .stdin(stdin)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    #[cfg(unix)]
+    if let Some(pid) = command.id() {
+        filter_command.process_group(pid as i32);
+    }
+    let child = filter_command.spawn().map_err(|source| WatchError::Spawn {
+        program: filter_command
+            .as_std()
+            .get_program()
+            .to_string_lossy()
+            .into_owned(),
+        source,
+    })?;
+    Ok((child, filter.command_line))
+}
+
+/// Reads `pipe` to the end into `capture`, keeping within `cap`. What was read so far is kept if
+/// this is cancelled.
+async fn read_all(
+    pipe: &mut (impl AsyncRead + Unpin),
+    capture: &mut Capture,
+    cap: Option<OutputCap>,
+) -> Result<()> {
+    let mut chunk = [0; 8192];
+    loop {
+        match pipe.read(&mut chunk).await? {
+            0 => return Ok(()),
+            n => capture.push(&chunk[..n], cap),
+        };
+    }
+}
+
+/// Kills everything the command spawned, which leads its own process group.
+#[cfg(unix)]
+fn kill_group(child: &tokio::process::Child) {
+    if let Some(pid) = child.id() {
+        // SAFETY: `kill` has no memory-safety preconditions
+        unsafe { libc::kill(-(pid as libc::pid_t), libc::SIGKILL) };
+    }
+}
+
+/// On Windows only the command itself is killed, by the caller.
+#[cfg(not(unix))]
+fn kill_group(_child: &tokio::process::Child) {}
@@ -0,0 +1,57 @@
+use std::io::{stdout, Result, Write};
+
+/// Copies text to the system clipboard, or through the terminal with an OSC 52 sequence when
+/// there's no clipboard to talk to, e.g. over SSH.
+pub(crate) struct Clipboard {
+    /// Kept open because on X11 the copied text is only available while its owner is alive.
+    native: Option<arboard::Clipboard>,
+}
+
+impl Clipboard {
+    pub fn new() -> Self {
+        Clipboard { native: None }
+    }
+
+    /// Copies `text`, returning a short description of where it went for the status bar.
+    pub fn copy(&mut self, text: &str) -> Result<&'static str> {
+        if !over_ssh() {
+            if self.native.is_none() {
+                self.native = arboard::Clipboard::new().ok();
+            }
+            if let Some(native) = self.native.as_mut() {
+                if native.set_text(text).is_ok() {
+                    return Ok("Copied to the clipboard");
+                }
+            }
+        }
+        // Terminals that support OSC 52 put the text on the clipboard of the machine they run on
+        let mut stdout = stdout();
+        write!(stdout, "\x1b]52;c;{}\x07", base64(text.as_bytes()))?;
+        stdout.flush()?;
+        Ok("Copied through the terminal")
+    }
+}
+
+/// Whether the watch is running in an SSH session, where the local clipboard isn't the user's.
+fn over_ssh() -> bool {
+    std::env::var_os("SSH_CONNECTION").is_some() || std::env::var_os("SSH_TTY").is_some()
+}
+
+/// Standard, padded base64, as OSC 52 expects.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char),
+                false => encoded.push('='),
+            }
+        }
+    }
+    encoded
+}
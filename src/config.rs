@@ -0,0 +1,187 @@
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    io::{Error, ErrorKind, Result},
+    path::{Path, PathBuf},
+};
+
+use clap::ValueEnum;
+use serde::Deserialize;
+use watch_rs::{Differences, HighlightRule, KeyBinding, Keymap, Shell, Theme};
+
+use crate::{parse_interval, Args};
+
+/// Defaults for the command-line flags, read from a TOML file. Flags given on the command line
+/// take precedence over these.
+///
+/// ```toml
+/// interval = 2
+/// shell = "bash -c"
+/// precise = true
+/// beep = true
+/// color = true
+/// differences = "permanent"
+/// highlight = ["ERROR:red", "WARN:yellow"]
+/// theme = "light"
+///
+/// [styles]
+/// command = "bold blue"
+///
+/// [keys]
+/// j = "scroll-down"
+/// k = "scroll-up"
+/// "ctrl+d" = "page-down"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    interval: Option<f64>,
+    shell: Option<String>,
+    precise: Option<bool>,
+    beep: Option<bool>,
+    color: Option<bool>,
+    title: Option<bool>,
+    wrap: Option<bool>,
+    line_numbers: Option<bool>,
+    mouse: Option<bool>,
+    differences: Option<String>,
+    highlight: Vec<String>,
+    /// The built-in theme to start from, see [`Theme`]'s `FromStr`.
+    theme: Option<String>,
+    /// Styles to set over the theme's, by element, see [`Theme::set`].
+    styles: BTreeMap<String, String>,
+    /// Keys to bind, to the name of an action or "none", see [`Keymap::bind`].
+    keys: BTreeMap<String, String>,
+}
+
+impl Config {
+    /// Reads the config file at `path`, or the one in the platform's config directory if no path
+    /// is given. Only a missing file in the default location is allowed.
+    pub fn load(path: Option<&Path>) -> Result<Config> {
+        let (path, required) = match path {
+            Some(path) => (path.to_path_buf(), true),
+            None => match default_path() {
+                Some(path) => (path, false),
+                None => return Ok(Config::default()),
+            },
+        };
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == ErrorKind::NotFound && !required => {
+                return Ok(Config::default())
+            }
+            Err(err) => return Err(invalid(&path, err)),
+        };
+        let config: Config = toml::from_str(&contents).map_err(|err| invalid(&path, err))?;
+        config.validate().map_err(|err| invalid(&path, err))?;
+        Ok(config)
+    }
+
+    /// Checks the values that are parsed further when they're applied, so that errors point at the
+    /// file.
+    fn validate(&self) -> std::result::Result<(), String> {
+        if let Some(interval) = self.interval {
+            parse_interval(&interval.to_string()).map_err(|err| format!("interval {err}"))?;
+        }
+        if let Some(shell) = &self.shell {
+            shell
+                .parse::<Shell>()
+                .map_err(|err| format!("shell: {err}"))?;
+        }
+        if let Some(differences) = &self.differences {
+            Differences::from_str(differences, true)
+                .map_err(|err| format!("differences: {err}"))?;
+        }
+        for rule in &self.highlight {
+            rule.parse::<HighlightRule>()
+                .map_err(|err| format!("highlight: {err}"))?;
+        }
+        self.try_keymap().map_err(|err| format!("keys: {err}"))?;
+        self.try_theme(None)?;
+        Ok(())
+    }
+
+    /// The config's theme, or `theme` from the command line instead, with the config's styles
+    /// applied either way.
+    pub fn theme(&self, theme: Option<Theme>) -> Theme {
+        self.try_theme(theme).unwrap_or_default()
+    }
+
+    fn try_theme(&self, theme: Option<Theme>) -> std::result::Result<Theme, String> {
+        let mut theme = match (theme, &self.theme) {
+            (Some(theme), _) => theme,
+            (None, Some(name)) => name.parse().map_err(|err| format!("theme: {err}"))?,
+            (None, None) => Theme::default(),
+        };
+        for (element, style) in &self.styles {
+            theme
+                .set(element, style)
+                .map_err(|err| format!("styles: {err}"))?;
+        }
+        Ok(theme)
+    }
+
+    /// The default keymap with the config's bindings applied.
+    pub fn keymap(&self) -> Keymap {
+        self.try_keymap().unwrap_or_default()
+    }
+
+    fn try_keymap(&self) -> std::result::Result<Keymap, String> {
+        let mut keymap = Keymap::default();
+        for (key, action) in &self.keys {
+            keymap.bind(key.parse::<KeyBinding>()?, action)?;
+        }
+        Ok(keymap)
+    }
+
+    /// Fills in whatever `args` left unset from the config.
+    pub fn apply(self, args: &mut Args) {
+        args.interval = args.interval.or(self.interval);
+        if args.shell.is_none() {
+            args.shell = self.shell.and_then(|shell| shell.parse().ok());
+        }
+        args.precise |= self.precise.unwrap_or(false);
+        args.beep |= self.beep.unwrap_or(false);
+        if !args.color && !args.no_color {
+            args.no_color = self.color == Some(false);
+        }
+        args.no_title |= self.title == Some(false);
+        args.no_wrap |= self.wrap == Some(false);
+        args.line_numbers |= self.line_numbers.unwrap_or(false);
+        args.mouse |= self.mouse.unwrap_or(false);
+        if args.differences.is_none() {
+            args.differences = self
+                .differences
+                .and_then(|differences| Differences::from_str(&differences, true).ok());
+        }
+        // The command line's rules come last, so they're applied over the config's
+        let mut highlights: Vec<HighlightRule> = self
+            .highlight
+            .iter()
+            .filter_map(|rule| rule.parse().ok())
+            .collect();
+        highlights.append(&mut args.highlights);
+        args.highlights = highlights;
+    }
+}
+
+/// Where the config file lives when `--config` isn't given: `watch-rs/config.toml` under
+/// `$XDG_CONFIG_HOME` or `~/.config` on Unix, and under `%APPDATA%` on Windows.
+fn default_path() -> Option<PathBuf> {
+    let dir = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+    }?;
+    Some(dir.join("watch-rs").join("config.toml"))
+}
+
+fn invalid(path: &Path, err: impl std::fmt::Display) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("{}: {}", path.display(), err),
+    )
+}
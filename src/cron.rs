@@ -0,0 +1,409 @@
+use std::{fmt, str::FromStr, time::Instant};
+
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveDateTime, TimeDelta, TimeZone, Timelike};
+
+/// A crontab-style schedule, see [`Watcher::cron`](crate::Watcher::cron).
+///
+/// It's the five fields of a crontab line: minute (0-59), hour (0-23), day of the month (1-31),
+/// month (1-12 or `jan`-`dec`), and day of the week (0-7 or `sun`-`sat`, 0 and 7 both being
+/// Sunday). Each is a `*`, a value, a range like `9-17`, or a list of them like `1,15`, and any
+/// but a single value can take a step like `*/5`. As in cron, when both the day of the month and
+/// the day of the week are restricted, a day matching either one matches, while a field starting
+/// with `*` like `*/2` doesn't count as restricted. `@hourly`, `@daily`, `@weekly`, `@monthly`, and
+/// `@yearly` are short for the usual schedules.
+///
+/// # Examples
+///
+/// ```
+/// use watch_rs::Cron;
+///
+/// assert!("*/5 * * * *".parse::<Cron>().is_ok());
+/// assert!("0 9-17 * * mon-fri".parse::<Cron>().is_ok());
+/// assert!("@daily".parse::<Cron>().is_ok());
+/// assert!("60 * * * *".parse::<Cron>().is_err());
+/// assert!("0 0 30 feb *".parse::<Cron>().is_err());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cron {
+    source: String,
+    /// A bit for each value of each field that matches.
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Whether the day fields start with anything but `*`, which decides how they combine.
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+/// The values one of the fields can take, and what it's called in errors.
+struct Field {
+    name: &'static str,
+    min: u32,
+    max: u32,
+    /// Names for the values from `min` up, e.g. "jan" for 1.
+    names: &'static [&'static str],
+}
+
+const MINUTE: Field = Field {
+    name: "minute",
+    min: 0,
+    max: 59,
+    names: &[],
+};
+const HOUR: Field = Field {
+    name: "hour",
+    min: 0,
+    max: 23,
+    names: &[],
+};
+const DAY: Field = Field {
+    name: "day of the month",
+    min: 1,
+    max: 31,
+    names: &[],
+};
+const MONTH: Field = Field {
+    name: "month",
+    min: 1,
+    max: 12,
+    names: &[
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ],
+};
+const WEEKDAY: Field = Field {
+    name: "day of the week",
+    min: 0,
+    max: 7,
+    names: &["sun", "mon", "tue", "wed", "thu", "fri", "sat"],
+};
+
+/// How far ahead [`Cron::next_after`] looks, which covers every day of the week falling on every
+/// day of the year at least once, leap days included.
+const LOOKAHEAD_YEARS: i64 = 28;
+
+impl FromStr for Cron {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let expanded = match s.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            other => other,
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(format!(
+                "`{}` has {} fields, but a cron expression has 5: minute, hour, day of the month, month, and day of the week",
+                s.trim(),
+                fields.len()
+            ));
+        };
+        let mut weekday_bits = WEEKDAY.parse(weekdays)?;
+        // 7 is Sunday too
+        if weekday_bits & (1 << 7) != 0 {
+            weekday_bits |= 1;
+        }
+        let cron = Cron {
+            source: s.trim().to_string(),
+            minutes: MINUTE.parse(minutes)?,
+            hours: HOUR.parse(hours)?,
+            days: DAY.parse(days)?,
+            months: MONTH.parse(months)?,
+            weekdays: weekday_bits,
+            days_restricted: !days.starts_with('*'),
+            weekdays_restricted: !weekdays.starts_with('*'),
+        };
+        match cron.next_after(Local::now()) {
+            Some(_) => Ok(cron),
+            None => Err(format!("`{}` never matches a date", s.trim())),
+        }
+    }
+}
+
+impl Field {
+    /// The bits of the values `field` matches.
+    fn parse(&self, field: &str) -> Result<u64, String> {
+        let invalid = || format!("`{}` isn't a valid {} field", field, self.name);
+        let mut bits = 0;
+        for part in field.split(',') {
+            let (range, step) = match part.split_once('/') {
+                Some((range, step)) => {
+                    let step: u32 = step.parse().map_err(|_| invalid())?;
+                    if step == 0 {
+                        return Err(invalid());
+                    }
+                    (range, Some(step))
+                }
+                None => (part, None),
+            };
+            let (start, end) = match range.split_once('-') {
+                _ if range == "*" => (self.min, self.max),
+                Some((start, end)) => (self.value(start)?, self.value(end)?),
+                // A step from a single value runs to the end, like "5/15"
+                None if step.is_some() => (self.value(range)?, self.max),
+                None => {
+                    let value = self.value(range)?;
+                    (value, value)
+                }
+            };
+            if start > end {
+                return Err(invalid());
+            }
+            for value in (start..=end).step_by(step.unwrap_or(1) as usize) {
+                bits |= 1 << value;
+            }
+        }
+        Ok(bits)
+    }
+
+    /// A single value, as a number or a name.
+    fn value(&self, value: &str) -> Result<u32, String> {
+        let named = self
+            .names
+            .iter()
+            .position(|name| name.eq_ignore_ascii_case(value))
+            .map(|i| self.min + i as u32);
+        match named.or_else(|| value.parse().ok()) {
+            Some(value) if (self.min..=self.max).contains(&value) => Ok(value),
+            _ => Err(format!(
+                "`{}` isn't a {} between {} and {}",
+                value, self.name, self.min, self.max
+            )),
+        }
+    }
+}
+
+impl Cron {
+    /// The first minute after `time` that the schedule matches, or `None` if there isn't one in
+    /// the years to come. Times that a change to daylight saving time skips are skipped.
+    pub(crate) fn next_after<Tz: TimeZone>(&self, time: DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let start = time.naive_local().with_second(0)?.with_nanosecond(0)? + TimeDelta::minutes(1);
+        let end = start + TimeDelta::days(LOOKAHEAD_YEARS * 366);
+        let mut next = start;
+        while next < end {
+            let date = next.date();
+            next = if !has(self.months, date.month()) {
+                let (year, month) = match date.month() {
+                    12 => (date.year() + 1, 1),
+                    month => (date.year(), month + 1),
+                };
+                midnight(NaiveDate::from_ymd_opt(year, month, 1)?)
+            } else if !self.matches_day(date) {
+                midnight(date.succ_opt()?)
+            } else if !has(self.hours, next.hour()) {
+                next.with_minute(0)? + TimeDelta::hours(1)
+            } else if !has(self.minutes, next.minute()) {
+                next + TimeDelta::minutes(1)
+            } else if let Some(time) = time.timezone().from_local_datetime(&next).earliest() {
+                return Some(time);
+            } else {
+                next + TimeDelta::minutes(1)
+            };
+        }
+        None
+    }
+
+    /// When the schedule next matches, as an [`Instant`], or `None` if it never does again.
+    pub(crate) fn next_instant(&self) -> Option<Instant> {
+        let now = Local::now();
+        let wait = (self.next_after(now)? - now).to_std().unwrap_or_default();
+        Some(Instant::now() + wait)
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        let day = has(self.days, date.day());
+        let weekday = has(self.weekdays, date.weekday().num_days_from_sunday());
+        match self.days_restricted && self.weekdays_restricted {
+            true => day || weekday,
+            false => day && weekday,
+        }
+    }
+}
+
+impl fmt::Display for Cron {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+fn has(bits: u64, value: u32) -> bool {
+    bits & (1 << value) != 0
+}
+
+fn midnight(date: NaiveDate) -> NaiveDateTime {
+    date.and_hms_opt(0, 0, 0).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{
+        FixedOffset, MappedLocalTime, NaiveDate, NaiveDateTime, TimeDelta, TimeZone, Utc,
+    };
+
+    use super::Cron;
+
+    /// When `cron` next matches after `time`, both like "2026-10-14 12:00 Wed", in UTC.
+    fn next(cron: &str, time: &str) -> String {
+        let cron: Cron = cron.parse().unwrap();
+        let time = NaiveDateTime::parse_from_str(&time[..16], "%Y-%m-%d %H:%M").unwrap();
+        let next = cron.next_after(Utc.from_utc_datetime(&time)).unwrap();
+        next.format("%Y-%m-%d %H:%M %a").to_string()
+    }
+
+    #[test]
+    fn either_day_field_matches_when_both_are_restricted() {
+        assert_eq!(
+            next("0 0 15 * fri", "2026-10-14 12:00 Wed"),
+            "2026-10-15 00:00 Thu"
+        );
+        assert_eq!(
+            next("0 0 15 * fri", "2026-10-15 00:00 Thu"),
+            "2026-10-16 00:00 Fri"
+        );
+    }
+
+    #[test]
+    fn both_day_fields_match_when_one_is_a_star() {
+        assert_eq!(
+            next("0 0 * * fri", "2026-10-14 12:00 Wed"),
+            "2026-10-16 00:00 Fri"
+        );
+        assert_eq!(
+            next("0 0 15 * *", "2026-10-16 12:00 Fri"),
+            "2026-11-15 00:00 Sun"
+        );
+        // The 16th is a Friday but an even day
+        assert_eq!(
+            next("0 0 */2 * fri", "2026-10-14 12:00 Wed"),
+            "2026-10-23 00:00 Fri"
+        );
+        assert_eq!(
+            next("0 0 14 * */2", "2026-10-14 12:00 Wed"),
+            "2026-11-14 00:00 Sat"
+        );
+    }
+
+    #[test]
+    fn seven_is_sunday() {
+        assert_eq!(
+            next("0 0 * * 7", "2026-10-14 12:00 Wed"),
+            "2026-10-18 00:00 Sun"
+        );
+        assert_eq!(
+            next("0 0 * * 5-7", "2026-10-17 12:00 Sat"),
+            "2026-10-18 00:00 Sun"
+        );
+        assert_eq!(
+            next("0 0 * * 0", "2026-10-14 12:00 Wed"),
+            "2026-10-18 00:00 Sun"
+        );
+    }
+
+    #[test]
+    fn december_rolls_over_into_the_next_year() {
+        assert_eq!(
+            next("0 0 1 jan *", "2026-12-15 12:00 Tue"),
+            "2027-01-01 00:00 Fri"
+        );
+        assert_eq!(
+            next("59 23 31 12 *", "2026-12-31 23:59 Thu"),
+            "2027-12-31 23:59 Fri"
+        );
+        assert_eq!(
+            next("*/30 * * * *", "2026-12-31 23:45 Thu"),
+            "2027-01-01 00:00 Fri"
+        );
+    }
+
+    #[test]
+    fn february_29th_waits_for_a_leap_year() {
+        assert_eq!(
+            next("0 0 29 2 *", "2026-10-14 12:00 Wed"),
+            "2028-02-29 00:00 Tue"
+        );
+        assert_eq!(
+            next("0 0 29 2 *", "2028-02-29 00:00 Tue"),
+            "2032-02-29 00:00 Sun"
+        );
+    }
+
+    /// New York's time zone around the start of daylight saving time in 2026, when 2:00 on March
+    /// 8th went straight to 3:00.
+    #[derive(Clone, Copy, Debug)]
+    struct SpringForward;
+
+    impl SpringForward {
+        const STANDARD: i32 = -5 * 3600;
+        const SUMMER: i32 = -4 * 3600;
+
+        /// When the clocks went forward, in local standard time.
+        fn switch() -> NaiveDateTime {
+            NaiveDate::from_ymd_opt(2026, 3, 8)
+                .unwrap()
+                .and_hms_opt(2, 0, 0)
+                .unwrap()
+        }
+
+        fn offset(seconds: i32) -> FixedOffset {
+            FixedOffset::east_opt(seconds).unwrap()
+        }
+    }
+
+    impl TimeZone for SpringForward {
+        type Offset = FixedOffset;
+
+        fn from_offset(_: &FixedOffset) -> Self {
+            SpringForward
+        }
+
+        fn offset_from_local_date(&self, local: &NaiveDate) -> MappedLocalTime<FixedOffset> {
+            self.offset_from_local_datetime(&local.and_hms_opt(0, 0, 0).unwrap())
+        }
+
+        fn offset_from_local_datetime(
+            &self,
+            local: &NaiveDateTime,
+        ) -> MappedLocalTime<FixedOffset> {
+            let switch = SpringForward::switch();
+            if *local < switch {
+                MappedLocalTime::Single(SpringForward::offset(SpringForward::STANDARD))
+            } else if *local < switch + TimeDelta::hours(1) {
+                MappedLocalTime::None
+            } else {
+                MappedLocalTime::Single(SpringForward::offset(SpringForward::SUMMER))
+            }
+        }
+
+        fn offset_from_utc_date(&self, utc: &NaiveDate) -> FixedOffset {
+            self.offset_from_utc_datetime(&utc.and_hms_opt(0, 0, 0).unwrap())
+        }
+
+        fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> FixedOffset {
+            let switch =
+                SpringForward::switch() - TimeDelta::seconds(SpringForward::STANDARD.into());
+            match *utc < switch {
+                true => SpringForward::offset(SpringForward::STANDARD),
+                false => SpringForward::offset(SpringForward::SUMMER),
+            }
+        }
+    }
+
+    #[test]
+    fn minutes_skipped_by_daylight_saving_time_are_skipped() {
+        let cron: Cron = "30 2 * * *".parse().unwrap();
+        let time = SpringForward
+            .with_ymd_and_hms(2026, 3, 7, 12, 0, 0)
+            .unwrap();
+        let next = cron.next_after(time).unwrap();
+        // 2:00 went straight to 3:00 on the 8th
+        assert_eq!(
+            next.format("%Y-%m-%d %H:%M %z").to_string(),
+            "2026-03-09 02:30 -0400"
+        );
+    }
+}
@@ -0,0 +1,73 @@
+/// The character encoding of the watched command's output.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Encoding {
+    /// UTF-8; invalid sequences are shown as U+FFFD.
+    #[default]
+    #[value(alias = "utf-8")]
+    Utf8,
+    /// ISO-8859-1, where every byte maps to the code point of the same value.
+    #[value(alias = "iso-8859-1")]
+    Latin1,
+    /// The Western European Windows codepage.
+    #[value(alias = "cp1252")]
+    Windows1252,
+    /// The original IBM PC / DOS console codepage.
+    #[value(alias = "ibm437")]
+    Cp437,
+    /// Little-endian UTF-16, as written by some Windows tools.
+    #[value(alias = "utf-16le")]
+    Utf16le,
+}
+
+/// Windows-1252 differs from Latin-1 only in 0x80..=0x9F. Unassigned bytes map to U+FFFD.
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '€', '\u{FFFD}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{FFFD}', 'Ž',
+    '\u{FFFD}', '\u{FFFD}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{FFFD}',
+    'ž', 'Ÿ',
+];
+
+/// Code page 437 for bytes 0x80..=0xFF.
+const CP437_HIGH: &str = "ÇüéâäàåçêëèïîìÄÅÉæÆôöòûùÿÖÜ¢£¥₧ƒáíóúñÑªº¿⌐¬½¼¡«»\
+░▒▓│┤╡╢╖╕╣║╗╝╜╛┐└┴┬├─┼╞╟╚╔╩╦╠═╬╧╨╤╥╙╘╒╓╫╪┘┌█▄▌▐▀\
+αßΓπΣσµτΦΘΩδ∞φε∩≡±≥≤⌠⌡÷≈°∙·√ⁿ²■\u{A0}";
+
+impl Encoding {
+    /// Decodes `bytes` without ever failing; anything that can't be represented becomes U+FFFD.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use watch_rs::Encoding;
+    ///
+    /// assert_eq!(Encoding::Utf8.decode(b"ok \xff"), "ok \u{FFFD}");
+    /// assert_eq!(Encoding::Latin1.decode(b"caf\xe9"), "café");
+    /// ```
+    pub fn decode(self, bytes: &[u8]) -> String {
+        match self {
+            Encoding::Utf8 => String::from_utf8_lossy(bytes).into_owned(),
+            Encoding::Latin1 => bytes.iter().map(|&b| b as char).collect(),
+            Encoding::Windows1252 => bytes
+                .iter()
+                .map(|&b| match b {
+                    0x80..=0x9F => WINDOWS_1252_HIGH[(b - 0x80) as usize],
+                    _ => b as char,
+                })
+                .collect(),
+            Encoding::Cp437 => bytes
+                .iter()
+                .map(|&b| match b {
+                    0x80..=0xFF => CP437_HIGH.chars().nth((b - 0x80) as usize).unwrap(),
+                    _ => b as char,
+                })
+                .collect(),
+            Encoding::Utf16le => {
+                let units = bytes
+                    .chunks(2)
+                    .map(|pair| u16::from_le_bytes([pair[0], *pair.get(1).unwrap_or(&0)]));
+                char::decode_utf16(units)
+                    .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+                    .collect()
+            }
+        }
+    }
+}
@@ -0,0 +1,269 @@
+use std::collections::HashSet;
+
+use crossterm::style::{ContentStyle, Stylize};
+
+use crate::theme::{self, Theme};
+use crate::viewport::{push_char, Line, Span};
+use crate::width::{text_width, truncate};
+
+/// How changes between successive executions are highlighted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Differences {
+    /// Highlight only what changed since the previous execution.
+    Transient,
+    /// Keep highlighting everything that has changed at least once since the first execution.
+    Permanent,
+}
+
+/// Compares each execution's output cell-by-cell against the previous one.
+pub(crate) struct DiffHighlighter {
+    mode: Differences,
+    /// Layered over the style of changed cells.
+    changed_style: ContentStyle,
+    previous: Option<Vec<Vec<char>>>,
+    /// Every (line, column) cell that has changed so far, used by [`Differences::Permanent`].
+    changed_cells: HashSet<(usize, usize)>,
+}
+
+impl DiffHighlighter {
+    pub fn new(mode: Differences, changed_style: ContentStyle) -> Self {
+        DiffHighlighter {
+            mode,
+            changed_style,
+            previous: None,
+            changed_cells: HashSet::new(),
+        }
+    }
+
+    /// Marks the cells of `lines` that differ from the previous call in the changed style, and
+    /// returns them with a marker for each line, see [`Gutter::marker`](crate::viewport::Gutter).
+    /// The first call never marks anything as changed.
+    pub fn highlight(&mut self, lines: Vec<Line>) -> (Vec<Line>, Vec<Option<char>>) {
+        let current: Vec<Vec<char>> = lines
+            .iter()
+            .map(|line| line.iter().flat_map(|span| span.text.chars()).collect())
+            .collect();
+
+        let mut highlighted = Vec::with_capacity(lines.len());
+        let mut markers = Vec::with_capacity(lines.len());
+        for (row, line) in lines.into_iter().enumerate() {
+            let previous_line = self.previous.as_ref().map(|prev| prev.get(row));
+            let mut new_line = Line::new();
+            let mut line_changed = false;
+            let mut col = 0;
+            for span in line {
+                for c in span.text.chars() {
+                    let mut changed = match previous_line {
+                        None => false,
+                        Some(None) => true,
+                        Some(Some(prev)) => prev.get(col) != Some(&c),
+                    };
+                    if self.mode == Differences::Permanent {
+                        if changed {
+                            self.changed_cells.insert((row, col));
+                        }
+                        changed |= self.changed_cells.contains(&(row, col));
+                    }
+
+                    let mut style = span.style;
+                    if changed {
+                        style = theme::overlay(style, self.changed_style);
+                        line_changed = true;
+                    }
+                    push_char(&mut new_line, style, c);
+                    col += 1;
+                }
+            }
+            let shortened = previous_line.flatten().is_some_and(|prev| prev.len() > col);
+            markers.push(match previous_line {
+                Some(None) => Some('+'),
+                _ if line_changed || shortened => Some('~'),
+                _ => None,
+            });
+            highlighted.push(new_line);
+        }
+
+        // Lines can't be shown once they're gone, so mark where they used to follow
+        let removed = self
+            .previous
+            .as_ref()
+            .is_some_and(|prev| prev.len() > current.len());
+        if let Some(marker) = markers.last_mut().filter(|_| removed) {
+            *marker = Some('-');
+        }
+
+        self.previous = Some(current);
+        (highlighted, markers)
+    }
+}
+
+/// One row of the side-by-side view: a line of the previous output next to a line of the current
+/// one. Either side is `None` where a line was added or removed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct SplitRow {
+    pub left: Option<Line>,
+    pub right: Option<Line>,
+    pub changed: bool,
+}
+
+impl SplitRow {
+    /// A row that never counts as changed, e.g. column headings.
+    pub fn heading(left: Line, right: Line) -> Self {
+        SplitRow {
+            left: Some(left),
+            right: Some(right),
+            changed: false,
+        }
+    }
+
+    /// Lays the row out in `width` columns: each side is truncated or padded to half the width,
+    /// with a separator between them that's highlighted when the row changed.
+    pub fn render(&self, width: usize, theme: &Theme) -> Line {
+        let column = width.saturating_sub(SEPARATOR_WIDTH) / 2;
+        let mut line = Line::new();
+        let side_style = |style: ContentStyle| match self.changed {
+            true => style,
+            false => ContentStyle::new(),
+        };
+        push_column(
+            &mut line,
+            self.left.as_ref(),
+            column,
+            side_style(theme.diff_removed),
+        );
+        line.push(if self.changed {
+            Span::new(theme.diff_separator, " ≠ ")
+        } else {
+            Span::new(ContentStyle::new().dark_grey(), " │ ")
+        });
+        push_column(
+            &mut line,
+            self.right.as_ref(),
+            column,
+            side_style(theme.diff_added),
+        );
+        line
+    }
+}
+
+/// Width of the separator between the two columns of a [`SplitRow`].
+const SEPARATOR_WIDTH: usize = 3;
+
+/// Aligning costs `previous.len() * current.len()`; past this, lines are paired up by position.
+const MAX_ALIGN_CELLS: usize = 4_000_000;
+
+/// How a line of one output lines up with the other, by their indexes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Aligned {
+    Same(usize, usize),
+    /// The line was edited in place.
+    Edited(usize, usize),
+    /// The line is only in the older output.
+    Removed(usize),
+    /// The line is only in the newer output.
+    Added(usize),
+}
+
+/// Lines up two outputs, matching up unchanged lines so that an insertion or removal only marks
+/// the lines actually added or removed. Each line of each output appears once, in order.
+pub(crate) fn align<T: PartialEq>(old: &[T], new: &[T]) -> Vec<Aligned> {
+    if old.len().saturating_mul(new.len()) > MAX_ALIGN_CELLS {
+        return (0..old.len().max(new.len()))
+            .map(|row| match (row < old.len(), row < new.len()) {
+                (true, true) if old[row] == new[row] => Aligned::Same(row, row),
+                (true, true) => Aligned::Edited(row, row),
+                (true, false) => Aligned::Removed(row),
+                _ => Aligned::Added(row),
+            })
+            .collect();
+    }
+
+    // Longest common subsequence of lines, filled in from the end so it can be walked forwards
+    let mut lcs = vec![vec![0u32; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut aligned = Vec::with_capacity(old.len().max(new.len()));
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            aligned.push(Aligned::Same(i, j));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && j < new.len() && lcs[i + 1][j] == lcs[i][j + 1] {
+            // Neither side is part of the common subsequence here: the line was edited in place
+            aligned.push(Aligned::Edited(i, j));
+            i += 1;
+            j += 1;
+        } else if j == new.len() || (i < old.len() && lcs[i + 1][j] > lcs[i][j + 1]) {
+            aligned.push(Aligned::Removed(i));
+            i += 1;
+        } else {
+            aligned.push(Aligned::Added(j));
+            j += 1;
+        }
+    }
+    aligned
+}
+
+/// Pairs up the lines of two outputs for the side-by-side view, see [`align`].
+pub(crate) fn side_by_side(previous: Vec<Line>, current: Vec<Line>) -> Vec<SplitRow> {
+    let text = |lines: &[Line]| -> Vec<String> {
+        lines
+            .iter()
+            .map(|line| line.iter().map(|span| span.text.as_str()).collect())
+            .collect()
+    };
+    let aligned = align(&text(&previous), &text(&current));
+
+    // Every line comes up in order, so each side can just be taken from the front
+    let mut previous = previous.into_iter();
+    let mut current = current.into_iter();
+    aligned
+        .into_iter()
+        .map(|aligned| match aligned {
+            Aligned::Same(..) | Aligned::Edited(..) => SplitRow {
+                left: previous.next(),
+                right: current.next(),
+                changed: matches!(aligned, Aligned::Edited(..)),
+            },
+            Aligned::Removed(_) => SplitRow {
+                left: previous.next(),
+                right: None,
+                changed: true,
+            },
+            Aligned::Added(_) => SplitRow {
+                left: None,
+                right: current.next(),
+                changed: true,
+            },
+        })
+        .collect()
+}
+
+/// Appends `side` to `line`, cut or padded to exactly `width` columns. `changed` is layered over
+/// the line's own styles.
+fn push_column(line: &mut Line, side: Option<&Line>, width: usize, changed: ContentStyle) {
+    let mut used = 0;
+    for span in side.into_iter().flatten() {
+        let style = theme::overlay(span.style, changed);
+        let text = truncate(&span.text, width - used);
+        for c in text.chars() {
+            push_char(line, style, c);
+        }
+        used += text_width(text);
+        if text.len() < span.text.len() {
+            break;
+        }
+    }
+    if used < width {
+        line.push(Span::new(ContentStyle::new(), " ".repeat(width - used)));
+    }
+}
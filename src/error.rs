@@ -0,0 +1,103 @@
+use std::{error::Error, fmt, io};
+
+use crate::exec::Status;
+use crate::outcome::status_code;
+use crate::watcher::describe_status;
+
+/// What went wrong while watching, as returned by [`Watcher::run`](crate::Watcher::run) and the
+/// rest of the public API.
+///
+/// # Examples
+///
+/// ```
+/// use watch_rs::{WatchError, Watcher};
+///
+/// let mut runs = Watcher::new("no-such-program").exec(true).watch_iter();
+/// match runs.next() {
+///     Some(Err(WatchError::Spawn { program, .. })) => assert_eq!(program, "no-such-program"),
+///     other => panic!("expected a spawn failure, got {:?}", other),
+/// }
+/// ```
+#[derive(Debug)]
+pub enum WatchError {
+    /// The command couldn't be started, e.g. because the program (or the shell running it) wasn't
+    /// found or isn't executable.
+    Spawn { program: String, source: io::Error },
+    /// Something read back in isn't in the expected format, e.g. a recording given to
+    /// [`replay`](crate::replay).
+    Decode(String),
+    /// Setting up the terminal, reading input from it, or drawing on it failed.
+    Terminal(io::Error),
+    /// The command exited unsuccessfully, with [`Watcher::errexit`](crate::Watcher::errexit).
+    Command { status: Status, stderr: String },
+    /// Any other I/O failure, e.g. writing the log file or watching paths.
+    Io(io::Error),
+}
+
+impl WatchError {
+    /// The exit code for a process reporting this error, as `watchr` uses: the command's own exit
+    /// code for [`WatchError::Command`], like [`Outcome::exit_code`](crate::Outcome::exit_code),
+    /// and 1 otherwise.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            WatchError::Command { status, .. } => status_code(*status),
+            _ => 1,
+        }
+    }
+
+    /// Whether this is an I/O error of `kind`, e.g. [`io::ErrorKind::Interrupted`].
+    pub(crate) fn is_kind(&self, kind: io::ErrorKind) -> bool {
+        match self {
+            WatchError::Spawn { source: err, .. }
+            | WatchError::Terminal(err)
+            | WatchError::Io(err) => err.kind() == kind,
+            WatchError::Decode(_) | WatchError::Command { .. } => false,
+        }
+    }
+}
+
+impl fmt::Display for WatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WatchError::Spawn { program, source } => {
+                write!(f, "couldn't run `{program}`: {source}")
+            }
+            WatchError::Decode(message) => f.write_str(message),
+            WatchError::Terminal(err) => write!(f, "terminal: {err}"),
+            WatchError::Command { status, .. } => {
+                write!(f, "the command failed ({})", describe_status(*status))
+            }
+            WatchError::Io(err) => err.fmt(f),
+        }
+    }
+}
+
+impl Error for WatchError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            WatchError::Spawn { source: err, .. }
+            | WatchError::Terminal(err)
+            | WatchError::Io(err) => Some(err),
+            WatchError::Decode(_) | WatchError::Command { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for WatchError {
+    fn from(err: io::Error) -> Self {
+        WatchError::Io(err)
+    }
+}
+
+/// For callers working in [`io::Result`]: I/O failures come back as they were, and the rest
+/// wrapped in an [`io::Error`] of a fitting kind.
+impl From<WatchError> for io::Error {
+    fn from(err: WatchError) -> Self {
+        match err {
+            WatchError::Terminal(err) | WatchError::Io(err) => err,
+            WatchError::Spawn { ref source, .. } => io::Error::new(source.kind(), err),
+            WatchError::Decode(_) => io::Error::new(io::ErrorKind::InvalidData, err),
+            WatchError::Command { .. } => io::Error::other(err),
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use std::{
+    ops::ControlFlow,
+    sync::mpsc::Sender,
+    time::{Duration, SystemTime},
+};
+
+use crate::diff::{align, Aligned};
+use crate::exec::ExecutionResult;
+use crate::hooks::Hook;
+
+/// A line of stdout that changed from one run to a later one, see
+/// [`Watcher::subscribe`](crate::Watcher::subscribe). Lines are numbered from 1.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ChangeEvent {
+    /// A line that's new, at `line` of the newer output.
+    Added { line: usize, text: String },
+    /// A line that's gone, from `line` of the older output.
+    Removed { line: usize, text: String },
+    /// A line edited in place, at `line` of the newer output.
+    Modified {
+        line: usize,
+        old: String,
+        new: String,
+    },
+}
+
+/// The changes that turn `old` into `new`, line by line.
+fn changes(old: &str, new: &str) -> Vec<ChangeEvent> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    align(&old, &new)
+        .into_iter()
+        .filter_map(|aligned| match aligned {
+            Aligned::Same(..) => None,
+            Aligned::Edited(i, j) => Some(ChangeEvent::Modified {
+                line: j + 1,
+                old: old[i].to_string(),
+                new: new[j].to_string(),
+            }),
+            Aligned::Removed(i) => Some(ChangeEvent::Removed {
+                line: i + 1,
+                text: old[i].to_string(),
+            }),
+            Aligned::Added(j) => Some(ChangeEvent::Added {
+                line: j + 1,
+                text: new[j].to_string(),
+            }),
+        })
+        .collect()
+}
+
+/// Sends the lines that changed as the output changes, see [`change_events`].
+struct ChangeEvents {
+    events: Sender<ChangeEvent>,
+    debounce: Option<Duration>,
+    /// The output the events so far lead to, and an output waiting out the debounce since a time
+    settled: Option<String>,
+    pending: Option<(String, SystemTime)>,
+}
+
+impl ChangeEvents {
+    fn update(&mut self, result: &ExecutionResult) {
+        let Some(old) = &self.settled else {
+            self.settled = Some(result.stdout.clone());
+            return;
+        };
+        if *old == result.stdout {
+            self.pending = None;
+            return;
+        }
+        if let Some(debounce) = self.debounce {
+            let since = match &self.pending {
+                Some((output, since)) if *output == result.stdout => *since,
+                _ => {
+                    self.pending = Some((result.stdout.clone(), result.started_at));
+                    result.started_at
+                }
+            };
+            let kept = result.started_at.duration_since(since).unwrap_or_default();
+            if kept < debounce {
+                return;
+            }
+        }
+        self.send(result.stdout.clone());
+    }
+
+    /// Sends the changes from the settled output to `output`, which becomes the settled one.
+    fn send(&mut self, output: String) {
+        if let Some(old) = &self.settled {
+            for event in changes(old, &output) {
+                // Nobody's listening anymore, which doesn't stop the watch
+                let _ = self.events.send(event);
+            }
+        }
+        self.settled = Some(output);
+        self.pending = None;
+    }
+}
+
+impl Drop for ChangeEvents {
+    /// Sends an output still waiting out the debounce once the watch is over, since it's the one
+    /// the watch ended on.
+    fn drop(&mut self) {
+        if let Some((output, _)) = self.pending.take() {
+            self.send(output);
+        }
+    }
+}
+
+/// A hook sending the lines that changed to `events` when the output changes. With a `debounce`,
+/// a new output is only sent once later runs have kept it for that long, compared to the last
+/// output sent, so output that flaps back within it sends nothing. An output still waiting out the
+/// debounce is sent when the hook is dropped with the watch.
+pub(crate) fn change_events(events: Sender<ChangeEvent>, debounce: Option<Duration>) -> Hook {
+    let mut changes = ChangeEvents {
+        events,
+        debounce,
+        settled: None,
+        pending: None,
+    };
+    Box::new(move |result| {
+        changes.update(result);
+        ControlFlow::Continue(())
+    })
+}
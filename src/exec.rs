@@ -0,0 +1,932 @@
+use std::{
+    collections::VecDeque,
+    io::{Error, ErrorKind, Read, Result, Write},
+    process::{Child, ChildStdin, Command, ExitStatus, Stdio},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::error::WatchError;
+use crate::signal;
+use crate::truncation::{Capture, OutputCap, Truncation};
+use crate::watcher::describe_status;
+
+/// How often a running command is checked for completion.
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How a single execution of the command ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    Exited(ExitStatus),
+    /// The command was killed after running for longer than the timeout.
+    TimedOut(Duration),
+    /// The command couldn't be reached where it runs, e.g. because the connection to the
+    /// [`Watcher::ssh`](crate::Watcher::ssh) host failed or the
+    /// [`Watcher::container`](crate::Watcher::container) isn't running. It's retried like any failure, but
+    /// doesn't end the watch with [`Watcher::errexit`](crate::Watcher::errexit).
+    Unreachable(ExitStatus),
+}
+
+impl Status {
+    /// Whether the command exited with a zero status.
+    pub fn success(&self) -> bool {
+        matches!(self, Status::Exited(status) if status.success())
+    }
+
+    /// The command's exit code, if it exited normally.
+    pub fn code(&self) -> Option<i32> {
+        match self {
+            Status::Exited(status) | Status::Unreachable(status) => status.code(),
+            Status::TimedOut(_) => None,
+        }
+    }
+}
+
+/// Which of the command's output pipes something was printed to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pipe {
+    Stdout,
+    Stderr,
+}
+
+/// The captured result of running the command once.
+pub(crate) struct Output {
+    pub status: Status,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    /// Which pipe each read of the output came from and how many bytes it got, in the order they
+    /// arrived. Empty if the order wasn't recorded.
+    pub chunks: Vec<(Pipe, usize)>,
+    pub started_at: SystemTime,
+    pub duration: Duration,
+}
+
+impl Output {
+    /// Puts `heading` above this step's output, when it's one of several, saying how the step
+    /// ended if it ran in `parallel` with others, see [`Watcher::foreach`](crate::Watcher::foreach).
+    pub fn headed(mut self, heading: Option<&str>, parallel: bool) -> Output {
+        if let Some(heading) = heading {
+            let heading = match parallel {
+                true => section_heading(&format!("{} ({})", heading, item_status(self.status))),
+                false => section_heading(heading),
+            };
+            self.stdout.splice(0..0, heading.bytes());
+            self.chunks.insert(0, (Pipe::Stdout, heading.len()));
+        }
+        self
+    }
+
+    /// Adds the output of the next step of the same execution after this one's, with a blank line
+    /// between them. The execution failed if either step did, and took as long as both together.
+    pub fn then(mut self, next: Output) -> Output {
+        let separator = append_section(&mut self.stdout, &next.stdout);
+        if separator > 0 {
+            self.chunks.push((Pipe::Stdout, separator));
+        }
+        self.stderr.extend_from_slice(&next.stderr);
+        self.chunks.extend_from_slice(&next.chunks);
+        if self.status.success() {
+            self.status = next.status;
+        }
+        self.duration += next.duration;
+        self
+    }
+
+    /// Adds the output of a step that ran at the same time as this one after this one's, like
+    /// [`Output::then`], except that together they took only as long as the slower one.
+    pub fn alongside(self, next: Output) -> Output {
+        let duration = self.duration.max(next.duration);
+        let mut output = self.then(next);
+        output.duration = duration;
+        output
+    }
+
+    /// Accounts for stdout having been piped through the filter `command_line`, which ended with
+    /// `status`, or was killed if it's `None`. A failing filter is noted at the end of stderr,
+    /// apart from the command's own status, and the command stopping because the filter stopped
+    /// reading, like `head` does, isn't it failing.
+    pub fn piped(mut self, command_line: &str, status: Option<ExitStatus>) -> Output {
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+            // Either killed by SIGPIPE, or a shell saying that what it ran was
+            if let Status::Exited(exited) = self.status {
+                let broken_pipe = exited.signal() == Some(libc::SIGPIPE)
+                    || exited.code() == Some(128 + libc::SIGPIPE);
+                if broken_pipe {
+                    self.status = Status::Exited(ExitStatus::default());
+                }
+            }
+        }
+        if let Some(status) = status.filter(|status| !status.success()) {
+            let mut failure = format!(
+                "pipe `{}` failed with {}",
+                command_line,
+                describe_status(Status::Exited(status))
+            );
+            if !self.stderr.is_empty() && !self.stderr.ends_with(b"\n") {
+                failure.insert(0, '\n');
+            }
+            self.stderr.extend_from_slice(failure.as_bytes());
+            self.chunks.push((Pipe::Stderr, failure.len()));
+        }
+        self
+    }
+
+    /// Pipes stdout through `filter` once it's all there, for output a
+    /// [`CommandRunner`](crate::CommandRunner) produced rather than a spawned command.
+    pub fn filter_through(mut self, filter: Filter) -> std::result::Result<Output, WatchError> {
+        let mut process = Process::spawn(filter.command, None, None, None, true)?;
+        process.write_stdin(&self.stdout)?;
+        // Closing its stdin lets the filter see the end of the output
+        process.stdin = None;
+        let status = loop {
+            if let Some(status) = process.try_finish()? {
+                break status;
+            }
+            if signal::cancelled() {
+                return Err(Error::from(ErrorKind::Interrupted).into());
+            }
+            thread::sleep(WAIT_POLL_INTERVAL);
+        };
+        let filtered = process.finish(status);
+        self.stdout = filtered.stdout;
+        self.stderr.extend_from_slice(&filtered.stderr);
+        let status = match filtered.status {
+            Status::Exited(status) => Some(status),
+            _ => None,
+        };
+        Ok(self.piped(&filter.command_line, status))
+    }
+
+    /// Keeps each of the pipes within `cap`, for output that wasn't capped as it was read, like
+    /// what a [`CommandRunner`](crate::CommandRunner) returns.
+    pub fn capped(mut self, cap: Option<OutputCap>) -> Output {
+        let Some(cap) = cap else {
+            return self;
+        };
+        let mut stdout = Capture::default();
+        let mut stderr = Capture::default();
+        stdout.push(&std::mem::take(&mut self.stdout), Some(cap));
+        stderr.push(&std::mem::take(&mut self.stderr), Some(cap));
+        stdout.finish(Some(cap));
+        stderr.finish(Some(cap));
+        self.stdout = stdout.bytes;
+        self.stderr = stderr.bytes;
+        self.truncated(Pipe::Stdout, stdout.dropped, cap.truncation)
+            .truncated(Pipe::Stderr, stderr.dropped, cap.truncation)
+    }
+
+    /// Notes where `dropped` bytes of `pipe` were left out to keep within the
+    /// [`Watcher::max_output`](crate::Watcher::max_output): at the end when keeping the start of
+    /// the output, and at the start when keeping the end.
+    pub fn truncated(mut self, pipe: Pipe, dropped: usize, truncation: Truncation) -> Output {
+        if dropped == 0 {
+            return self;
+        }
+        let output = match pipe {
+            Pipe::Stdout => &mut self.stdout,
+            Pipe::Stderr => &mut self.stderr,
+        };
+        let mut marker = format!("[output truncated ({} bytes dropped)]", dropped);
+        match truncation {
+            Truncation::Head => {
+                if !output.is_empty() && !output.ends_with(b"\n") {
+                    marker.insert(0, '\n');
+                }
+                output.extend_from_slice(marker.as_bytes());
+                self.chunks.push((pipe, marker.len()));
+            }
+            Truncation::Tail => {
+                marker.push('\n');
+                output.splice(0..0, marker.bytes());
+                self.chunks.insert(0, (pipe, marker.len()));
+            }
+        }
+        self
+    }
+}
+
+/// The line above each step's output when several run in turn, like `head` shows between files.
+fn section_heading(heading: &str) -> String {
+    format!("==> {} <==\n", heading)
+}
+
+/// How a step run in parallel ended, for its heading.
+fn item_status(status: Status) -> String {
+    match status.success() {
+        true => "ok".to_string(),
+        false => describe_status(status),
+    }
+}
+
+/// Appends the output of the next step to `stdout`, with a blank line between them, returning
+/// how many bytes went between them.
+fn append_section(stdout: &mut Vec<u8>, next: &[u8]) -> usize {
+    let separator: &[u8] = match (
+        stdout.is_empty() || next.is_empty(),
+        stdout.ends_with(b"\n"),
+    ) {
+        (true, _) => b"",
+        (false, true) => b"\n",
+        (false, false) => b"\n\n",
+    };
+    stdout.extend_from_slice(separator);
+    stdout.extend_from_slice(next);
+    separator.len()
+}
+
+/// One of the commands run in turn for each execution, see [`Watcher::and`](crate::Watcher::and).
+pub(crate) struct Step {
+    pub command: Command,
+    /// The line its output is shown under, when there are several.
+    pub heading: Option<String>,
+    /// What its stdout is piped through before it's captured.
+    pub filter: Option<Filter>,
+    /// Whether it runs at the same time as the parallel steps next to it, instead of after them,
+    /// see [`Watcher::foreach`](crate::Watcher::foreach).
+    pub parallel: bool,
+    /// How much of its output is kept, see [`Watcher::max_output`](crate::Watcher::max_output).
+    pub cap: Option<OutputCap>,
+}
+
+impl Step {
+    pub fn new(command: Command, heading: Option<String>) -> Self {
+        Step {
+            command,
+            heading,
+            filter: None,
+            parallel: false,
+            cap: None,
+        }
+    }
+
+    /// Pipes the step's stdout through `filter`.
+    pub fn filtered(mut self, filter: Option<Filter>) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Runs the step at the same time as the parallel steps next to it.
+    pub fn parallel(mut self) -> Self {
+        self.parallel = true;
+        self
+    }
+
+    /// Keeps only as much of the step's output as `cap` allows.
+    pub fn capped(mut self, cap: Option<OutputCap>) -> Self {
+        self.cap = cap;
+        self
+    }
+}
+
+/// Takes the next steps to run from the front of `steps`: the first one, and the parallel steps
+/// following it if it's parallel too.
+pub(crate) fn next_batch(steps: &mut VecDeque<Step>) -> Vec<Step> {
+    let mut batch: Vec<Step> = steps.pop_front().into_iter().collect();
+    while batch.first().is_some_and(|first| first.parallel)
+        && steps.front().is_some_and(|step| step.parallel)
+    {
+        batch.extend(steps.pop_front());
+    }
+    batch
+}
+
+/// A command a step's stdout is piped through, see [`Watcher::pipe`](crate::Watcher::pipe).
+pub(crate) struct Filter {
+    pub command: Command,
+    /// What it runs, e.g. "sort | head -20", for saying so when it fails.
+    pub command_line: String,
+}
+
+impl From<Command> for Step {
+    fn from(command: Command) -> Self {
+        Step::new(command, None)
+    }
+}
+
+/// The decoded result of one execution of the watched command, as passed to
+/// [`Watcher`](crate::Watcher) hooks.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExecutionResult {
+    pub stdout: String,
+    pub stderr: String,
+    /// The lines of stdout and stderr in the order they were printed, with
+    /// [`Watcher::interleave`](crate::Watcher::interleave). Empty otherwise.
+    pub interleaved: Vec<(Pipe, String)>,
+    pub status: Status,
+    /// When the command was started.
+    pub started_at: SystemTime,
+    /// How long the command ran for.
+    pub duration: Duration,
+    /// Whether stdout or stderr differs from the previous execution. Always false for the first one.
+    pub changed: bool,
+}
+
+/// Runs `steps` to completion one after the other, capturing their output.
+///
+/// If `timeout` elapses before a step ends it's killed, along with everything it spawned (its
+/// process group on Unix, its job object on Windows), and whatever it printed so far is kept.
+///
+/// Fails with [`ErrorKind::Interrupted`], killing the command, if the watch is interrupted.
+pub(crate) fn run(
+    steps: Vec<Step>,
+    timeout: Option<Duration>,
+) -> std::result::Result<Output, WatchError> {
+    let mut running = Running::spawn(steps, timeout)?;
+    loop {
+        if let Some(status) = running.try_finish()? {
+            return Ok(running.finish(status));
+        }
+        if signal::cancelled() {
+            return Err(Error::from(ErrorKind::Interrupted).into());
+        }
+        thread::sleep(WAIT_POLL_INTERVAL);
+    }
+}
+
+/// An execution in progress, whose steps run one after the other, each spawned once the previous
+/// one has exited, except that parallel steps next to each other run at the same time.
+///
+/// Dropping it before it finishes kills the steps that are running.
+pub(crate) struct Running {
+    /// The steps running now: one, or a batch of parallel ones.
+    current: Vec<Current>,
+    /// The steps still to run.
+    rest: VecDeque<Step>,
+    /// The combined output of the steps that have finished.
+    done: Option<Output>,
+    timeout: Option<Duration>,
+    interactive: bool,
+    start_time: Instant,
+}
+
+/// One of the steps running now.
+struct Current {
+    process: Process,
+    /// Its heading, when there are several steps.
+    heading: Option<String>,
+    parallel: bool,
+    /// How it ended, once it has while others in its batch are still running.
+    status: Option<Status>,
+}
+
+impl Current {
+    /// Spawns each of `batch`, killing those already spawned if one can't be.
+    fn spawn_batch(
+        batch: Vec<Step>,
+        timeout: Option<Duration>,
+        interactive: bool,
+    ) -> std::result::Result<Vec<Current>, WatchError> {
+        batch
+            .into_iter()
+            .map(|step| {
+                Ok(Current {
+                    process: Process::spawn(
+                        step.command,
+                        step.filter,
+                        step.cap,
+                        timeout,
+                        interactive,
+                    )?,
+                    heading: step.heading,
+                    parallel: step.parallel,
+                    status: None,
+                })
+            })
+            .collect()
+    }
+
+    /// Waits for the output to be fully read, once the step has ended, or else with `status`.
+    fn finish(self, status: Status) -> Output {
+        let status = self.status.unwrap_or(status);
+        self.process
+            .finish(status)
+            .headed(self.heading.as_deref(), self.parallel)
+    }
+}
+
+/// Combines the output of a batch of steps, in the order they were given.
+fn finish_batch(batch: Vec<Current>, status: Status) -> Output {
+    batch
+        .into_iter()
+        .map(|current| current.finish(status))
+        .reduce(Output::alongside)
+        .unwrap_or_else(|| Output {
+            status,
+            stdout: Vec::new(),
+            stderr: Vec::new(),
+            chunks: Vec::new(),
+            started_at: SystemTime::now(),
+            duration: Duration::ZERO,
+        })
+}
+
+impl Running {
+    /// Spawns the first of `steps`. Each of them may run for up to `timeout`.
+    pub fn spawn(
+        steps: Vec<Step>,
+        timeout: Option<Duration>,
+    ) -> std::result::Result<Self, WatchError> {
+        Running::start(steps, timeout, false)
+    }
+
+    /// Spawns the first of `steps` with a pipe to its stdin, see [`Running::write_stdin`].
+    pub fn spawn_interactive(
+        steps: Vec<Step>,
+        timeout: Option<Duration>,
+    ) -> std::result::Result<Self, WatchError> {
+        Running::start(steps, timeout, true)
+    }
+
+    fn start(
+        steps: Vec<Step>,
+        timeout: Option<Duration>,
+        interactive: bool,
+    ) -> std::result::Result<Self, WatchError> {
+        let mut rest = VecDeque::from(steps);
+        let first = next_batch(&mut rest);
+        if first.is_empty() {
+            return Err(Error::other("there's no command to run").into());
+        }
+        Ok(Running {
+            current: Current::spawn_batch(first, timeout, interactive)?,
+            rest,
+            done: None,
+            timeout,
+            interactive,
+            start_time: Instant::now(),
+        })
+    }
+
+    /// The process ids of the steps that are running.
+    pub fn pids(&self) -> Vec<u32> {
+        self.current
+            .iter()
+            .filter(|current| current.status.is_none())
+            .map(|current| current.process.child.id())
+            .collect()
+    }
+
+    /// How long the execution has been running, across its steps.
+    pub fn elapsed(&self) -> Duration {
+        self.start_time.elapsed()
+    }
+
+    /// Returns how the last step ended once every step has, or the first of the last batch to
+    /// fail, spawning the next steps as the ones before them end. A step that runs past the
+    /// timeout is killed first.
+    pub fn try_finish(&mut self) -> std::result::Result<Option<Status>, WatchError> {
+        loop {
+            for current in &mut self.current {
+                if current.status.is_none() {
+                    current.status = current.process.try_finish()?;
+                }
+            }
+            let statuses: Option<Vec<Status>> =
+                self.current.iter().map(|current| current.status).collect();
+            let Some(statuses) = statuses else {
+                return Ok(None);
+            };
+            let status = statuses
+                .iter()
+                .find(|status| !status.success())
+                .or(statuses.last())
+                .copied()
+                .unwrap_or(Status::Exited(ExitStatus::default()));
+            let next = next_batch(&mut self.rest);
+            if next.is_empty() {
+                return Ok(Some(status));
+            }
+            let next = Current::spawn_batch(next, self.timeout, self.interactive)?;
+            let finished = finish_batch(std::mem::replace(&mut self.current, next), status);
+            self.done = Some(match self.done.take() {
+                Some(done) => done.then(finished),
+                None => finished,
+            });
+        }
+    }
+
+    /// Everything the steps have printed so far, as (stdout, stderr).
+    pub fn output_so_far(&self) -> (Vec<u8>, Vec<u8>) {
+        let (mut all, mut all_err) = match &self.done {
+            Some(done) => (done.stdout.clone(), done.stderr.clone()),
+            None => Default::default(),
+        };
+        for current in &self.current {
+            let (stdout, stderr) = current.process.output_so_far();
+            let mut section = match (&current.heading, current.status) {
+                (Some(heading), Some(status)) if current.parallel => {
+                    section_heading(&format!("{} ({})", heading, item_status(status)))
+                }
+                (Some(heading), _) => section_heading(heading),
+                (None, _) => String::new(),
+            }
+            .into_bytes();
+            section.extend_from_slice(&stdout);
+            append_section(&mut all, &section);
+            all_err.extend_from_slice(&stderr);
+        }
+        (all, all_err)
+    }
+
+    /// Sends `bytes` to the running steps' stdin, if they were spawned interactively. A command
+    /// that has closed its stdin just doesn't get them.
+    pub fn write_stdin(&mut self, bytes: &[u8]) -> Result<()> {
+        for current in &mut self.current {
+            current.process.write_stdin(bytes)?;
+        }
+        Ok(())
+    }
+
+    /// Waits for the output to be fully read once the last steps have ended, the last of them
+    /// with `status`, and combines it with the others'.
+    pub fn finish(self, status: Status) -> Output {
+        let last = finish_batch(self.current, status);
+        match self.done {
+            Some(done) => done.then(last),
+            None => last,
+        }
+    }
+}
+
+/// A spawned command whose output is being collected in the background.
+///
+/// Dropping it before it finishes kills the command.
+struct Process {
+    child: Child,
+    /// The filter the command's stdout is piped through, running alongside it.
+    filter: Option<(Child, String)>,
+    group: ProcessGroup,
+    stdin: Option<ChildStdin>,
+    captured: Arc<Mutex<Captured>>,
+    readers: Vec<thread::JoinHandle<()>>,
+    start_time: Instant,
+    started_at: SystemTime,
+    timeout: Option<Duration>,
+    finished: bool,
+}
+
+impl Process {
+    /// Spawns the command, with a pipe to its stdin if it's `interactive`, see
+    /// [`Running::write_stdin`], and its stdout piped through `filter`, if there is one. Only as
+    /// much of its output as `cap` allows is kept.
+    fn spawn(
+        mut command: Command,
+        filter: Option<Filter>,
+        cap: Option<OutputCap>,
+        timeout: Option<Duration>,
+        interactive: bool,
+    ) -> std::result::Result<Self, WatchError> {
+        let stdin = match interactive {
+            true => Stdio::piped(),
+            false => Stdio::null(),
+        };
+        command
+            .stdin(stdin)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            command.process_group(0);
+        }
+        let mut child = command.spawn().map_err(|source| WatchError::Spawn {
+            program: command.get_program().to_string_lossy().into_owned(),
+            source,
+        })?;
+        let group = ProcessGroup::new(&child)?;
+        let stdin = child.stdin.take();
+        let mut filter = match filter {
+            Some(filter) => match spawn_filter(filter, &mut child, &group) {
+                Ok(filter) => Some(filter),
+                Err(err) => {
+                    let _ = group.kill(&mut child);
+                    let _ = child.wait();
+                    return Err(err);
+                }
+            },
+            None => None,
+        };
+
+        // Drain the pipes on other threads so a chatty command can't block on a full pipe. With a
+        // filter, what's captured is its output, along with the command's stderr.
+        let captured = Arc::new(Mutex::new(Captured {
+            cap,
+            ..Captured::default()
+        }));
+        let mut readers = Vec::new();
+        let output = filter.as_mut().map_or(&mut child, |(filter, _)| filter);
+        if let Some(pipe) = output.stdout.take() {
+            readers.push(collect(pipe, Pipe::Stdout, Arc::clone(&captured)));
+        }
+        if let Some(pipe) = output.stderr.take() {
+            readers.push(collect(pipe, Pipe::Stderr, Arc::clone(&captured)));
+        }
+        if let Some(pipe) = child.stderr.take() {
+            readers.push(collect(pipe, Pipe::Stderr, Arc::clone(&captured)));
+        }
+
+        Ok(Process {
+            child,
+            filter,
+            group,
+            stdin,
+            captured,
+            readers,
+            start_time: Instant::now(),
+            started_at: SystemTime::now(),
+            timeout,
+            finished: false,
+        })
+    }
+
+    /// How long the command has been running.
+    fn elapsed(&self) -> Duration {
+        self.start_time.elapsed()
+    }
+
+    /// Returns how the command ended if it has, killing it first if it ran past the timeout.
+    fn try_finish(&mut self) -> Result<Option<Status>> {
+        // With a filter, it's done once both have exited
+        let filtered = match &mut self.filter {
+            Some((filter, _)) => filter.try_wait()?.is_some(),
+            None => true,
+        };
+        if let (Some(status), true) = (self.child.try_wait()?, filtered) {
+            self.finished = true;
+            return Ok(Some(Status::Exited(status)));
+        }
+        match self.timeout {
+            Some(timeout) if self.elapsed() >= timeout => {
+                self.kill()?;
+                Ok(Some(Status::TimedOut(timeout)))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Everything the command has printed so far, as (stdout, stderr).
+    fn output_so_far(&self) -> (Vec<u8>, Vec<u8>) {
+        match self.captured.lock() {
+            Ok(captured) => (captured.stdout.bytes.clone(), captured.stderr.bytes.clone()),
+            Err(_) => Default::default(),
+        }
+    }
+
+    /// Sends `bytes` to the command's stdin, if it was spawned interactively. A command that has
+    /// closed its stdin just doesn't get them.
+    fn write_stdin(&mut self, bytes: &[u8]) -> Result<()> {
+        let Some(stdin) = &mut self.stdin else {
+            return Ok(());
+        };
+        match stdin.write_all(bytes).and_then(|()| stdin.flush()) {
+            Err(err) if err.kind() == ErrorKind::BrokenPipe => {
+                self.stdin = None;
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    /// Kills the command and everything it spawned.
+    fn kill(&mut self) -> Result<()> {
+        self.group.kill(&mut self.child)?;
+        self.child.wait()?;
+        if let Some((filter, _)) = &mut self.filter {
+            // Stopping the command ends its output, which ends the filter if it wasn't killed too
+            filter.wait()?;
+        }
+        self.finished = true;
+        Ok(())
+    }
+
+    /// Waits for the output to be fully read once the command has ended with `status`.
+    fn finish(mut self, status: Status) -> Output {
+        for reader in self.readers.drain(..) {
+            let _ = reader.join();
+        }
+        let mut captured = self
+            .captured
+            .lock()
+            .map(|mut captured| std::mem::take(&mut *captured))
+            .unwrap_or_default();
+        let truncation = captured.finish();
+        let mut output = Output {
+            status,
+            stdout: captured.stdout.bytes,
+            stderr: captured.stderr.bytes,
+            chunks: captured.chunks,
+            started_at: self.started_at,
+            duration: self.elapsed(),
+        };
+        if let Some(truncation) = truncation {
+            output = output
+                .truncated(Pipe::Stdout, captured.stdout.dropped, truncation)
+                .truncated(Pipe::Stderr, captured.stderr.dropped, truncation);
+        }
+        match &mut self.filter {
+            Some((filter, command_line)) => {
+                let filter_status = match status {
+                    Status::TimedOut(_) => None,
+                    _ => filter.try_wait().ok().flatten(),
+                };
+                output.piped(command_line, filter_status)
+            }
+            None => output,
+        }
+    }
+}
+
+/// Spawns `filter` reading the stdout of `command`, which is in `group`, and adds it to the group
+/// so that they're killed together.
+fn spawn_filter(
+    filter: Filter,
+    command: &mut Child,
+    group: &ProcessGroup,
+) -> std::result::Result<(Child, String), WatchError> {
+    let Filter {
+        command: mut filter,
+        command_line,
+    } = filter;
+    let stdin = command.stdout.take().map_or_else(Stdio::null, Stdio::from);
+    filter
+        .stdin(stdin)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        filter.process_group(command.id() as i32);
+    }
+    let child = filter.spawn().map_err(|source| WatchError::Spawn {
+        program: filter.get_program().to_string_lossy().into_owned(),
+        source,
+    })?;
+    group.add(&child)?;
+    Ok((child, command_line))
+}
+
+impl Drop for Process {
+    fn drop(&mut self) {
+        if !self.finished {
+            let _ = self.kill();
+        }
+    }
+}
+
+/// Everything the command has printed, and in what order.
+#[derive(Default)]
+struct Captured {
+    stdout: Capture,
+    stderr: Capture,
+    chunks: Vec<(Pipe, usize)>,
+    cap: Option<OutputCap>,
+}
+
+impl Captured {
+    fn push(&mut self, which: Pipe, read: &[u8]) {
+        let capture = match which {
+            Pipe::Stdout => &mut self.stdout,
+            Pipe::Stderr => &mut self.stderr,
+        };
+        let (added, dropped) = capture.push(read, self.cap);
+        if added > 0 {
+            self.chunks.push((which, added));
+        }
+        self.forget(which, dropped);
+    }
+
+    /// Drops whatever is still over the cap once the pipes have been read to the end, returning
+    /// which part of the output was kept if there's a cap.
+    fn finish(&mut self) -> Option<Truncation> {
+        let dropped = self.stdout.finish(self.cap);
+        self.forget(Pipe::Stdout, dropped);
+        let dropped = self.stderr.finish(self.cap);
+        self.forget(Pipe::Stderr, dropped);
+        self.cap.map(|cap| cap.truncation)
+    }
+
+    /// Takes the first `bytes` of `which` pipe out of the chunks, once they've been dropped from
+    /// the start of its output.
+    fn forget(&mut self, which: Pipe, mut bytes: usize) {
+        if bytes == 0 {
+            return;
+        }
+        for (pipe, len) in &mut self.chunks {
+            if *pipe == which {
+                let cut = bytes.min(*len);
+                *len -= cut;
+                bytes -= cut;
+                if bytes == 0 {
+                    break;
+                }
+            }
+        }
+        self.chunks.retain(|&(_, len)| len > 0);
+    }
+}
+
+/// Appends everything read from `pipe`, which is `which` of the command's pipes, to `captured` as
+/// it arrives, keeping within its cap.
+fn collect(
+    mut pipe: impl Read + Send + 'static,
+    which: Pipe,
+    captured: Arc<Mutex<Captured>>,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let mut chunk = [0; 8192];
+        // Stop quietly if the pipe breaks when the command is killed
+        while let Ok(n) = pipe.read(&mut chunk) {
+            if n == 0 {
+                break;
+            }
+            let Ok(mut captured) = captured.lock() else {
+                break;
+            };
+            captured.push(which, &chunk[..n]);
+        }
+    })
+}
+
+/// The command and every process it spawns, so they can all be killed together.
+#[cfg(unix)]
+struct ProcessGroup {
+    pgid: libc::pid_t,
+}
+
+#[cfg(unix)]
+impl ProcessGroup {
+    fn new(child: &Child) -> Result<Self> {
+        // The child leads its own group, see `process_group(0)` in `Process::spawn`
+        Ok(ProcessGroup {
+            pgid: child.id() as libc::pid_t,
+        })
+    }
+
+    /// Does nothing, as other processes join the group when they're spawned.
+    fn add(&self, _child: &Child) -> Result<()> {
+        Ok(())
+    }
+
+    fn kill(&self, child: &mut Child) -> Result<()> {
+        // SAFETY: `kill` has no memory-safety preconditions
+        if unsafe { libc::kill(-self.pgid, libc::SIGKILL) } != 0 {
+            // The group may already be gone; fall back to the direct child
+            return child.kill();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+struct ProcessGroup {
+    job: windows_sys::Win32::Foundation::HANDLE,
+}
+
+#[cfg(windows)]
+impl ProcessGroup {
+    fn new(child: &Child) -> Result<Self> {
+        use windows_sys::Win32::System::JobObjects::CreateJobObjectW;
+
+        // SAFETY: a null name and security descriptor create an anonymous job with default security
+        let job = unsafe { CreateJobObjectW(std::ptr::null(), std::ptr::null()) };
+        if job == 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let group = ProcessGroup { job };
+        group.add(child)?;
+        Ok(group)
+    }
+
+    /// Puts another process in the job, so it's killed with the command.
+    fn add(&self, child: &Child) -> Result<()> {
+        use std::os::windows::io::AsRawHandle;
+        use windows_sys::Win32::System::JobObjects::AssignProcessToJobObject;
+
+        // SAFETY: both handles are valid for the duration of the call
+        if unsafe { AssignProcessToJobObject(self.job, child.as_raw_handle() as _) } == 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    fn kill(&self, child: &mut Child) -> Result<()> {
+        use windows_sys::Win32::System::JobObjects::TerminateJobObject;
+
+        // SAFETY: `self.job` is a valid job handle until dropped
+        if unsafe { TerminateJobObject(self.job, 1) } == 0 {
+            return child.kill();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+impl Drop for ProcessGroup {
+    fn drop(&mut self) {
+        // SAFETY: `self.job` was returned by `CreateJobObjectW` and is closed exactly once
+        unsafe { windows_sys::Win32::Foundation::CloseHandle(self.job) };
+    }
+}
@@ -0,0 +1,56 @@
+use std::{
+    fs,
+    io::Result,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use chrono::{DateTime, Local};
+use serde_json::json;
+
+use crate::exec::ExecutionResult;
+use crate::history::History;
+use crate::log::save_snapshot;
+
+/// The file in an export listing every run in it.
+const INDEX: &str = "index.json";
+
+/// Writes every run in `history` to `dir`, creating it if needed: each one to a file named after
+/// when it started, in the format of [`save_snapshot`], and all of them to an `index.json`
+/// describing each run and naming its file. Runs exported before are overwritten. Returns how
+/// many runs were written.
+pub(crate) fn export(dir: &Path, command: &str, history: &History) -> Result<usize> {
+    fs::create_dir_all(dir)?;
+    let mut runs = Vec::new();
+    for result in history.iter() {
+        let file = run_file(result);
+        save_snapshot(&dir.join(&file), command, result)?;
+        let started_at: DateTime<Local> = result.started_at.into();
+        runs.push(json!({
+            "file": file,
+            "ts": started_at.to_rfc3339(),
+            "exit_code": result.status.code(),
+            "duration_ms": result.duration.as_millis() as u64,
+            "changed": result.changed,
+        }));
+    }
+    let count = runs.len();
+    let index = json!({ "command": command, "runs": runs });
+    let mut index = serde_json::to_string_pretty(&index)?;
+    index.push('\n');
+    fs::write(dir.join(INDEX), index)?;
+    Ok(count)
+}
+
+/// A directory to export to when none was given, e.g. "watch-export-20240601-142233".
+pub(crate) fn default_dir() -> PathBuf {
+    let now: DateTime<Local> = SystemTime::now().into();
+    PathBuf::from(format!("watch-export-{}", now.format("%Y%m%d-%H%M%S")))
+}
+
+/// The name of the file `result` is exported to, e.g. "20240601-142233.120.txt". The
+/// milliseconds keep runs apart at short intervals.
+fn run_file(result: &ExecutionResult) -> String {
+    let started_at: DateTime<Local> = result.started_at.into();
+    format!("{}.txt", started_at.format("%Y%m%d-%H%M%S%.3f"))
+}
@@ -0,0 +1,301 @@
+use std::{
+    io::ErrorKind,
+    ops::ControlFlow,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::error::WatchError;
+use crate::exec::{ExecutionResult, Output};
+use crate::export::export;
+use crate::history::History;
+use crate::log::LogSink;
+use crate::outcome::ExitReason;
+use crate::runner::{self, CommandRunner};
+use crate::signal::{self, CANCEL_POLL_INTERVAL};
+use crate::template::Vars;
+use crate::trigger::{FileTrigger, FILE_POLL_INTERVAL};
+use crate::watcher::{backoff_interval, next_slot, Watcher};
+
+/// Runs a [`Watcher`]'s command on its interval without a TUI, yielding each execution's result.
+///
+/// Created by [`Watcher::watch_iter`]. The first execution happens immediately, and each call to
+/// `next` after that sleeps until the interval has elapsed since the previous execution finished.
+#[derive(Debug)]
+pub struct WatchStream {
+    watcher: Watcher,
+    full_watch_command: String,
+    previous: Option<ExecutionResult>,
+    log: Option<LogSink>,
+    /// Runs the command instead of spawning it, see [`Watcher::runner`].
+    runner: Option<Box<dyn CommandRunner>>,
+    /// When the next execution is due, or `None` before the first one.
+    next_run: Option<Instant>,
+    runs: u64,
+    /// Consecutive failed runs, which [`Watcher::backoff`] backs the interval off by.
+    failures: u32,
+    /// Whether the command has succeeded yet, for [`Watcher::wait_first_success`].
+    succeeded: bool,
+    /// When the [`Watcher::run_for`] budget runs out.
+    deadline: Option<Instant>,
+    /// Started on the first call to `next`, so that errors can be reported through it.
+    files: Option<FileTrigger>,
+    /// The runs kept for [`Watcher::export`], when exporting.
+    history: Option<History>,
+    /// Why exporting failed once the stream ended, reported by the next call to `next`.
+    export_error: Option<WatchError>,
+    done: bool,
+    /// Why the stream ended, once it has.
+    exit_reason: Option<ExitReason>,
+}
+
+impl WatchStream {
+    pub(crate) fn new(mut watcher: Watcher) -> Self {
+        watcher.install_builtin_hooks();
+        let history = watcher.export.is_some().then(|| {
+            let mut history = History::new(watcher.history);
+            for result in &watcher.preloaded {
+                history.push(result.clone());
+            }
+            history
+        });
+        let previous = watcher.preloaded.pop();
+        WatchStream {
+            full_watch_command: watcher.full_command(),
+            log: watcher.open_log(),
+            runner: watcher.runner.take(),
+            deadline: watcher.run_for.map(|run_for| Instant::now() + run_for),
+            watcher,
+            previous,
+            next_run: None,
+            runs: 0,
+            failures: 0,
+            succeeded: false,
+            files: None,
+            history,
+            export_error: None,
+            done: false,
+            exit_reason: None,
+        }
+    }
+
+    /// The time left until the next execution is due.
+    pub fn time_until_next(&self) -> Duration {
+        match self.next_run {
+            Some(next_run) => next_run.saturating_duration_since(Instant::now()),
+            None => Duration::ZERO,
+        }
+    }
+
+    /// Why the stream ended, or `None` while it's still going or if it ended with an error.
+    pub fn exit_reason(&self) -> Option<ExitReason> {
+        self.exit_reason
+    }
+
+    fn finish(&mut self, reason: ExitReason) {
+        self.done = true;
+        self.exit_reason = Some(reason);
+        if let (Some(dir), Some(history)) = (&self.watcher.export, &self.history) {
+            let command = self.full_watch_command.trim_end();
+            self.export_error = export(dir, command, history).err().map(WatchError::from);
+        }
+    }
+}
+
+/// What a [`WatchStream`] is waiting on before its next execution, see [`WatchStream::poll_wait`].
+pub(crate) enum Wait {
+    /// The next execution is due now.
+    Run,
+    /// Check again after this long.
+    Sleep(Duration),
+    /// The stream has ended, because the [`Watcher::run_for`] budget ran out or the watch was
+    /// interrupted.
+    Stop,
+}
+
+impl WatchStream {
+    /// Starts watching files before the first execution, so that errors can be reported through
+    /// the stream. Returns `None` once the stream has ended.
+    pub(crate) fn begin(&mut self) -> Option<std::result::Result<(), WatchError>> {
+        if self.done {
+            return self.export_error.take().map(Err);
+        }
+        if let Err(err) = self.watcher.serve_metrics() {
+            self.done = true;
+            return Some(Err(err.into()));
+        }
+        if self.next_run.is_none() && self.files.is_none() {
+            match self.watcher.file_trigger() {
+                Ok(files) => self.files = files,
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err.into()));
+                }
+            }
+            self.next_run = self.watcher.first_run_at();
+        }
+        Some(Ok(()))
+    }
+
+    /// Checks whether the next execution is due, on the interval or because files changed,
+    /// without blocking.
+    pub(crate) fn poll_wait(&mut self) -> Wait {
+        let wait = self.wait();
+        if let Wait::Stop = wait {
+            self.finish(match signal::cancelled() {
+                true => ExitReason::Interrupted,
+                false => ExitReason::Duration,
+            });
+        }
+        wait
+    }
+
+    fn wait(&mut self) -> Wait {
+        if signal::cancelled() {
+            return Wait::Stop;
+        }
+        let on_interval = self.watcher.runs_on_interval();
+        let until_next = self.time_until_next();
+        let Some(files) = self.files.as_mut() else {
+            if self
+                .deadline
+                .is_some_and(|deadline| Instant::now() + until_next >= deadline)
+            {
+                return Wait::Stop;
+            }
+            return match until_next.is_zero() {
+                true => Wait::Run,
+                false => Wait::Sleep(CANCEL_POLL_INTERVAL.min(until_next)),
+            };
+        };
+
+        // The first execution happens right away, like without watched paths, unless it's delayed
+        let due = (on_interval || self.runs == 0) && until_next.is_zero();
+        if self.next_run.is_none() || files.fired() || due {
+            return Wait::Run;
+        }
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return Wait::Stop;
+        }
+        match on_interval {
+            true => Wait::Sleep(FILE_POLL_INTERVAL.min(until_next)),
+            false => Wait::Sleep(FILE_POLL_INTERVAL),
+        }
+    }
+
+    /// When the next execution counts as having started, which the precise cadence is measured
+    /// from: when it was due rather than when it actually started.
+    pub(crate) fn scheduled(&self) -> Instant {
+        match self.next_run {
+            Some(next_run) if self.watcher.precise => next_run,
+            _ => Instant::now(),
+        }
+    }
+
+    /// The processes for the next execution.
+    #[cfg(feature = "async")]
+    pub(crate) fn steps(&self) -> Vec<crate::exec::Step> {
+        self.watcher
+            .build_steps(&self.full_watch_command, &self.vars())
+    }
+
+    /// What the command's placeholders stand for in the next execution.
+    fn vars(&self) -> Vars {
+        Vars::new(self.runs + 1, self.previous.as_ref())
+    }
+
+    /// How long an execution may run before it's killed.
+    #[cfg(feature = "async")]
+    pub(crate) fn timeout(&self) -> Option<Duration> {
+        self.watcher.timeout
+    }
+
+    /// Records an execution that was `scheduled` at that time, returning its result, or `None` if
+    /// it was interrupted.
+    pub(crate) fn complete(
+        &mut self,
+        output: std::result::Result<Output, WatchError>,
+        scheduled: Instant,
+    ) -> Option<std::result::Result<ExecutionResult, WatchError>> {
+        let output = match output {
+            Ok(output) => output,
+            // Being interrupted just ends the watch
+            Err(err) if err.is_kind(ErrorKind::Interrupted) => {
+                self.finish(ExitReason::Interrupted);
+                return None;
+            }
+            Err(err) => {
+                self.done = true;
+                return Some(Err(err));
+            }
+        };
+        self.failures = match output.status.success() {
+            true => 0,
+            false => self.failures.saturating_add(1),
+        };
+        self.succeeded |= output.status.success();
+        let interval = match (self.watcher.wait_first_success, self.watcher.backoff) {
+            (Some(retry), _) if !self.succeeded => retry,
+            (_, Some(max)) => backoff_interval(self.watcher.interval, self.failures, max),
+            _ => self.watcher.interval,
+        };
+        self.next_run = Some(match (self.watcher.next_cron_run(), self.watcher.precise) {
+            (Some(next_run), _) => next_run,
+            (None, true) => next_slot(scheduled, interval, Instant::now()),
+            // Like the TUI, the interval is counted from when the run finished
+            (None, false) => Instant::now() + interval,
+        });
+        let result = self.watcher.decode(output, self.previous.as_ref());
+        self.runs += 1;
+        if let Some(history) = self.history.as_mut() {
+            history.push(result.clone());
+        }
+        if let Some(Err(err)) = self.log.as_mut().map(|log| log.record(&result)) {
+            self.done = true;
+            return Some(Err(err.into()));
+        }
+
+        let reason = match self.watcher.hooks.fire(&result) {
+            ControlFlow::Break(()) => Some(ExitReason::Hook),
+            ControlFlow::Continue(()) if self.watcher.errexits_on(result.status) => {
+                Some(ExitReason::Failed)
+            }
+            ControlFlow::Continue(()) => self.watcher.exit_reason(&result, self.runs),
+        };
+        if let Some(reason) = reason {
+            self.finish(reason);
+        }
+        self.previous = Some(result.clone());
+        Some(Ok(result))
+    }
+}
+
+impl Iterator for WatchStream {
+    type Item = std::result::Result<ExecutionResult, WatchError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Err(err) = self.begin()? {
+            return Some(Err(err));
+        }
+        loop {
+            match self.poll_wait() {
+                Wait::Run => break,
+                Wait::Sleep(wait) => thread::sleep(wait),
+                Wait::Stop => return None,
+            }
+        }
+        let scheduled = self.scheduled();
+        let vars = self.vars();
+        let output = match self.runner.as_deref_mut() {
+            Some(command_runner) => {
+                let steps = self.watcher.build_steps(&self.full_watch_command, &vars);
+                runner::run(command_runner, steps)
+            }
+            None => self.watcher.execute(&self.full_watch_command, &vars),
+        };
+        self.complete(output, scheduled)
+    }
+}
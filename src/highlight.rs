@@ -0,0 +1,137 @@
+use std::{
+    fmt::{self, Write},
+    str::FromStr,
+};
+
+use crossterm::style::Color;
+use regex::Regex;
+
+use crate::viewport::{push_char, Line};
+
+/// Colors every match of a regular expression in the command's output.
+#[derive(Clone, Debug)]
+pub struct HighlightRule {
+    pattern: Regex,
+    color: Color,
+}
+
+impl HighlightRule {
+    pub fn new(pattern: Regex, color: Color) -> Self {
+        HighlightRule { pattern, color }
+    }
+}
+
+/// Parses a `"<regex>:<color>"` rule, e.g. `"ERROR:red"`. The color is the part after the last
+/// colon, so the regex can contain colons itself.
+///
+/// Colors are named (`red`, `dark_green`, `grey`, ...), an ANSI 256-color number, or `#rrggbb`.
+///
+/// # Examples
+///
+/// ```
+/// use watch_rs::HighlightRule;
+///
+/// assert!("ERROR:red".parse::<HighlightRule>().is_ok());
+/// assert!(r"\d{2}:\d{2}:#00ff00".parse::<HighlightRule>().is_ok());
+/// assert!("ERROR".parse::<HighlightRule>().is_err());
+/// ```
+impl FromStr for HighlightRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (pattern, color) = s
+            .rsplit_once(':')
+            .ok_or_else(|| format!("`{s}` isn't in <regex>:<color> form"))?;
+        let color = parse_color(color).ok_or_else(|| format!("`{color}` isn't a color"))?;
+        let pattern = Regex::new(pattern).map_err(|err| err.to_string())?;
+        Ok(HighlightRule::new(pattern, color))
+    }
+}
+
+/// The rule in the form it's parsed from.
+///
+/// # Examples
+///
+/// ```
+/// use watch_rs::HighlightRule;
+///
+/// let rule: HighlightRule = "WARN:dark-yellow".parse().unwrap();
+/// assert_eq!(rule.to_string(), "WARN:dark_yellow");
+/// ```
+impl fmt::Display for HighlightRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:", self.pattern)?;
+        match self.color {
+            Color::Rgb { r, g, b } => write!(f, "#{r:02x}{g:02x}{b:02x}"),
+            Color::AnsiValue(value) => write!(f, "{value}"),
+            // Named colors, e.g. "dark_green" for `DarkGreen`
+            named => {
+                for (i, c) in format!("{named:?}").chars().enumerate() {
+                    if i > 0 && c.is_uppercase() {
+                        f.write_char('_')?;
+                    }
+                    f.write_char(c.to_ascii_lowercase())?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Parses a color name, an ANSI 256-color number, or `#rrggbb`.
+pub(crate) fn parse_color(s: &str) -> Option<Color> {
+    let s = s.trim();
+    if let Some(hex) = s.strip_prefix('#') {
+        if hex.len() != 6 {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+        return Some(Color::Rgb {
+            r: channel(0)?,
+            g: channel(2)?,
+            b: channel(4)?,
+        });
+    }
+    if let Ok(value) = s.parse::<u8>() {
+        return Some(Color::AnsiValue(value));
+    }
+    Color::try_from(s.replace('-', "_").as_str()).ok()
+}
+
+/// Applies `rules` to every line, coloring the text they match. Later rules win where matches
+/// overlap. Other styling, like bold or a background, is kept.
+pub(crate) fn apply(lines: Vec<Line>, rules: &[HighlightRule]) -> Vec<Line> {
+    if rules.is_empty() {
+        return lines;
+    }
+    lines
+        .into_iter()
+        .map(|line| {
+            let text: String = line.iter().map(|span| span.text.as_str()).collect();
+            // The color to apply at each byte offset of `text`, if any
+            let mut colors: Vec<Option<Color>> = vec![None; text.len()];
+            for rule in rules {
+                for found in rule.pattern.find_iter(&text) {
+                    colors[found.range()].fill(Some(rule.color));
+                }
+            }
+            if colors.iter().all(Option::is_none) {
+                return line;
+            }
+
+            let mut highlighted = Line::new();
+            let mut offset = 0;
+            for span in &line {
+                for c in span.text.chars() {
+                    let mut style = span.style;
+                    if let Some(color) = colors[offset] {
+                        style.foreground_color = Some(color);
+                    }
+                    push_char(&mut highlighted, style, c);
+                    offset += c.len_utf8();
+                }
+            }
+            highlighted
+        })
+        .collect()
+}
@@ -0,0 +1,110 @@
+use std::{collections::VecDeque, time::SystemTime};
+
+use crate::exec::ExecutionResult;
+
+/// The most recent executions, and which one the user is looking at.
+#[derive(Debug)]
+pub(crate) struct History {
+    entries: VecDeque<ExecutionResult>,
+    capacity: usize,
+    /// Index into `entries` of the run being viewed, or `None` to follow the latest run.
+    cursor: Option<usize>,
+}
+
+impl History {
+    /// Creates a history keeping the last `capacity` executions, and always at least the latest.
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        History {
+            entries: VecDeque::with_capacity(capacity.min(64)),
+            capacity,
+            cursor: None,
+        }
+    }
+
+    /// Records a new execution, dropping the oldest once full. A run being viewed stays selected.
+    pub fn push(&mut self, result: ExecutionResult) {
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+            self.cursor = self.cursor.map(|cursor| cursor.saturating_sub(1));
+        }
+        self.entries.push_back(result);
+    }
+
+    /// Every run kept, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = &ExecutionResult> {
+        self.entries.iter()
+    }
+
+    pub fn latest(&self) -> Option<&ExecutionResult> {
+        self.entries.back()
+    }
+
+    pub fn into_latest(mut self) -> Option<ExecutionResult> {
+        self.entries.pop_back()
+    }
+
+    /// Whether an older run is being viewed instead of the latest.
+    pub fn is_browsing(&self) -> bool {
+        self.cursor.is_some()
+    }
+
+    /// The run being viewed, when browsing.
+    pub fn selected(&self) -> Option<&ExecutionResult> {
+        self.cursor.and_then(|cursor| self.entries.get(cursor))
+    }
+
+    /// The run on screen (the selected one, or else the latest) and the run before it.
+    pub fn shown_with_previous(&self) -> Option<(Option<&ExecutionResult>, &ExecutionResult)> {
+        let index = self
+            .cursor
+            .unwrap_or_else(|| self.entries.len().saturating_sub(1));
+        let shown = self.entries.get(index)?;
+        let previous = index.checked_sub(1).and_then(|i| self.entries.get(i));
+        Some((previous, shown))
+    }
+
+    /// Steps to the previous run. Returns whether the selection changed.
+    pub fn back(&mut self) -> bool {
+        let current = self
+            .cursor
+            .unwrap_or_else(|| self.entries.len().saturating_sub(1));
+        if current == 0 {
+            return false;
+        }
+        self.cursor = Some(current - 1);
+        true
+    }
+
+    /// Steps to the next run, returning to following the latest past the end. Returns whether the
+    /// selection changed.
+    pub fn forward(&mut self) -> bool {
+        match self.cursor {
+            None => false,
+            Some(cursor) if cursor + 2 >= self.entries.len() => {
+                self.cursor = None;
+                true
+            }
+            Some(cursor) => {
+                self.cursor = Some(cursor + 1);
+                true
+            }
+        }
+    }
+
+    /// Describes the run being viewed for the header, e.g. "run 47/50, 12s ago".
+    pub fn label(&self) -> Option<String> {
+        let cursor = self.cursor?;
+        let entry = self.entries.get(cursor)?;
+        let ago = SystemTime::now()
+            .duration_since(entry.started_at)
+            .unwrap_or_default()
+            .as_secs();
+        Some(format!(
+            "run {}/{}, {}s ago",
+            cursor + 1,
+            self.entries.len(),
+            ago
+        ))
+    }
+}
@@ -0,0 +1,79 @@
+use std::{fmt, ops::ControlFlow, process::Stdio, thread};
+
+use crate::exec::ExecutionResult;
+use crate::shell::Shell;
+
+/// A callback invoked with the result of an execution. Returning [`ControlFlow::Break`] stops watching.
+pub(crate) type Hook = Box<dyn FnMut(&ExecutionResult) -> ControlFlow<()> + Send>;
+
+/// The callbacks registered on a [`Watcher`](crate::Watcher).
+#[derive(Default)]
+pub(crate) struct Hooks {
+    pub on_output: Vec<Hook>,
+    pub on_error: Vec<Hook>,
+    pub on_change: Vec<Hook>,
+}
+
+impl Hooks {
+    /// Invokes every hook that applies to `result`, stopping early if one breaks.
+    pub fn fire(&mut self, result: &ExecutionResult) -> ControlFlow<()> {
+        for hook in &mut self.on_output {
+            hook(result)?;
+        }
+        if !result.status.success() {
+            for hook in &mut self.on_error {
+                hook(result)?;
+            }
+        }
+        if result.changed {
+            for hook in &mut self.on_change {
+                hook(result)?;
+            }
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+impl fmt::Debug for Hooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Hooks")
+            .field("on_output", &self.on_output.len())
+            .field("on_error", &self.on_error.len())
+            .field("on_change", &self.on_change.len())
+            .finish()
+    }
+}
+
+/// A hook that runs `command` through `shell` in the background whenever the output changes.
+///
+/// It watches every execution, rather than being an `on_change` hook, so it can remember the
+/// previous output to pass along.
+pub(crate) fn command_on_change(shell: Shell, command: String) -> Hook {
+    let mut previous: Option<String> = None;
+    Box::new(move |result| {
+        let old_output = previous.replace(result.stdout.clone());
+        if !result.changed {
+            return ControlFlow::Continue(());
+        }
+        let exit_code = result
+            .status
+            .code()
+            .map(|code| code.to_string())
+            .unwrap_or_default();
+        let spawned = shell
+            .command(&command)
+            .env("WATCH_OLD_OUTPUT", old_output.unwrap_or_default())
+            .env("WATCH_NEW_OUTPUT", &result.stdout)
+            .env("WATCH_EXIT_CODE", exit_code)
+            // Keep it from drawing over the TUI
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        if let Ok(mut child) = spawned {
+            // Reap it once it exits
+            thread::spawn(move || child.wait());
+        }
+        ControlFlow::Continue(())
+    })
+}
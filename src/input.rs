@@ -0,0 +1,501 @@
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use crossterm::event::{
+    Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
+
+use crate::viewport::Scroll;
+
+/// Something the user asked the watch loop to do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Action {
+    Quit,
+    Scroll(Scroll),
+    /// Stop or restart running the command on the interval.
+    TogglePause,
+    /// Run the command immediately, regardless of the remaining interval.
+    Refresh,
+    IncreaseInterval,
+    DecreaseInterval,
+    /// Show the run before the one on screen.
+    HistoryBack,
+    /// Show the run after the one on screen, or the latest.
+    HistoryForward,
+    /// Switch between the normal view and a side-by-side diff against the previous run.
+    ToggleSplit,
+    /// Switch between wrapping long lines and clipping them at the terminal width.
+    ToggleWrap,
+    /// Collapse the stderr section to its heading, or expand it back.
+    ToggleStderr,
+    /// Copy the output on screen to the clipboard.
+    Yank,
+    /// Save the output on screen to a file, after prompting for its name.
+    Save,
+    /// Write every run in the history to a directory.
+    Export,
+    /// Show the output on screen in the pager, leaving the TUI until it exits.
+    Pager,
+    /// Open the search prompt.
+    Search,
+    /// Scroll to the next or previous line matching the search.
+    NextMatch,
+    PreviousMatch,
+    /// Keep the first line of output in view at the top, or unpin every line.
+    Pin,
+    UnpinAll,
+    /// Sort the table by this column, counting from 1, or reverse it if it already is. Column 0
+    /// is the output's own order.
+    Sort(usize),
+    /// Move the focus to the next or previous pane, when watching several commands.
+    FocusNext,
+    FocusPrevious,
+    /// Show what every key does and the options the watch runs with, until the next key press.
+    Help,
+    /// Re-render the last output, e.g. because the terminal was resized.
+    Redraw,
+    /// The left mouse button was pressed on this cell, with [`Watcher::mouse`](crate::Watcher::mouse).
+    Click {
+        column: u16,
+        row: u16,
+    },
+}
+
+impl Action {
+    /// What the action does, for the help.
+    pub(crate) fn description(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::Scroll(Scroll::Up) => "scroll up",
+            Action::Scroll(Scroll::Down) => "scroll down",
+            Action::Scroll(Scroll::PageUp) => "scroll up a page",
+            Action::Scroll(Scroll::PageDown) => "scroll down a page",
+            Action::Scroll(Scroll::Home) => "scroll to the top",
+            Action::Scroll(Scroll::End) => "scroll to the bottom",
+            Action::Scroll(Scroll::Left) => "scroll left",
+            Action::Scroll(Scroll::Right) => "scroll right",
+            Action::TogglePause => "pause or resume",
+            Action::Refresh => "run the command now",
+            Action::IncreaseInterval => "lengthen the interval",
+            Action::DecreaseInterval => "shorten the interval",
+            Action::HistoryBack => "show the run before",
+            Action::HistoryForward => "show the run after, or the latest",
+            Action::ToggleSplit => "compare with the run before, side by side",
+            Action::ToggleWrap => "wrap or clip long lines",
+            Action::ToggleStderr => "collapse or expand stderr",
+            Action::Yank => "copy the output to the clipboard",
+            Action::Save => "save the output to a file",
+            Action::Export => "export every run in the history",
+            Action::Pager => "show the output in the pager",
+            Action::Search => "search the output",
+            Action::NextMatch => "go to the next match",
+            Action::PreviousMatch => "go to the previous match",
+            Action::Pin => "pin the top line of output",
+            Action::UnpinAll => "unpin every line",
+            Action::Sort(0) => "undo sorting the table",
+            Action::Sort(_) => "sort the table by that column, again to reverse",
+            Action::FocusNext => "focus the next pane",
+            Action::FocusPrevious => "focus the previous pane",
+            Action::Help => "show this help",
+            Action::Redraw => "redraw the screen",
+            Action::Click { .. } => "click",
+        }
+    }
+}
+
+/// The name each remappable action goes by in [`Keymap::bind`] and the config file.
+const ACTION_NAMES: &[(&str, Action)] = &[
+    ("quit", Action::Quit),
+    ("pause", Action::TogglePause),
+    ("refresh", Action::Refresh),
+    ("increase-interval", Action::IncreaseInterval),
+    ("decrease-interval", Action::DecreaseInterval),
+    ("history-back", Action::HistoryBack),
+    ("history-forward", Action::HistoryForward),
+    ("toggle-split", Action::ToggleSplit),
+    ("toggle-wrap", Action::ToggleWrap),
+    ("toggle-stderr", Action::ToggleStderr),
+    ("yank", Action::Yank),
+    ("save", Action::Save),
+    ("export", Action::Export),
+    ("pager", Action::Pager),
+    ("search", Action::Search),
+    ("next-match", Action::NextMatch),
+    ("previous-match", Action::PreviousMatch),
+    ("pin", Action::Pin),
+    ("unpin-all", Action::UnpinAll),
+    ("unsort", Action::Sort(0)),
+    ("sort-by-1", Action::Sort(1)),
+    ("sort-by-2", Action::Sort(2)),
+    ("sort-by-3", Action::Sort(3)),
+    ("sort-by-4", Action::Sort(4)),
+    ("sort-by-5", Action::Sort(5)),
+    ("sort-by-6", Action::Sort(6)),
+    ("sort-by-7", Action::Sort(7)),
+    ("sort-by-8", Action::Sort(8)),
+    ("sort-by-9", Action::Sort(9)),
+    ("focus-next", Action::FocusNext),
+    ("focus-previous", Action::FocusPrevious),
+    ("help", Action::Help),
+    ("scroll-up", Action::Scroll(Scroll::Up)),
+    ("scroll-down", Action::Scroll(Scroll::Down)),
+    ("page-up", Action::Scroll(Scroll::PageUp)),
+    ("page-down", Action::Scroll(Scroll::PageDown)),
+    ("scroll-top", Action::Scroll(Scroll::Home)),
+    ("scroll-bottom", Action::Scroll(Scroll::End)),
+    ("scroll-left", Action::Scroll(Scroll::Left)),
+    ("scroll-right", Action::Scroll(Scroll::Right)),
+];
+
+/// The names of special keys in a [`KeyBinding`], besides `f1` through `f12`.
+const KEY_NAMES: &[(&str, KeyCode)] = &[
+    ("space", KeyCode::Char(' ')),
+    ("enter", KeyCode::Enter),
+    ("tab", KeyCode::Tab),
+    ("backtab", KeyCode::BackTab),
+    ("esc", KeyCode::Esc),
+    ("backspace", KeyCode::Backspace),
+    ("delete", KeyCode::Delete),
+    ("insert", KeyCode::Insert),
+    ("up", KeyCode::Up),
+    ("down", KeyCode::Down),
+    ("left", KeyCode::Left),
+    ("right", KeyCode::Right),
+    ("pageup", KeyCode::PageUp),
+    ("pagedown", KeyCode::PageDown),
+    ("home", KeyCode::Home),
+    ("end", KeyCode::End),
+];
+
+/// A key along with the modifiers held down with it, e.g. `j`, `ctrl+d`, or `pagedown`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct KeyBinding {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        // Shift is already in the case of a character, and in Shift+Tab being a key of its own
+        let shift = modifiers.contains(KeyModifiers::SHIFT);
+        let code = match code {
+            KeyCode::Char(c) if shift => KeyCode::Char(c.to_ascii_uppercase()),
+            KeyCode::Tab if shift => KeyCode::BackTab,
+            code => code,
+        };
+        let modifiers = match code {
+            KeyCode::Char(_) | KeyCode::BackTab => modifiers - KeyModifiers::SHIFT,
+            _ => modifiers,
+        };
+        KeyBinding { code, modifiers }
+    }
+}
+
+/// Parses a key, optionally prefixed with `ctrl+`, `alt+`, or `shift+`: a single character, or a
+/// name like `space`, `enter`, `esc`, `up`, `pagedown`, `home`, or `f5`.
+///
+/// # Examples
+///
+/// ```
+/// use watch_rs::KeyBinding;
+///
+/// let key: KeyBinding = "ctrl+d".parse().unwrap();
+/// assert_eq!(key.to_string(), "ctrl+d");
+/// assert_eq!("Shift+Tab".parse::<KeyBinding>(), "backtab".parse());
+/// assert!("ctrl+nope".parse::<KeyBinding>().is_err());
+/// ```
+impl FromStr for KeyBinding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Split off the modifiers, taking care that "+" and "ctrl++" are keys themselves
+        let (prefix, key) = match s.rsplit_once('+') {
+            Some((prefix, "")) if !prefix.is_empty() => (
+                prefix
+                    .strip_suffix('+')
+                    .ok_or(format!("`{s}` isn't a key"))?,
+                "+",
+            ),
+            Some((prefix, key)) if !prefix.is_empty() => (prefix, key),
+            _ => ("", s),
+        };
+        let mut modifiers = KeyModifiers::NONE;
+        for modifier in prefix.split('+').filter(|_| !prefix.is_empty()) {
+            modifiers |= match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => {
+                    return Err(format!(
+                        "`{modifier}` isn't a modifier like ctrl, alt, or shift"
+                    ))
+                }
+            };
+        }
+
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => {
+                let name = key.to_ascii_lowercase();
+                let function = name
+                    .strip_prefix('f')
+                    .and_then(|n| n.parse().ok())
+                    .filter(|n| (1..=12).contains(n));
+                match (KEY_NAMES.iter().find(|(known, _)| *known == name), function) {
+                    (Some(&(_, code)), _) => code,
+                    (None, Some(n)) => KeyCode::F(n),
+                    (None, None) => return Err(format!("`{key}` isn't a key")),
+                }
+            }
+        };
+        Ok(KeyBinding::new(code, modifiers))
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (modifier, name) in [
+            (KeyModifiers::CONTROL, "ctrl"),
+            (KeyModifiers::ALT, "alt"),
+            (KeyModifiers::SHIFT, "shift"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                write!(f, "{}+", name)?;
+            }
+        }
+        match KEY_NAMES.iter().find(|(_, code)| *code == self.code) {
+            Some((name, _)) => write!(f, "{}", name),
+            None => match self.code {
+                KeyCode::Char(c) => write!(f, "{}", c),
+                KeyCode::F(n) => write!(f, "f{}", n),
+                code => write!(f, "{:?}", code),
+            },
+        }
+    }
+}
+
+/// The keys that trigger each action in the TUI.
+///
+/// Starts out with the default bindings, which [`Keymap::bind`] adds to or overrides.
+///
+/// # Examples
+///
+/// ```
+/// use watch_rs::Keymap;
+///
+/// // vi-style scrolling, on top of the arrow keys
+/// let mut keymap = Keymap::default();
+/// keymap.bind("j".parse().unwrap(), "scroll-down").unwrap();
+/// keymap.bind("k".parse().unwrap(), "scroll-up").unwrap();
+/// assert!(keymap.bind("x".parse().unwrap(), "explode").is_err());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Keymap {
+    bindings: HashMap<KeyBinding, Action>,
+}
+
+impl Keymap {
+    /// Binds `key` to the action named `action`, replacing whatever it was bound to; `"none"`
+    /// unbinds it instead.
+    ///
+    /// The actions are `quit`, `pause`, `refresh`, `increase-interval`, `decrease-interval`,
+    /// `history-back`, `history-forward`, `toggle-split`, `toggle-wrap`, `toggle-stderr`, `yank`,
+    /// `save`, `pager`, `search`, `next-match`, `previous-match`, `focus-next`, `focus-previous`,
+    /// `scroll-up`, `scroll-down`, `page-up`, `page-down`, `scroll-top`, `scroll-bottom`,
+    /// `scroll-left`, `scroll-right`, and `help`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a message if there's no action named `action`.
+    pub fn bind(&mut self, key: KeyBinding, action: &str) -> Result<(), String> {
+        if action == "none" {
+            self.bindings.remove(&key);
+            return Ok(());
+        }
+        let (_, action) = ACTION_NAMES
+            .iter()
+            .find(|(name, _)| *name == action)
+            .ok_or_else(|| format!("`{action}` isn't an action"))?;
+        self.bindings.insert(key, *action);
+        Ok(())
+    }
+
+    /// The keys bound to `action`, plain keys before ones with modifiers.
+    pub(crate) fn keys_for(&self, action: Action) -> Vec<KeyBinding> {
+        let mut keys: Vec<KeyBinding> = self
+            .bindings
+            .iter()
+            .filter(|(_, bound)| **bound == action)
+            .map(|(key, _)| *key)
+            .collect();
+        keys.sort_by_key(|key| (key.modifiers.bits(), key.to_string()));
+        keys
+    }
+
+    /// The keys bound to each action but those in `skip`, next to what it does, for the help.
+    /// Actions doing the same for different columns share a line, like "1-9".
+    pub(crate) fn help(&self, skip: &[Action]) -> Vec<(String, &'static str)> {
+        let mut help: Vec<(Vec<KeyBinding>, &'static str)> = Vec::new();
+        for &(_, action) in ACTION_NAMES {
+            let keys = self.keys_for(action);
+            if keys.is_empty() || skip.contains(&action) {
+                continue;
+            }
+            match help.last_mut() {
+                Some((shared, description)) if *description == action.description() => {
+                    shared.extend(keys)
+                }
+                _ => help.push((keys, action.description())),
+            }
+        }
+        help.into_iter()
+            .map(|(keys, description)| (describe_keys(&keys), description))
+            .collect()
+    }
+
+    /// Maps a terminal event to the action it triggers, if any.
+    pub(crate) fn action_for(&self, event: Event) -> Option<Action> {
+        match event {
+            Event::Key(event) => self.key_action(event),
+            Event::Resize(_, _) => Some(Action::Redraw),
+            Event::Mouse(event) => mouse_action(event),
+            _ => None,
+        }
+    }
+
+    /// Maps a key press to the action it triggers, if any.
+    fn key_action(&self, event: KeyEvent) -> Option<Action> {
+        // Windows also reports key releases and repeats
+        if event.kind != KeyEventKind::Press {
+            return None;
+        }
+        let key = KeyBinding::new(event.code, event.modifiers);
+        self.bindings.get(&key).copied()
+    }
+}
+
+/// `keys` as a list like "q, ctrl+c", or a range like "1-9" if they're three or more characters in
+/// a row.
+fn describe_keys(keys: &[KeyBinding]) -> String {
+    let chars: Option<Vec<char>> = keys
+        .iter()
+        .map(|key| match (key.code, key.modifiers.is_empty()) {
+            (KeyCode::Char(c), true) => Some(c),
+            _ => None,
+        })
+        .collect();
+    if let Some(chars @ [first, _, .., last]) = chars.as_deref() {
+        if chars
+            .windows(2)
+            .all(|pair| pair[0] as u32 + 1 == pair[1] as u32)
+        {
+            return format!("{}-{}", first, last);
+        }
+    }
+    let keys: Vec<String> = keys.iter().map(ToString::to_string).collect();
+    keys.join(", ")
+}
+
+/// Maps the mouse wheel to scrolling, and left clicks to [`Action::Click`].
+fn mouse_action(event: MouseEvent) -> Option<Action> {
+    match event.kind {
+        MouseEventKind::ScrollUp => Some(Action::Scroll(Scroll::Up)),
+        MouseEventKind::ScrollDown => Some(Action::Scroll(Scroll::Down)),
+        MouseEventKind::ScrollLeft => Some(Action::Scroll(Scroll::Left)),
+        MouseEventKind::ScrollRight => Some(Action::Scroll(Scroll::Right)),
+        MouseEventKind::Down(MouseButton::Left) => Some(Action::Click {
+            column: event.column,
+            row: event.row,
+        }),
+        _ => None,
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let key = |code| KeyBinding::new(code, KeyModifiers::NONE);
+        let char = |c| key(KeyCode::Char(c));
+        let bindings = [
+            (char('q'), Action::Quit),
+            (
+                KeyBinding::new(KeyCode::Char('c'), KeyModifiers::CONTROL),
+                Action::Quit,
+            ),
+            (char(' '), Action::TogglePause),
+            (char('r'), Action::Refresh),
+            (key(KeyCode::Enter), Action::Refresh),
+            (char('+'), Action::IncreaseInterval),
+            (char('>'), Action::IncreaseInterval),
+            (char('-'), Action::DecreaseInterval),
+            (char('<'), Action::DecreaseInterval),
+            (char('['), Action::HistoryBack),
+            (char(']'), Action::HistoryForward),
+            (char('D'), Action::ToggleSplit),
+            (char('w'), Action::ToggleWrap),
+            (char('e'), Action::ToggleStderr),
+            (char('y'), Action::Yank),
+            (char('s'), Action::Save),
+            (char('x'), Action::Export),
+            (char('|'), Action::Pager),
+            (char('/'), Action::Search),
+            (char('n'), Action::NextMatch),
+            (char('N'), Action::PreviousMatch),
+            (char('p'), Action::Pin),
+            (char('P'), Action::UnpinAll),
+            (char('0'), Action::Sort(0)),
+            (char('1'), Action::Sort(1)),
+            (char('2'), Action::Sort(2)),
+            (char('3'), Action::Sort(3)),
+            (char('4'), Action::Sort(4)),
+            (char('5'), Action::Sort(5)),
+            (char('6'), Action::Sort(6)),
+            (char('7'), Action::Sort(7)),
+            (char('8'), Action::Sort(8)),
+            (char('9'), Action::Sort(9)),
+            (key(KeyCode::Tab), Action::FocusNext),
+            (key(KeyCode::BackTab), Action::FocusPrevious),
+            (char('?'), Action::Help),
+            (key(KeyCode::Up), Action::Scroll(Scroll::Up)),
+            (key(KeyCode::Down), Action::Scroll(Scroll::Down)),
+            (key(KeyCode::PageUp), Action::Scroll(Scroll::PageUp)),
+            (key(KeyCode::PageDown), Action::Scroll(Scroll::PageDown)),
+            (key(KeyCode::Home), Action::Scroll(Scroll::Home)),
+            (key(KeyCode::End), Action::Scroll(Scroll::End)),
+            (key(KeyCode::Left), Action::Scroll(Scroll::Left)),
+            (key(KeyCode::Right), Action::Scroll(Scroll::Right)),
+        ];
+        Keymap {
+            bindings: bindings.into_iter().collect(),
+        }
+    }
+}
+
+/// The bytes a key press or paste would send to a program reading a terminal, for forwarding to
+/// the command in interactive mode.
+pub(crate) fn key_bytes(event: &Event) -> Option<Vec<u8>> {
+    let event = match event {
+        Event::Key(event) => event,
+        Event::Paste(text) => return Some(text.clone().into_bytes()),
+        _ => return None,
+    };
+    if event.kind != KeyEventKind::Press {
+        return None;
+    }
+    let bytes = match event.code {
+        // Ctrl+A through Ctrl+Z map to the control characters 0x01 through 0x1A
+        KeyCode::Char(c) if event.modifiers.contains(KeyModifiers::CONTROL) => {
+            let c = c.to_ascii_lowercase();
+            if !c.is_ascii_lowercase() {
+                return None;
+            }
+            vec![c as u8 - b'a' + 1]
+        }
+        KeyCode::Char(c) => c.to_string().into_bytes(),
+        KeyCode::Enter => vec![b'\n'],
+        KeyCode::Tab => vec![b'\t'],
+        KeyCode::Backspace => vec![0x7f],
+        KeyCode::Esc => vec![0x1b],
+        _ => return None,
+    };
+    Some(bytes)
+}
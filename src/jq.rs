@@ -0,0 +1,389 @@
+use std::str::FromStr;
+
+use crossterm::style::ContentStyle;
+use serde_json::Value;
+
+use crate::theme::Theme;
+use crate::viewport::{push_char, Line};
+
+/// A jq-like filter selecting part of a JSON document, e.g. `.items[].metadata.name`.
+///
+/// It's a path of object keys (`.name`, `."a key"`, `["a key"]`), array indices (`[0]`, `[-1]`),
+/// and iteration over every element (`[]`), each of which can end in `?` to skip values it
+/// doesn't apply to instead of failing. Paths can be piped into each other and into `keys` and
+/// `length`, e.g. `.items | length`.
+///
+/// # Examples
+///
+/// ```
+/// use watch_rs::JqFilter;
+///
+/// assert!(".items[].metadata.name".parse::<JqFilter>().is_ok());
+/// assert!(r#".["a key"][0] | keys"#.parse::<JqFilter>().is_ok());
+/// assert!("items".parse::<JqFilter>().is_err());
+/// assert!(".items[".parse::<JqFilter>().is_err());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JqFilter {
+    source: String,
+    steps: Vec<Step>,
+}
+
+/// One step of a [`JqFilter`], and whether it ended in `?`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Step {
+    kind: StepKind,
+    optional: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum StepKind {
+    Key(String),
+    Index(i64),
+    Iterate,
+    Keys,
+    Length,
+}
+
+impl FromStr for JqFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut steps = Vec::new();
+        for part in split_pipes(s) {
+            let part = part.trim();
+            match part {
+                "keys" => steps.push(Step::new(StepKind::Keys)),
+                "length" => steps.push(Step::new(StepKind::Length)),
+                _ if part.starts_with('.') => parse_path(part, &mut steps)?,
+                _ => return Err(format!("`{part}` isn't a path like `.name` or `.[0]`")),
+            }
+        }
+        Ok(JqFilter {
+            source: s.trim().to_string(),
+            steps,
+        })
+    }
+}
+
+impl Step {
+    fn new(kind: StepKind) -> Self {
+        Step {
+            kind,
+            optional: false,
+        }
+    }
+
+    /// The values this step selects from `value`.
+    fn apply(&self, value: Value) -> Result<Vec<Value>, String> {
+        let type_name = |value: &Value| match value {
+            Value::Null => "null",
+            Value::Bool(_) => "boolean",
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Array(_) => "array",
+            Value::Object(_) => "object",
+        };
+        let selected = match (&self.kind, value) {
+            (StepKind::Key(_) | StepKind::Index(_), Value::Null) => Ok(vec![Value::Null]),
+            (StepKind::Key(key), Value::Object(mut object)) => {
+                Ok(vec![object.remove(key).unwrap_or(Value::Null)])
+            }
+            (StepKind::Index(index), Value::Array(mut array)) => {
+                let index = match *index < 0 {
+                    true => array.len().checked_sub(index.unsigned_abs() as usize),
+                    false => Some(*index as usize),
+                };
+                let found = index.filter(|&index| index < array.len());
+                Ok(vec![
+                    found.map_or(Value::Null, |index| array.swap_remove(index))
+                ])
+            }
+            (StepKind::Iterate, Value::Array(array)) => Ok(array),
+            (StepKind::Iterate, Value::Object(object)) => {
+                Ok(object.into_iter().map(|(_, v)| v).collect())
+            }
+            (StepKind::Keys, Value::Object(object)) => {
+                // Sorted, like jq's
+                let mut keys: Vec<String> = object.into_iter().map(|(key, _)| key).collect();
+                keys.sort();
+                Ok(vec![Value::Array(
+                    keys.into_iter().map(Value::String).collect(),
+                )])
+            }
+            (StepKind::Keys, Value::Array(array)) => Ok(vec![Value::Array(
+                (0..array.len()).map(Value::from).collect(),
+            )]),
+            (StepKind::Length, Value::Null) => Ok(vec![Value::from(0)]),
+            (StepKind::Length, Value::Array(array)) => Ok(vec![Value::from(array.len())]),
+            (StepKind::Length, Value::Object(object)) => Ok(vec![Value::from(object.len())]),
+            (StepKind::Length, Value::String(string)) => {
+                Ok(vec![Value::from(string.chars().count())])
+            }
+            // The absolute value, staying an integer for integers like jq's
+            (StepKind::Length, Value::Number(number)) => Ok(vec![match number.as_i64() {
+                Some(integer) => Value::from(integer.unsigned_abs()),
+                None if number.is_u64() => Value::Number(number),
+                None => Value::from(number.as_f64().unwrap_or_default().abs()),
+            }]),
+            (StepKind::Key(key), value) => Err(format!(
+                "cannot index {} with \"{}\"",
+                type_name(&value),
+                key
+            )),
+            (StepKind::Index(_), value) => {
+                Err(format!("cannot index {} with a number", type_name(&value)))
+            }
+            (StepKind::Iterate, value) => Err(format!("cannot iterate over {}", type_name(&value))),
+            (StepKind::Keys | StepKind::Length, value) => {
+                Err(format!("{} has no keys or length", type_name(&value)))
+            }
+        };
+        match (selected, self.optional) {
+            (Err(_), true) => Ok(Vec::new()),
+            (selected, _) => selected,
+        }
+    }
+}
+
+impl JqFilter {
+    /// Applies the filter to `value`, returning every value it selects.
+    pub(crate) fn apply(&self, value: Value) -> Result<Vec<Value>, String> {
+        let mut values = vec![value];
+        for step in &self.steps {
+            let mut next = Vec::new();
+            for value in values {
+                next.extend(step.apply(value)?);
+            }
+            values = next;
+        }
+        Ok(values)
+    }
+}
+
+impl std::fmt::Display for JqFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// Splits `s` at the `|`s outside of quotes.
+fn split_pipes(s: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut start, mut quoted, mut escaped) = (0, false, false);
+    for (i, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            '|' if !quoted => {
+                parts.push(&s[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+/// Parses a path like `.items[0]."a key"` onto `steps`.
+fn parse_path(path: &str, steps: &mut Vec<Step>) -> Result<(), String> {
+    let invalid = || format!("`{path}` isn't a valid path");
+    let mut rest = path;
+    while !rest.is_empty() {
+        let kind = if let Some(after) = rest.strip_prefix('[') {
+            let (inside, after) = after.split_once(']').ok_or_else(invalid)?;
+            rest = after;
+            let inside = inside.trim();
+            if inside.is_empty() {
+                StepKind::Iterate
+            } else if inside.starts_with('"') {
+                StepKind::Key(unquote(inside).ok_or_else(invalid)?)
+            } else {
+                StepKind::Index(inside.parse().map_err(|_| invalid())?)
+            }
+        } else if let Some(after) = rest.strip_prefix('.') {
+            if after.starts_with('"') {
+                let end = closing_quote(after).ok_or_else(invalid)?;
+                rest = &after[end + 1..];
+                StepKind::Key(unquote(&after[..=end]).ok_or_else(invalid)?)
+            } else {
+                let end = after
+                    .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                    .unwrap_or(after.len());
+                rest = &after[end..];
+                if end == 0 {
+                    // `.` alone is the whole value, and `.[` continues with a bracket
+                    if rest.is_empty() || rest.starts_with('[') {
+                        continue;
+                    }
+                    return Err(invalid());
+                }
+                StepKind::Key(after[..end].to_string())
+            }
+        } else {
+            return Err(invalid());
+        };
+        let optional = rest.starts_with('?');
+        rest = rest.strip_prefix('?').unwrap_or(rest);
+        steps.push(Step { kind, optional });
+    }
+    Ok(())
+}
+
+/// The index of the quote closing the string `s` starts with.
+fn closing_quote(s: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in s.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The contents of a quoted JSON string.
+fn unquote(s: &str) -> Option<String> {
+    match serde_json::from_str(s).ok()? {
+        Value::String(s) => Some(s),
+        _ => None,
+    }
+}
+
+/// Parses `text` as one or more JSON values, like JSON Lines, or `None` if it isn't JSON.
+pub(crate) fn parse(text: &str) -> Option<Vec<Value>> {
+    let values = serde_json::Deserializer::from_str(text)
+        .into_iter::<Value>()
+        .collect::<Result<Vec<_>, _>>()
+        .ok()?;
+    (!values.is_empty()).then_some(values)
+}
+
+/// Pretty-prints `values` with two-space indents, one after the other.
+pub(crate) fn pretty(values: &[Value]) -> String {
+    let values: Vec<String> = values
+        .iter()
+        .map(|value| serde_json::to_string_pretty(value).unwrap_or_default())
+        .collect();
+    values.join("\n")
+}
+
+/// Colors the keys, strings, and numbers, booleans, and nulls of the JSON in `text`.
+pub(crate) fn highlight(text: &str, theme: &Theme) -> Vec<Line> {
+    text.lines()
+        .map(|row| {
+            let mut line = Line::new();
+            let mut chars = row.char_indices().peekable();
+            while let Some((i, c)) = chars.next() {
+                let style = match c {
+                    '"' => {
+                        let end = closing_quote(&row[i..]).map_or(row.len(), |end| i + end + 1);
+                        while chars.next_if(|&(j, _)| j < end).is_some() {}
+                        let is_key = row[end..].trim_start().starts_with(':');
+                        let style = if is_key {
+                            theme.json_key
+                        } else {
+                            theme.json_string
+                        };
+                        for c in row[i..end].chars() {
+                            push_char(&mut line, style, c);
+                        }
+                        continue;
+                    }
+                    c if c.is_ascii_digit() || c == '-' || c.is_ascii_lowercase() => {
+                        theme.json_literal
+                    }
+                    _ => ContentStyle::new(),
+                };
+                push_char(&mut line, style, c);
+            }
+            line
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::JqFilter;
+
+    /// What `filter` selects from `json`, as compact JSON separated by spaces.
+    fn apply(filter: &str, json: &str) -> Result<String, String> {
+        let filter: JqFilter = filter.parse().unwrap();
+        let values = filter.apply(serde_json::from_str(json).unwrap())?;
+        let values: Vec<String> = values.iter().map(ToString::to_string).collect();
+        Ok(values.join(" "))
+    }
+
+    #[test]
+    fn negative_indexes_count_from_the_end() {
+        assert_eq!(apply(".[-1]", "[1, 2, 3]").unwrap(), "3");
+        assert_eq!(apply(".[-3]", "[1, 2, 3]").unwrap(), "1");
+        assert_eq!(apply(".[0]", "[1, 2, 3]").unwrap(), "1");
+    }
+
+    #[test]
+    fn indexes_out_of_range_are_null() {
+        assert_eq!(apply(".[3]", "[1, 2, 3]").unwrap(), "null");
+        assert_eq!(apply(".[-4]", "[1, 2, 3]").unwrap(), "null");
+        assert_eq!(apply(".[0]", "[]").unwrap(), "null");
+        assert_eq!(apply(".a[0].b", r#"{"b": 1}"#).unwrap(), "null");
+    }
+
+    #[test]
+    fn optional_steps_skip_values_they_dont_apply_to() {
+        assert_eq!(apply(".[]?", "5").unwrap(), "");
+        assert_eq!(apply(".[]?", r#""text""#).unwrap(), "");
+        assert_eq!(apply(".a[]?.b", r#"{"a": true}"#).unwrap(), "");
+        assert_eq!(
+            apply(".[].a?", r#"[{"a": 1}, 2, {"a": 3}]"#).unwrap(),
+            "1 3"
+        );
+        assert_eq!(apply(".[]", "5").unwrap_err(), "cannot iterate over number");
+        assert_eq!(
+            apply(".a", "[1]").unwrap_err(),
+            r#"cannot index array with "a""#
+        );
+    }
+
+    #[test]
+    fn quoted_keys_take_json_escapes() {
+        let json = r#"{"a \"b\"": 1, "a\\b": 2, "é": 3, "a|b": 4, "a.b": 5}"#;
+        assert_eq!(apply(r#"."a \"b\"""#, json).unwrap(), "1");
+        assert_eq!(apply(r#".["a\\b"]"#, json).unwrap(), "2");
+        assert_eq!(apply(r#"."\u00e9""#, json).unwrap(), "3");
+        assert_eq!(apply(r#"."a|b""#, json).unwrap(), "4");
+        assert_eq!(apply(r#".["a.b"]"#, json).unwrap(), "5");
+    }
+
+    #[test]
+    fn keys_are_sorted_by_code_point_and_indexes_for_arrays() {
+        let json = r#"{"b": 1, "a": 2, "B": 3, "aa": 4, "é": 5}"#;
+        assert_eq!(apply("keys", json).unwrap(), r#"["B","a","aa","b","é"]"#);
+        assert_eq!(apply("keys", r#"["x", "y"]"#).unwrap(), "[0,1]");
+        assert!(apply("keys", "1").is_err());
+    }
+
+    #[test]
+    fn length_is_like_jqs() {
+        assert_eq!(apply("length", "[1, 2, 3]").unwrap(), "3");
+        assert_eq!(apply("length", r#"{"a": 1}"#).unwrap(), "1");
+        assert_eq!(apply("length", r#""héllo""#).unwrap(), "5");
+        assert_eq!(apply("length", "null").unwrap(), "0");
+        // Numbers give their absolute value, integers staying integers
+        assert_eq!(apply("length", "-5").unwrap(), "5");
+        assert_eq!(
+            apply("length", "18446744073709551615").unwrap(),
+            "18446744073709551615"
+        );
+        assert_eq!(apply("length", "-2.5").unwrap(), "2.5");
+        assert!(apply("length", "true").is_err());
+        assert_eq!(
+            apply(".[] | length", r#"["ab", [1], -3]"#).unwrap(),
+            "2 1 3"
+        );
+    }
+}
@@ -0,0 +1,101 @@
+use std::{
+    fmt::Write as _,
+    io::{stdout, Write},
+};
+
+use chrono::{DateTime, Local};
+
+use crate::error::WatchError;
+use crate::exec::ExecutionResult;
+use crate::outcome::{ExitReason, Outcome};
+use crate::watcher::Watcher;
+
+/// How a [`Watcher`] presents the command's output.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputMode {
+    /// Full-screen, interactive terminal UI, or [`OutputMode::Plain`] when stdout isn't a
+    /// terminal.
+    #[default]
+    Tui,
+    /// One JSON object per execution on stdout (JSON Lines), with no TUI.
+    Json,
+    /// Each execution's output appended to stdout under a header with the time it ran, with no
+    /// TUI.
+    Plain,
+}
+
+impl ExecutionResult {
+    /// Serializes the result as a single-line JSON object, as written by [`OutputMode::Json`].
+    ///
+    /// `exit_code` is `null` when the command timed out or was killed by a signal.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use watch_rs::Watcher;
+    ///
+    /// let result = Watcher::new("echo hi").watch_iter().next().unwrap().unwrap();
+    /// let json = result.to_json();
+    /// assert!(json.starts_with(r#"{"ts":""#));
+    /// assert!(json.contains(r#""exit_code":0,"#));
+    /// assert!(json.ends_with(r#""stdout":"hi","stderr":""}"#));
+    /// ```
+    pub fn to_json(&self) -> String {
+        let ts: DateTime<Local> = self.started_at.into();
+        let exit_code = match self.status.code() {
+            Some(code) => code.to_string(),
+            None => "null".to_string(),
+        };
+        let mut json = String::from("{\"ts\":");
+        push_json_string(&mut json, &ts.to_rfc3339());
+        let _ = write!(
+            json,
+            ",\"exit_code\":{},\"duration_ms\":{},\"stdout\":",
+            exit_code,
+            self.duration.as_millis()
+        );
+        push_json_string(&mut json, &self.stdout);
+        json.push_str(",\"stderr\":");
+        push_json_string(&mut json, &self.stderr);
+        json.push('}');
+        json
+    }
+}
+
+impl Watcher {
+    /// Writes one JSON line per execution to stdout until an exit condition is met.
+    pub(crate) fn run_json(self) -> std::result::Result<Outcome, WatchError> {
+        let mut stream = self.watch_iter();
+        let mut last = None;
+        for result in stream.by_ref() {
+            let result = result?;
+            let mut out = stdout().lock();
+            writeln!(out, "{}", result.to_json())?;
+            out.flush()?;
+            last = Some(result);
+        }
+        Ok(Outcome {
+            reason: stream.exit_reason().unwrap_or(ExitReason::Quit),
+            last,
+        })
+    }
+}
+
+/// Appends `s` to `json` as a quoted, escaped JSON string.
+pub(crate) fn push_json_string(json: &mut String, s: &str) {
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c < ' ' => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
@@ -1,156 +1,159 @@
-use std::{
-    io::{stdout, Error, Result, Write},
-    process::Command,
-    time::{Duration, Instant},
-};
+mod alert;
+mod ansi;
+#[cfg(feature = "async")]
+pub mod r#async;
+mod clipboard;
+mod cron;
+mod decode;
+mod diff;
+mod error;
+mod events;
+mod exec;
+mod export;
+mod headless;
+mod highlight;
+mod history;
+mod hooks;
+mod input;
+mod jq;
+mod json;
+mod log;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod notify;
+mod outcome;
+mod overlay;
+mod pager;
+mod panes;
+mod plain;
+mod plan;
+mod prompt;
+mod record;
+mod remote;
+pub mod render;
+mod replay;
+mod runner;
+mod shell;
+mod signal;
+mod stats;
+mod table;
+mod template;
+mod terminal;
+mod theme;
+mod trigger;
+mod truncation;
+mod usage;
+#[cfg(unix)]
+mod user;
+mod viewport;
+mod watcher;
+mod width;
 
-use crossterm::{
-    cursor::*,
-    event::{poll, read, Event, KeyCode},
-    execute, queue,
-    style::*,
-    terminal::*,
-};
+pub use alert::AlertRule;
+pub use cron::Cron;
+pub use decode::Encoding;
+pub use diff::Differences;
+pub use error::WatchError;
+pub use events::ChangeEvent;
+pub use exec::{ExecutionResult, Pipe, Status};
+pub use headless::WatchStream;
+pub use highlight::HighlightRule;
+pub use input::{KeyBinding, Keymap};
+pub use jq::JqFilter;
+pub use json::OutputMode;
+pub use notify::Notify;
+pub use outcome::{ExitReason, Outcome};
+pub use panes::{Layout, Pane};
+pub use plan::{Plan, PlannedCommand};
+pub use remote::ContainerRuntime;
+pub use replay::replay;
+pub use runner::{CommandRunner, RunOutput};
+pub use shell::Shell;
+pub use table::TableFormat;
+pub use theme::Theme;
+pub use trigger::FirstRun;
+pub use truncation::Truncation;
+#[cfg(unix)]
+pub use user::{Group, User};
+pub use watcher::Watcher;
+
+use std::time::Duration;
+
+/// The smallest interval, in seconds, allowed between command executions.
+/// Mirrors the 0.1s floor enforced by GNU `watch`.
+pub const MIN_INTERVAL: f64 = 0.1;
 
 /// Uses `crossterm` to watch a command and print its output.
 /// Allows the user to exit by pressing 'q' or 'Ctrl+C'.
 ///
+/// This is a shorthand for a watch with nothing but an interval, which stays as it is as options
+/// are added; use [`Watcher`] for everything else.
+///
 /// # Arguments
 ///
 /// * `command` - The command to watch.
 /// * `args` - The arguments to pass to the command.
-/// * `interval` - The interval in seconds between command executions.
+/// * `interval` - The interval in seconds between command executions. Fractional values are
+///   supported; anything below [`MIN_INTERVAL`] is clamped up to it.
+///
+/// Returns how the watch ended, with the last execution, whose output is also printed to the
+/// normal screen on exit.
 ///
 /// # Errors
 ///
-/// Returns a `std::io::Error` if the command fails to execute.
+/// Returns a [`WatchError`] if the command can't be started, the terminal can't be set up, or the
+/// command fails with `errexit`.
 ///
 /// # Examples
 ///
-/// ```
+/// ```no_run
 /// use watch_rs::watch;
 ///
 /// fn main() {
-///     if let Err(err) = watch("ls".to_string(), vec!["-l".to_string()], 1) {
+///     if let Err(err) = watch("ls".to_string(), vec!["-l".to_string()], 0.5) {
 ///         eprintln!("Error: {}", err);
 ///     }
 /// }
 /// ```
-pub fn watch(command: String, args: Vec<String>, interval: u64) -> Result<()> {
-    let interval_duration: Duration = Duration::from_secs(interval);
-
-    let mut full_watch_command: String = command.to_owned();
-    full_watch_command.push_str(" ");
-    full_watch_command.push_str(args.join(" ").as_str());
-
-    let (program, command_arg): (&str, &str);
-    if cfg!(windows) {
-        program = "powershell";
-        command_arg = "-Command";
+pub fn watch(command: String, args: Vec<String>, interval: f64) -> Result<Outcome, WatchError> {
+    let interval = if interval.is_finite() {
+        interval.max(MIN_INTERVAL)
     } else {
-        program = "sh";
-        command_arg = "-c";
-    }
-
-    const QUIT_MSG: &str = "Press 'q' or 'Ctrl+C' to exit";
-    let interval_msg = format!("Interval: {}s", interval);
-
-    enable_raw_mode()?;
-    execute!(stdout(), Hide, EnterAlternateScreen, EnableLineWrap)?;
-    'watchLoop: loop {
-        // Begin queueing updates
-        queue!(
-            stdout(),
-            Clear(ClearType::All),
-            MoveTo(0, 0),
-            Print("> "),
-            PrintStyledContent(full_watch_command.to_owned().rapid_blink()),
-            MoveToColumn(size().unwrap().0 - interval_msg.len() as u16),
-            PrintStyledContent(interval_msg.to_owned().bold()),
-            MoveToNextLine(2),
-        )?;
-        let output = Command::new(program)
-            .arg(command_arg)
-            .arg(&full_watch_command)
-            .output()?;
-
-        if !output.status.success() {
-            return Err(Error::other(format!(
-                "Command failed with exitCode: {}",
-                output.status.code().unwrap()
-            )));
-        }
-
-        let to_trim = String::from_utf8(output.stdout).expect("Get stdout");
-        let std_output = to_trim.trim();
-        let to_trim = String::from_utf8(output.stderr).expect("Get stderr");
-        let std_error = to_trim.trim();
-
-        // Print the output
-        queue!(
-            stdout(),
-            PrintStyledContent("Output:".bold().underlined()),
-            MoveToNextLine(1),
-            Print(std_output),
-            MoveToNextLine(1),
-        )?;
-        if !std_error.is_empty() {
-            queue!(
-                stdout(),
-                PrintStyledContent("StdErr:".bold().underlined()),
-                MoveToNextLine(1),
-                Print(std_error),
-                MoveToNextLine(1),
-            )?;
-        }
-        queue!(
-            stdout(),
-            MoveTo(size().unwrap().0 - QUIT_MSG.len() as u16, size().unwrap().1 - 1),
-            PrintStyledContent(QUIT_MSG.italic()),
-        )?;
-
-        // Flush updates
-        stdout().flush()?;
+        MIN_INTERVAL
+    };
+    Watcher::new(command)
+        .args(args)
+        .interval(Duration::from_secs_f64(interval))
+        .run()
+}
 
-        // Poll for keys/sleep
-        let start_time = Instant::now();
-        while start_time.elapsed() < interval_duration {
-            if poll(interval_duration - start_time.elapsed())? {
-                match read()? {
-                    Event::Key(event)
-                        if event.code == KeyCode::Char('q')
-                            || (event.code == KeyCode::Char('c')
-                                && event.modifiers == crossterm::event::KeyModifiers::CONTROL) =>
-                    {
-                        // Leave alternate screen and print output one more time before exit
-                        queue!(
-                            stdout(),
-                            LeaveAlternateScreen,
-                            Print("> "),
-                            Print(full_watch_command),
-                            MoveToNextLine(2),
-                            PrintStyledContent("Output:".bold().underlined()),
-                            MoveToNextLine(1),
-                            Print(std_output),
-                            MoveToNextLine(1),
-                        )?;
-                        if !std_error.is_empty() {
-                            queue!(
-                                stdout(),
-                                PrintStyledContent("StdErr:".bold().underlined()),
-                                MoveToNextLine(1),
-                                Print(std_error),
-                                MoveToNextLine(1),
-                            )?;
-                        }
-                        stdout().flush()?;
-                        break 'watchLoop;
-                    }
-                    _ => {}
-                }
-            }
-        }
-    }
-    execute!(stdout(), Show, DisableLineWrap)?;
-    disable_raw_mode()
+/// Shows the text `source` returns in the TUI, calling it again every `interval`, like [`watch`]
+/// does with a command's output. An error is shown like a failing command's stderr, and the watch
+/// goes on.
+///
+/// This is a shorthand for [`Watcher::from_fn`] with `<fn>` in the header for the command, which
+/// takes all the other options.
+///
+/// # Errors
+///
+/// Returns a [`WatchError`] if the terminal can't be set up.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::{Duration, Instant};
+/// use watch_rs::watch_fn;
+///
+/// let start = Instant::now();
+/// watch_fn(Duration::from_secs(1), move || {
+///     Ok::<_, String>(format!("up {}s", start.elapsed().as_secs()))
+/// })
+/// .unwrap();
+/// ```
+pub fn watch_fn<F, E>(interval: Duration, source: F) -> Result<Outcome, WatchError>
+where
+    F: FnMut() -> Result<String, E> + Send + 'static,
+    E: std::fmt::Display,
+{
+    Watcher::from_fn("<fn>", source).interval(interval).run()
 }
@@ -0,0 +1,101 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{Result, Write},
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Local};
+
+use crate::exec::ExecutionResult;
+
+/// Appends a record of every execution to a log file, rotating it once it grows too large.
+#[derive(Debug)]
+pub(crate) struct LogSink {
+    path: PathBuf,
+    max_size: Option<u64>,
+    /// Opened on the first record, so configuring a watch never fails.
+    file: Option<File>,
+}
+
+impl LogSink {
+    pub fn new(path: PathBuf, max_size: Option<u64>) -> Self {
+        LogSink {
+            path,
+            max_size,
+            file: None,
+        }
+    }
+
+    pub fn record(&mut self, result: &ExecutionResult) -> Result<()> {
+        self.rotate_if_needed()?;
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => self.file.insert(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&self.path)?,
+            ),
+        };
+
+        write_record(file, result)?;
+        file.flush()
+    }
+
+    /// Moves the log to `<path>.1`, replacing any previous backup, once it exceeds `max_size`.
+    fn rotate_if_needed(&mut self) -> Result<()> {
+        let Some(max_size) = self.max_size else {
+            return Ok(());
+        };
+        match fs::metadata(&self.path) {
+            Ok(metadata) if metadata.len() >= max_size => {
+                // Close the current handle before renaming, which Windows requires
+                self.file = None;
+                let mut backup = self.path.clone().into_os_string();
+                backup.push(".1");
+                fs::rename(&self.path, backup)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Writes `result` to a new file at `path`, in the same format as the log, after a line naming
+/// the command.
+pub(crate) fn save_snapshot(path: &Path, command: &str, result: &ExecutionResult) -> Result<()> {
+    let mut file = File::create(path)?;
+    writeln!(file, "$ {}", command)?;
+    write_record(&mut file, result)?;
+    file.flush()
+}
+
+/// A file name for a snapshot of `result`, e.g. "watch-20240601-142233.txt".
+pub(crate) fn snapshot_name(result: &ExecutionResult) -> String {
+    let started_at: DateTime<Local> = result.started_at.into();
+    format!("watch-{}.txt", started_at.format("%Y%m%d-%H%M%S"))
+}
+
+/// Writes a header with the time, exit code, and duration of `result`, then its output.
+fn write_record(out: &mut impl Write, result: &ExecutionResult) -> Result<()> {
+    let started_at: DateTime<Local> = result.started_at.into();
+    let exit_code = match result.status.code() {
+        Some(code) => code.to_string(),
+        None if result.status.success() => "0".to_string(),
+        None => "none".to_string(),
+    };
+    writeln!(
+        out,
+        "=== {} exit={} duration={}ms ===",
+        started_at.to_rfc3339(),
+        exit_code,
+        result.duration.as_millis()
+    )?;
+    if !result.stdout.is_empty() {
+        writeln!(out, "{}", result.stdout)?;
+    }
+    if !result.stderr.is_empty() {
+        writeln!(out, "--- stderr ---")?;
+        writeln!(out, "{}", result.stderr)?;
+    }
+    Ok(())
+}
@@ -1,31 +1,654 @@
+mod config;
+mod session;
+
+use std::collections::VecDeque;
 use std::fmt::Debug;
-use std::io::Result;
+use std::fs;
+use std::io::{self, Error, ErrorKind, Result};
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use clap::{crate_authors, CommandFactory, Parser, Subcommand};
+use clap_complete::Shell as CompletionShell;
+use config::Config;
+use regex::Regex;
+use session::Session;
+use watch_rs::{
+    replay, AlertRule, ContainerRuntime, Cron, Differences, Encoding, FirstRun, HighlightRule,
+    JqFilter, Layout, Notify, OutputMode, Pane, Shell, TableFormat, Theme, Truncation, WatchError,
+    Watcher, MIN_INTERVAL,
+};
+#[cfg(unix)]
+use watch_rs::{Group, User};
 
-use clap::{command, crate_authors, Parser};
-use watch_rs::watch;
+/// The name the executable is installed as, which completions and the man page are for.
+const BIN_NAME: &str = "watchr";
 
 #[derive(Parser, Debug)]
 #[command(version, author = crate_authors!(), about, long_about = None)]
-#[command(help_template("\
+#[command(after_help = "\
+Exit status:
+  0    You quit, or --chgexit, --until, or --while ended the watch
+  N    The command's exit status, when --errexit or --count ended the watch
+  124  --for ran out, or the last run timed out
+  130  Interrupted by a signal")]
+#[command(help_template(
+    "\
 {before-help}{name} {version}
 Author: {author-with-newline}{about-with-newline}
 {usage-heading} {usage}
 
 {all-args}{after-help}
-"))]
+"
+))]
+#[command(
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true,
+    disable_help_subcommand = true
+)]
 struct Args {
-    /// The interval to run the command, in seconds
-    #[arg(name = "interval", short, long, value_name="sec", default_value = "5")]
-    interval: u64,
+    #[command(subcommand)]
+    generate: Option<Generate>,
+    /// The interval to run the command, in seconds (fractions allowed, minimum 0.1) [default: 5,
+    /// or only on file changes with --watch-path]
+    #[arg(name = "interval", short, short_alias = 'n', long, value_name="sec", value_parser = parse_interval)]
+    interval: Option<f64>,
+    /// Run the command when this crontab-style schedule matches instead of on an interval, e.g.
+    /// "*/5 * * * *"
+    #[arg(
+        name = "cron",
+        long,
+        value_name = "expression",
+        conflicts_with = "interval"
+    )]
+    cron: Option<Cron>,
+    /// Run on a fixed cadence from the start time, instead of an interval after each run finishes
+    #[arg(name = "precise", short, long)]
+    precise: bool,
+    /// While the command keeps failing, double the interval after each failure, up to this many
+    /// seconds [default: 300]
+    #[arg(
+        name = "backoff",
+        long,
+        value_name = "max sec",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "300",
+        value_parser = parse_interval
+    )]
+    backoff: Option<f64>,
+    /// Until the command first succeeds, retry it every this many seconds instead of on the
+    /// interval [default: 1]
+    #[arg(
+        name = "wait-first-success",
+        long,
+        value_name = "sec",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "1",
+        value_parser = parse_interval
+    )]
+    wait_first_success: Option<f64>,
+    /// Also run the command when files under this path change; can be repeated
+    #[arg(name = "watch-path", long, value_name = "path")]
+    watch_paths: Vec<PathBuf>,
+    /// How long files must be quiet after a change before running, in seconds
+    #[arg(name = "debounce", long, value_name = "sec", default_value = "0.2", value_parser = parse_interval)]
+    debounce: f64,
+    /// Exit when the output of the command changes
+    #[arg(name = "chgexit", short = 'g', long)]
+    chgexit: bool,
+    /// Exit after running the command this many times
+    #[arg(name = "count", long, value_name = "runs", value_parser = clap::value_parser!(u64).range(1..))]
+    count: Option<u64>,
+    /// Wait before the first run instead of starting right away: one interval, or this long, e.g.
+    /// "--delay-start=10s"
+    #[arg(
+        name = "delay-start",
+        long,
+        value_name = "duration",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "interval",
+        value_parser = parse_first_run
+    )]
+    delay_start: Option<FirstRun>,
+    /// Stop watching after this long, e.g. "30s", "5m", or "1h30m"
+    #[arg(name = "for", long, value_name = "duration", value_parser = parse_duration)]
+    run_for: Option<Duration>,
+    /// Exit once the command's stdout matches this regular expression
+    #[arg(name = "until", long, value_name = "regex")]
+    until: Option<Regex>,
+    /// Exit once the command's stdout no longer matches this regular expression
+    #[arg(name = "while", long, value_name = "regex")]
+    while_matches: Option<Regex>,
+    /// Only show the lines of output that match this regular expression
+    #[arg(long, value_name = "regex")]
+    grep: Option<Regex>,
+    /// Hide the lines of output that match this regular expression
+    #[arg(long, value_name = "regex")]
+    grep_v: Option<Regex>,
+    /// Highlight the differences between successive updates
+    #[arg(
+        name = "differences",
+        short,
+        long,
+        value_name = "mode",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "transient"
+    )]
+    differences: Option<Differences>,
+    /// Freeze updates when the command exits with a non-zero status, and exit after a key press
+    #[arg(name = "errexit", short, long)]
+    errexit: bool,
+    /// Beep when the command exits with a non-zero status
+    #[arg(name = "beep", short, long)]
+    beep: bool,
+    /// When the command prints nothing, keep its last output on screen instead of blanking it
+    #[arg(name = "quiet-empty", long)]
+    quiet_empty: bool,
+    /// Run the command directly instead of through a shell
+    #[arg(name = "exec", short = 'x', long)]
+    exec: bool,
+    /// Pipe the command's output through this shell command before showing it, e.g. "sort | head"
+    #[arg(name = "pipe", long, value_name = "command")]
+    pipe: Option<String>,
+    /// The shell and flags used to run the command, e.g. "bash -c" or "cmd /S /C"
+    #[arg(name = "shell", long, value_name = "program flag", env = "WATCH_SHELL")]
+    shell: Option<Shell>,
+    /// Set an environment variable for the command; can be repeated
+    #[arg(name = "env", long, value_name = "KEY=VALUE", value_parser = parse_env_var)]
+    env: Vec<(String, String)>,
+    /// Set environment variables for the command from a file of KEY=VALUE lines
+    #[arg(name = "env-file", long, value_name = "path")]
+    env_file: Option<PathBuf>,
+    /// Run the command in this directory
+    #[arg(name = "cwd", long, value_name = "dir", value_parser = parse_dir)]
+    cwd: Option<PathBuf>,
+    /// Run the command on this host over SSH, e.g. user@host
+    #[arg(name = "ssh", long, value_name = "destination")]
+    ssh: Option<String>,
+    /// Pass this option to ssh as -o <option>, e.g. Port=2222; can be repeated
+    #[arg(name = "ssh-option", long, value_name = "option", requires = "ssh")]
+    ssh_options: Vec<String>,
+    /// Run the command in this running container, with docker exec or podman exec
+    #[arg(name = "container", long, value_name = "name", conflicts_with = "ssh")]
+    container: Option<String>,
+    /// The container runtime to use: docker or podman; detected from PATH by default
+    #[arg(
+        name = "container-runtime",
+        long,
+        value_name = "runtime",
+        requires = "container"
+    )]
+    container_runtime: Option<ContainerRuntime>,
+    /// Kill the command if it runs longer than this many seconds
+    #[arg(name = "timeout", long, value_name = "sec", value_parser = parse_interval)]
+    timeout: Option<f64>,
+    /// Turn off the header and footer
+    #[arg(name = "no-title", short = 't', long)]
+    no_title: bool,
+    /// Number the lines of output, marking changed lines when highlighting differences
+    #[arg(name = "line-numbers", long)]
+    line_numbers: bool,
+    /// Scroll with the mouse wheel, click the header to pause, and click a pane to focus it
+    #[arg(name = "mouse", long)]
+    mouse: bool,
+    /// Clip long lines at the terminal width instead of wrapping them
+    #[arg(name = "no-wrap", long)]
+    no_wrap: bool,
+    /// Show stderr mixed into the output, line by line in the order it was printed
+    #[arg(name = "interleave", long)]
+    interleave: bool,
+    /// Show a dashboard above the output with the runs' successes, failures, and durations
+    #[arg(name = "stats", long)]
+    stats: bool,
+    /// The command '|' pipes the output into [default: $PAGER, or less]
+    #[arg(name = "pager", long, value_name = "command")]
+    pager: Option<String>,
+    /// Fill in {i} (the run's number), {ts} (Unix time), and {prev_exit} in the command each run
+    #[arg(name = "template", long)]
+    template: bool,
+    /// Serve Prometheus metrics about the runs at http://<host>:<port>/metrics
+    #[cfg(feature = "metrics")]
+    #[arg(name = "metrics-port", long, value_name = "port")]
+    metrics_port: Option<u16>,
+    /// Show the command's output as it arrives, instead of when it exits
+    #[arg(name = "stream", short = 's', long)]
+    stream: bool,
+    /// Forward keystrokes to the command while it runs, for commands that prompt (implies --stream)
+    #[arg(name = "interactive", long)]
+    interactive: bool,
+    /// Interpret ANSI color and style sequences (the default)
+    #[arg(name = "color", short, long, overrides_with = "no-color")]
+    color: bool,
+    /// Strip ANSI color and style sequences instead of interpreting them
+    #[arg(name = "no-color", long)]
+    no_color: bool,
+    /// Show control characters and escape sequences in the output as ^G, ^[ etc. instead of
+    /// dropping them
+    #[arg(name = "show-control-chars", long)]
+    show_control_chars: bool,
+    /// Expand tabs in the output to tab stops this many columns apart
+    #[arg(name = "tabs", long, value_name = "n", default_value = "8", value_parser = clap::value_parser!(u16).range(1..))]
+    tabs: u16,
+    /// Show the output as an aligned table, split into columns as csv or tsv, with the first row
+    /// as the header; press a column's number to sort by it
+    #[arg(name = "table", long, value_name = "format")]
+    table: Option<TableFormat>,
+    /// Pretty-print and color JSON output, before changes are detected
+    #[arg(name = "json", long, conflicts_with = "table")]
+    json: bool,
+    /// Show only the parts of the JSON output this jq-like path selects, e.g.
+    /// '.items[].status'; implies --json
+    #[arg(name = "jq", long, value_name = "filter", conflicts_with = "table")]
+    jq: Option<JqFilter>,
+    /// Color matches of a regex in the output, as "<regex>:<color>"; can be repeated
+    #[arg(name = "highlight", long, value_name = "regex:color")]
+    highlights: Vec<HighlightRule>,
+    /// Highlight lines where a number crosses a threshold, and beep and notify when it starts to,
+    /// as "<regex> <operator> <number>", e.g. 'load average: ([\d.]+) > 4'; can be repeated
+    #[arg(name = "alert", long, value_name = "rule")]
+    alerts: Vec<AlertRule>,
+    /// The colors and styles of the header, section titles, diff highlights, and footer: dark,
+    /// light, or none
+    #[arg(name = "theme", long, value_name = "name")]
+    theme: Option<Theme>,
+    /// The character encoding of the command's output
+    #[arg(name = "encoding", long, value_name = "name", default_value = "utf8")]
+    encoding: Encoding,
+    /// Keep at most this much of each of the command's stdout and stderr, e.g. "4M", dropping the
+    /// rest as it's read
+    #[arg(name = "max-output", long, value_name = "size", value_parser = parse_size)]
+    max_output: Option<u64>,
+    /// Which part of an output longer than --max-output to keep: head (the start) or tail (the
+    /// end)
+    #[arg(
+        name = "truncate",
+        long,
+        value_name = "part",
+        requires = "max-output",
+        default_value = "head"
+    )]
+    truncate: Truncation,
+    /// Append each run's timestamp, exit code, duration, and output to this file
+    #[arg(name = "log-file", long, value_name = "path")]
+    log_file: Option<PathBuf>,
+    /// Rotate the log file once it grows past this size, e.g. "10M"
+    #[arg(name = "log-max-size", long, value_name = "size", requires = "log-file", value_parser = parse_size)]
+    log_max_size: Option<u64>,
+    /// Write every run kept in the history to this directory on exit, or when 'x' is pressed, as a
+    /// file each and an index.json
+    #[arg(long, value_name = "dir")]
+    export: Option<PathBuf>,
+    /// Run the command as this user, by name or id, e.g. to drop root's privileges
+    #[cfg(unix)]
+    #[arg(long, value_name = "name")]
+    user: Option<User>,
+    /// Run the command in this group, by name or id, instead of the --user's primary group
+    #[cfg(unix)]
+    #[arg(long, value_name = "name")]
+    group: Option<Group>,
+    /// Record the session to this file in asciinema's format
+    #[arg(long, value_name = "path")]
+    record: Option<PathBuf>,
+    /// Read defaults for these options from this TOML file, instead of
+    /// ~/.config/watch-rs/config.toml (%APPDATA%\watch-rs\config.toml on Windows)
+    #[arg(long, value_name = "path")]
+    config: Option<PathBuf>,
+    /// Save the command, interval, highlights, and recent runs under this name, and resume them,
+    /// runs and all, when it's given again without a command
+    #[arg(long, value_name = "name", value_parser = session::parse_name, conflicts_with = "replay")]
+    session: Option<String>,
+    /// Play back a session recorded with --record instead of running a command
+    #[arg(long, value_name = "path", conflicts_with = "record")]
+    replay: Option<PathBuf>,
+    /// Print what would be run, with which environment, how often, and until when, then exit
+    #[arg(name = "dry-run", long)]
+    dry_run: bool,
+    /// Print the man page, in roff, instead of running a command
+    #[arg(long, hide = true)]
+    man: bool,
+    /// Show a desktop notification when the output changes, the command fails, or both
+    #[arg(
+        name = "notify",
+        long,
+        value_name = "event",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "both"
+    )]
+    notify: Option<Notify>,
+    /// Run this shell command whenever the output changes, with WATCH_OLD_OUTPUT,
+    /// WATCH_NEW_OUTPUT, and WATCH_EXIT_CODE set
+    #[arg(name = "on-change", long, value_name = "command")]
+    on_change: Option<String>,
+    /// How many past runs to keep for browsing with '[' and ']'
+    #[arg(name = "history", long, value_name = "runs", default_value = "50")]
+    history: usize,
+    /// How to present the output: the interactive TUI, one JSON object per run on stdout, or each
+    /// run's output appended to stdout, which is what the TUI falls back to when it isn't a terminal
+    #[arg(name = "output", long, value_name = "mode", default_value = "tui")]
+    output: OutputMode,
+    /// Also run this command after the watched one on every run, showing each one's output under
+    /// a heading; can be repeated
+    #[arg(name = "and", long, value_name = "command")]
+    and: Vec<String>,
+    /// Run the command once for each of these comma-separated items at the same time, with {} in
+    /// the command replaced by the item, showing each one's output under a heading with its status
+    #[arg(name = "foreach", long, value_name = "items", value_delimiter = ',')]
+    foreach: Vec<String>,
+    /// Also watch this command in its own pane, optionally on its own interval as "<sec>:<command>";
+    /// can be repeated
+    #[arg(name = "pane", long, value_name = "command")]
+    panes: Vec<Pane>,
+    /// How to arrange the panes: stacked (horizontal) or side by side (vertical)
+    #[arg(
+        name = "layout",
+        long,
+        value_name = "layout",
+        default_value = "horizontal"
+    )]
+    layout: Layout,
     /// The command to run
-    #[arg(name = "command", required = true)]
-    command: String,
+    #[arg(name = "command", required_unless_present_any = ["replay", "man", "session"])]
+    command: Option<String>,
     /// Any number of arguments to pass to the `command`
     #[arg(name = "args", required = false)]
     args: Vec<String>,
 }
 
+/// Generators that print something about watchr itself, instead of watching a command.
+#[derive(Subcommand, Debug)]
+enum Generate {
+    /// Print a completion script for bash, zsh, fish, or PowerShell
+    Completions {
+        #[arg(value_name = "shell")]
+        shell: CompletionShell,
+    },
+}
+
+/// Parses the `--interval` value, rejecting anything below [`MIN_INTERVAL`].
+fn parse_interval(s: &str) -> std::result::Result<f64, String> {
+    let interval: f64 = s.parse().map_err(|_| format!("`{s}` isn't a number"))?;
+    if !interval.is_finite() || interval < MIN_INTERVAL {
+        return Err(format!("must be at least {MIN_INTERVAL} seconds"));
+    }
+    Ok(interval)
+}
+
+/// Parses a size in bytes, with an optional K, M, or G (binary) suffix.
+fn parse_size(s: &str) -> std::result::Result<u64, String> {
+    let s = s.trim();
+    let (digits, multiplier) = match s.char_indices().last() {
+        Some((i, 'k' | 'K')) => (&s[..i], 1 << 10),
+        Some((i, 'm' | 'M')) => (&s[..i], 1 << 20),
+        Some((i, 'g' | 'G')) => (&s[..i], 1 << 30),
+        _ => (s, 1),
+    };
+    let size: u64 = digits
+        .trim()
+        .parse()
+        .map_err(|_| format!("`{s}` isn't a size like 512K or 10M"))?;
+    size.checked_mul(multiplier)
+        .ok_or_else(|| format!("`{s}` is too large"))
+}
+
+/// Parses a human duration made of number-unit pairs, e.g. "90s", "5m", or "1h30m".
+/// A bare number is taken as seconds.
+fn parse_duration(s: &str) -> std::result::Result<Duration, String> {
+    let invalid = || format!("`{s}` isn't a duration like 30s, 5m, or 1h30m");
+    let s = s.trim();
+    if let Ok(secs) = s.parse::<f64>() {
+        return Duration::try_from_secs_f64(secs).map_err(|_| invalid());
+    }
+
+    let mut total = 0.0;
+    let mut number = String::new();
+    for c in s.chars() {
+        let unit = match c {
+            '0'..='9' | '.' => {
+                number.push(c);
+                continue;
+            }
+            's' => 1.0,
+            'm' => 60.0,
+            'h' => 3600.0,
+            'd' => 86400.0,
+            _ => return Err(invalid()),
+        };
+        let value: f64 = number.parse().map_err(|_| invalid())?;
+        total += value * unit;
+        number.clear();
+    }
+    if !number.is_empty() || s.is_empty() {
+        return Err(invalid());
+    }
+    Duration::try_from_secs_f64(total).map_err(|_| invalid())
+}
+
+/// Parses the `--delay-start` value: "interval", or a duration like "10s".
+fn parse_first_run(s: &str) -> std::result::Result<FirstRun, String> {
+    match s {
+        "interval" => Ok(FirstRun::AfterInterval),
+        _ => parse_duration(s).map(FirstRun::After),
+    }
+}
+
+/// Parses the `--cwd` value, checking up front that it's an existing directory.
+fn parse_dir(s: &str) -> std::result::Result<PathBuf, String> {
+    let dir = PathBuf::from(s);
+    if !dir.is_dir() {
+        return Err(format!("`{s}` isn't a directory"));
+    }
+    Ok(dir)
+}
+
+/// Parses a `KEY=VALUE` pair for `--env`.
+fn parse_env_var(s: &str) -> std::result::Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("`{s}` isn't in KEY=VALUE form")),
+    }
+}
+
+/// Reads an env file: `KEY=VALUE` lines, with blank lines, `#` comments, an optional `export`
+/// prefix, and values optionally wrapped in matching quotes.
+fn read_env_file(path: &Path) -> Result<Vec<(String, String)>> {
+    let contents = fs::read_to_string(path)?;
+    let mut vars = Vec::new();
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (key, value) = parse_env_var(line).map_err(|err| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("{}:{}: {}", path.display(), number + 1, err),
+            )
+        })?;
+        let value = value.trim();
+        let value = ['"', '\'']
+            .iter()
+            .find_map(|&quote| value.strip_prefix(quote)?.strip_suffix(quote))
+            .unwrap_or(value);
+        vars.push((key.trim().to_string(), value.to_string()));
+    }
+    Ok(vars)
+}
+
 fn main() -> Result<()> {
-    let args = Args::parse();
-    watch(args.command, args.args, args.interval)
+    let mut args = Args::parse();
+    if let Some(Generate::Completions { shell }) = args.generate {
+        clap_complete::generate(shell, &mut Args::command(), BIN_NAME, &mut io::stdout());
+        return Ok(());
+    }
+    if args.man {
+        return clap_mangen::Man::new(Args::command().name(BIN_NAME)).render(&mut io::stdout());
+    }
+    if let Some(recording) = args.replay {
+        return replay(recording).or_else(|err| fail(err));
+    }
+    let mut session = match args.session.clone() {
+        Some(name) => Some(Session::resume(&name, &mut args)?),
+        None => None,
+    };
+    let config = Config::load(args.config.as_deref())?;
+    let keymap = config.keymap();
+    let theme = config.theme(args.theme.take());
+    config.apply(&mut args);
+    let mut watcher = Watcher::new(args.command.unwrap_or_default());
+    if let Some(shell) = args.shell {
+        watcher = watcher.shell(shell);
+    }
+    if let Some(env_file) = &args.env_file {
+        watcher = watcher.envs(read_env_file(env_file)?);
+    }
+    // Watching paths replaces the interval unless one was given explicitly
+    let on_interval = args.interval.is_some() || args.watch_paths.is_empty();
+    for path in args.watch_paths {
+        watcher = watcher.watch_path(path);
+    }
+    for rule in args.highlights {
+        watcher = watcher.highlight(rule);
+    }
+    for rule in args.alerts {
+        watcher = watcher.alert(rule);
+    }
+    for command in args.and {
+        watcher = watcher.and(command);
+    }
+    for pane in args.panes {
+        watcher = watcher.pane(pane);
+    }
+    if let Some(cwd) = args.cwd {
+        watcher = watcher.current_dir(cwd);
+    }
+    if let Some(destination) = args.ssh {
+        watcher = watcher.ssh(destination);
+    }
+    for option in args.ssh_options {
+        watcher = watcher.ssh_option(option);
+    }
+    if let Some(name) = args.container {
+        let runtime = args
+            .container_runtime
+            .unwrap_or_else(ContainerRuntime::detect);
+        watcher = watcher.container(name, runtime);
+    }
+    #[cfg(unix)]
+    {
+        watcher = watcher.user(args.user).group(args.group);
+    }
+    if let Some(dir) = args.export {
+        watcher = watcher.export(dir);
+    }
+    if let Some(record) = args.record {
+        watcher = watcher.record(record);
+    }
+    if let Some(log_file) = args.log_file {
+        watcher = watcher.log_file(log_file).log_max_size(args.log_max_size);
+    }
+    #[cfg(feature = "metrics")]
+    if let Some(port) = args.metrics_port {
+        watcher = watcher.metrics_port(Some(port));
+    }
+    // The runs to save with the session, starting with the ones it was resumed with
+    let kept = Arc::new(Mutex::new(VecDeque::new()));
+    if let Some(session) = session.as_mut() {
+        let capacity = args.history.max(1);
+        let preloaded = session.take_runs();
+        kept.lock()
+            .unwrap()
+            .extend(preloaded.iter().rev().take(capacity).rev().cloned());
+        let kept = Arc::clone(&kept);
+        watcher = watcher.preload(preloaded).on_output(move |result| {
+            let mut kept = kept.lock().unwrap();
+            if kept.len() == capacity {
+                kept.pop_front();
+            }
+            kept.push_back(result.clone());
+            ControlFlow::Continue(())
+        });
+    }
+    let watcher = watcher
+        .args(args.args)
+        .foreach(args.foreach)
+        .envs(args.env)
+        .layout(args.layout)
+        .keymap(keymap)
+        .theme(theme)
+        .interval(Duration::from_secs_f64(args.interval.unwrap_or(5.0)))
+        .on_interval(on_interval)
+        .cron(args.cron)
+        .first_run(args.delay_start.unwrap_or_default())
+        .debounce(Duration::from_secs_f64(args.debounce))
+        .precise(args.precise)
+        .backoff(args.backoff.map(Duration::from_secs_f64))
+        .wait_first_success(args.wait_first_success.map(Duration::from_secs_f64))
+        .chgexit(args.chgexit)
+        .count(args.count)
+        .run_for(args.run_for)
+        .until(args.until)
+        .while_matches(args.while_matches)
+        .grep(args.grep)
+        .grep_v(args.grep_v)
+        .differences(args.differences)
+        .color(!args.no_color)
+        .show_control_chars(args.show_control_chars)
+        .tabs(args.tabs.into())
+        .table(args.table)
+        .json(args.json)
+        .jq(args.jq)
+        .errexit(args.errexit)
+        .beep(args.beep)
+        .quiet_empty(args.quiet_empty)
+        .exec(args.exec)
+        .pipe(args.pipe)
+        .timeout(args.timeout.map(Duration::from_secs_f64))
+        .title(!args.no_title)
+        .wrap(!args.no_wrap)
+        .interleave(args.interleave)
+        .line_numbers(args.line_numbers)
+        .mouse(args.mouse)
+        .stats(args.stats)
+        .encoding(args.encoding)
+        .max_output(args.max_output)
+        .truncation(args.truncate)
+        .stream(args.stream)
+        .interactive(args.interactive)
+        .output(args.output)
+        .history(args.history)
+        .notify(args.notify)
+        .on_change_command(args.on_change)
+        .pager(args.pager)
+        .template(args.template);
+    if args.dry_run {
+        print!("{}", watcher.plan());
+        return Ok(());
+    }
+    let outcome = watcher.run();
+    if let Some(session) = session {
+        if let Err(err) = session.save(kept.lock().unwrap().make_contiguous()) {
+            eprintln!("Error: couldn't save the session: {}", err);
+        }
+    }
+    let outcome = outcome.unwrap_or_else(|err| fail(err));
+    std::process::exit(outcome.exit_code());
+}
+
+/// Reports `err` and exits, with the command's own exit code if it failed.
+fn fail(err: WatchError) -> ! {
+    match err {
+        WatchError::Command { .. } => {
+            eprintln!("Command failed with exit code {}", err.exit_code())
+        }
+        _ => eprintln!("Error: {}", err),
+    }
+    std::process::exit(err.exit_code());
 }
@@ -0,0 +1,167 @@
+use std::{
+    fmt::Write as _,
+    io::{BufRead, BufReader, Result, Write},
+    net::{TcpListener, TcpStream},
+    ops::ControlFlow,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use crate::exec::ExecutionResult;
+use crate::hooks::Hook;
+use crate::outcome::status_code;
+
+/// The upper bounds, in seconds, of the execution duration histogram's buckets.
+const BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// How long a scrape may take to send its request before it's dropped, so a stalled client can't
+/// hold up the others.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The counters exposed to Prometheus, across the whole watch.
+#[derive(Debug, Default)]
+struct Metrics {
+    runs: u64,
+    failures: u64,
+    changes: u64,
+    last_exit_code: i32,
+    /// When the last execution started, in seconds since the Unix epoch.
+    last_run: f64,
+    /// How many executions fell in each of [`BUCKETS`], cumulatively.
+    buckets: [u64; BUCKETS.len()],
+    duration_sum: f64,
+}
+
+impl Metrics {
+    fn record(&mut self, result: &ExecutionResult) {
+        self.runs += 1;
+        if !result.status.success() {
+            self.failures += 1;
+        }
+        if result.changed {
+            self.changes += 1;
+        }
+        self.last_exit_code = status_code(result.status);
+        self.last_run = result
+            .started_at
+            .duration_since(UNIX_EPOCH)
+            .map_or(0.0, |since| since.as_secs_f64());
+        let duration = result.duration.as_secs_f64();
+        for (bucket, bound) in self.buckets.iter_mut().zip(BUCKETS) {
+            if duration <= bound {
+                *bucket += 1;
+            }
+        }
+        self.duration_sum += duration;
+    }
+
+    /// The metrics in Prometheus' text exposition format.
+    fn render(&self) -> String {
+        let mut text = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, value: String| {
+            let _ = writeln!(text, "# HELP {name} {help}");
+            let _ = writeln!(text, "# TYPE {name} {kind}");
+            let _ = writeln!(text, "{name} {value}");
+        };
+        metric(
+            "watchr_runs_total",
+            "counter",
+            "Executions of the command.",
+            self.runs.to_string(),
+        );
+        metric(
+            "watchr_failures_total",
+            "counter",
+            "Executions that exited unsuccessfully.",
+            self.failures.to_string(),
+        );
+        metric(
+            "watchr_changes_total",
+            "counter",
+            "Executions whose output differed from the previous one.",
+            self.changes.to_string(),
+        );
+        metric(
+            "watchr_last_exit_code",
+            "gauge",
+            "Exit code of the last execution, 128 plus the signal's number if it was killed.",
+            self.last_exit_code.to_string(),
+        );
+        metric(
+            "watchr_last_run_timestamp_seconds",
+            "gauge",
+            "When the last execution started, in seconds since the Unix epoch.",
+            self.last_run.to_string(),
+        );
+
+        let name = "watchr_run_duration_seconds";
+        let _ = writeln!(text, "# HELP {name} How long executions took.");
+        let _ = writeln!(text, "# TYPE {name} histogram");
+        for (count, bound) in self.buckets.iter().zip(BUCKETS) {
+            let _ = writeln!(text, "{name}_bucket{{le=\"{bound}\"}} {count}");
+        }
+        let _ = writeln!(text, "{name}_bucket{{le=\"+Inf\"}} {}", self.runs);
+        let _ = writeln!(text, "{name}_sum {}", self.duration_sum);
+        let _ = writeln!(text, "{name}_count {}", self.runs);
+        text
+    }
+}
+
+/// Starts serving metrics about the executions at `/metrics` on `port`, on every interface, from
+/// a background thread. Returns the hook that records each execution.
+pub(crate) fn serve(port: u16) -> Result<Hook> {
+    let listener = TcpListener::bind(("0.0.0.0", port))?;
+    let metrics = Arc::new(Mutex::new(Metrics::default()));
+    let served = Arc::clone(&metrics);
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            // A client that goes away mid-request only affects itself
+            let _ = respond(stream, &served);
+        }
+    });
+    Ok(Box::new(move |result| {
+        metrics
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .record(result);
+        ControlFlow::Continue(())
+    }))
+}
+
+/// Answers one HTTP request: the metrics for `GET /metrics`, and 404 for anything else.
+fn respond(stream: TcpStream, metrics: &Mutex<Metrics>) -> Result<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Skip the headers, up to the blank line ending them
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 0 && !header.trim_end().is_empty() {
+        header.clear();
+    }
+
+    let path = request_line.split_whitespace().nth(1).unwrap_or_default();
+    let (status, body) = match path.split('?').next() {
+        Some("/metrics") => (
+            "200 OK",
+            metrics
+                .lock()
+                .unwrap_or_else(|err| err.into_inner())
+                .render(),
+        ),
+        _ => (
+            "404 Not Found",
+            "Metrics are served at /metrics\n".to_string(),
+        ),
+    };
+    let mut stream = &stream;
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
@@ -0,0 +1,73 @@
+use std::thread;
+
+use notify_rust::Notification;
+
+use crate::alert::AlertRule;
+use crate::exec::ExecutionResult;
+use crate::watcher::describe_status;
+
+/// Which events raise a desktop notification.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Notify {
+    /// When the output differs from the previous execution.
+    Change,
+    /// When the command exits with a non-zero status or times out.
+    Error,
+    /// Both of the above.
+    Both,
+}
+
+impl Notify {
+    pub(crate) fn on_change(self) -> bool {
+        matches!(self, Notify::Change | Notify::Both)
+    }
+
+    pub(crate) fn on_error(self) -> bool {
+        matches!(self, Notify::Error | Notify::Both)
+    }
+}
+
+/// Shows a desktop notification about `result` on a background thread, so a slow or missing
+/// notification daemon never stalls the watch. Failures to notify are ignored.
+pub(crate) fn send(command: &str, result: &ExecutionResult) {
+    let summary = if result.status.success() {
+        format!("Output changed: {}", command)
+    } else {
+        format!("Command failed: {}", command)
+    };
+    let body = if result.status.success() {
+        last_line(&result.stdout)
+    } else {
+        let detail = last_line(if result.stderr.is_empty() {
+            &result.stdout
+        } else {
+            &result.stderr
+        });
+        format!("{}\n{}", describe_status(result.status), detail)
+    };
+    show(summary, body);
+}
+
+/// Shows a desktop notification that `line` of `command`'s output crossed `rule`'s threshold.
+pub(crate) fn send_alert(command: &str, rule: &AlertRule, line: &str) {
+    show(
+        format!("Alert: {}", command),
+        format!("{}\n{}", line.trim(), rule),
+    );
+}
+
+/// Shows a notification on a background thread, ignoring failures.
+fn show(summary: String, body: String) {
+    thread::spawn(move || {
+        let _ = Notification::new()
+            .appname("watch-rs")
+            .summary(&summary)
+            .body(&body)
+            .show();
+    });
+}
+
+/// The last line of `text`, which for most commands is the most relevant one.
+fn last_line(text: &str) -> String {
+    text.lines().last().unwrap_or_default().to_string()
+}
@@ -0,0 +1,81 @@
+use crate::exec::{ExecutionResult, Status};
+
+/// Why a watch stopped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExitReason {
+    /// The user pressed 'q' or Ctrl+C.
+    Quit,
+    /// The process received a signal (a console control event on Windows) asking it to stop.
+    Interrupted,
+    /// The output changed, with [`Watcher::chgexit`](crate::Watcher::chgexit).
+    Changed,
+    /// The output matched [`Watcher::until`](crate::Watcher::until).
+    Until,
+    /// The output stopped matching [`Watcher::while_matches`](crate::Watcher::while_matches).
+    While,
+    /// The command ran [`Watcher::count`](crate::Watcher::count) times.
+    Count,
+    /// The [`Watcher::run_for`](crate::Watcher::run_for) budget ran out.
+    Duration,
+    /// The command failed, with [`Watcher::errexit`](crate::Watcher::errexit). This ends a
+    /// [`WatchStream`](crate::WatchStream), while [`Watcher::run`](crate::Watcher::run) returns
+    /// [`WatchError::Command`](crate::WatchError::Command) instead.
+    Failed,
+    /// A hook returned [`ControlFlow::Break`](std::ops::ControlFlow::Break).
+    Hook,
+}
+
+/// How a watch ended, as returned by [`Watcher::run`](crate::Watcher::run).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Outcome {
+    pub reason: ExitReason,
+    /// The last execution, or `None` if the watch stopped before the command first finished.
+    pub last: Option<ExecutionResult>,
+}
+
+impl Outcome {
+    /// The last execution's stdout, or an empty string if there wasn't one.
+    pub fn stdout(&self) -> &str {
+        self.last.as_ref().map_or("", |last| &last.stdout)
+    }
+
+    /// The exit code for a process reporting this outcome, as `watchr` uses:
+    ///
+    /// * 0 when the user quit, or an exit condition was met: [`ExitReason::Changed`],
+    ///   [`ExitReason::Until`], [`ExitReason::While`], or [`ExitReason::Hook`].
+    /// * The last execution's exit code for [`ExitReason::Count`] and [`ExitReason::Failed`].
+    ///   A command killed by a signal gives 128 plus the signal's number, like in a shell, and one
+    ///   killed for running past its timeout gives 124, like `timeout`.
+    /// * 124 when the [`ExitReason::Duration`] budget ran out before anything else stopped the
+    ///   watch, e.g. before `--until` matched.
+    /// * 130 when [`ExitReason::Interrupted`].
+    pub fn exit_code(&self) -> i32 {
+        match self.reason {
+            ExitReason::Quit
+            | ExitReason::Changed
+            | ExitReason::Until
+            | ExitReason::While
+            | ExitReason::Hook => 0,
+            ExitReason::Count | ExitReason::Failed => self
+                .last
+                .as_ref()
+                .map_or(0, |last| status_code(last.status)),
+            ExitReason::Duration => 124,
+            ExitReason::Interrupted => 130,
+        }
+    }
+}
+
+/// The exit code a shell would report for a command that ended with `status`.
+pub(crate) fn status_code(status: Status) -> i32 {
+    match status {
+        Status::Exited(status) | Status::Unreachable(status) => {
+            #[cfg(unix)]
+            if let Some(signal) = std::os::unix::process::ExitStatusExt::signal(&status) {
+                return 128 + signal;
+            }
+            status.code().unwrap_or(1)
+        }
+        Status::TimedOut(_) => 124,
+    }
+}
@@ -0,0 +1,70 @@
+use crossterm::style::ContentStyle;
+
+use crate::viewport::{Line, Span};
+use crate::width::{ellipsize, fit, text_width, truncate};
+
+/// Blank columns between the text and the sides of the box, and between columns of text.
+const PADDING: usize = 1;
+const GUTTER: usize = 3;
+
+/// A box drawn over the middle of the output until the next key press, like the help.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Overlay {
+    /// Shown in the top of the border.
+    pub title: String,
+    pub lines: Vec<String>,
+}
+
+impl Overlay {
+    /// The box's rows, centered in an area of `width` by `height`, and the column and row of its
+    /// top left corner in the area. Lines that don't fit the height go on in another column, and
+    /// whatever doesn't fit the width is cut off.
+    pub fn layout(&self, width: u16, height: u16, border: ContentStyle) -> (u16, u16, Vec<Line>) {
+        let (width, height) = (width as usize, height as usize);
+        if width < 2 * (PADDING + 1) || height < 3 {
+            return (0, 0, Vec::new());
+        }
+        let per_column = self.lines.len().clamp(1, height - 2);
+        let columns: Vec<&[String]> = self.lines.chunks(per_column).collect();
+        // Lines are cut short rather than leaving columns out
+        let room = (width - 2 - 2 * PADDING)
+            .saturating_sub(columns.len().saturating_sub(1) * GUTTER)
+            / columns.len().max(1);
+        let column_width = self
+            .lines
+            .iter()
+            .map(|line| text_width(line))
+            .max()
+            .unwrap_or(0)
+            .min(room);
+        let text_columns = columns.len() * column_width + columns.len().saturating_sub(1) * GUTTER;
+        let title = format!("─ {} ", self.title);
+        let inner = (text_columns + 2 * PADDING)
+            .max(text_width(&title) + 1)
+            .min(width - 2);
+
+        let top = truncate(&title, inner);
+        let mut rows = vec![vec![Span::new(
+            border,
+            format!("┌{}{}┐", top, "─".repeat(inner - text_width(top))),
+        )]];
+        for i in 0..per_column {
+            let text: Vec<String> = columns
+                .iter()
+                .filter_map(|column| column.get(i))
+                .map(|line| fit(&ellipsize(line, column_width), column_width))
+                .collect();
+            let text = format!("{}{}", " ".repeat(PADDING), text.join(&" ".repeat(GUTTER)));
+            rows.push(vec![
+                Span::new(border, "│"),
+                Span::new(ContentStyle::new(), fit(&text, inner)),
+                Span::new(border, "│"),
+            ]);
+        }
+        rows.push(vec![Span::new(border, format!("└{}┘", "─".repeat(inner)))]);
+
+        let left = (width - inner - 2) / 2;
+        let top = (height - rows.len()) / 2;
+        (left as u16, top as u16, rows)
+    }
+}
@@ -0,0 +1,39 @@
+use std::{
+    env,
+    io::{self, Result, Write},
+    process::Stdio,
+    thread,
+};
+
+use crate::shell::Shell;
+
+/// The pager used unless [`Watcher::pager`](crate::Watcher::pager) says otherwise: `$PAGER`, or
+/// `less` (`more` on Windows).
+pub(crate) fn default_pager() -> String {
+    match env::var("PAGER") {
+        Ok(pager) if !pager.trim().is_empty() => pager,
+        _ if cfg!(windows) => "more".to_string(),
+        _ => "less".to_string(),
+    }
+}
+
+/// Runs `command` through `shell` with `text` on its stdin and the terminal for everything else,
+/// until it exits. The terminal must be out of raw mode, see [`TerminalGuard::suspend`].
+///
+/// [`TerminalGuard::suspend`]: crate::terminal::TerminalGuard::suspend
+pub(crate) fn page(shell: &Shell, command: &str, text: &str) -> Result<()> {
+    let mut child = shell.command(command).stdin(Stdio::piped()).spawn()?;
+    let mut stdin = child.stdin.take();
+    let text = text.to_string();
+    // Feed it from another thread, since a pager only reads as far as the user scrolls
+    let writer = thread::spawn(move || match stdin.as_mut() {
+        Some(stdin) => stdin.write_all(text.as_bytes()),
+        None => Ok(()),
+    });
+    child.wait()?;
+    match writer.join() {
+        // Quitting before reading everything closes the pipe early, which is fine
+        Ok(Err(err)) if err.kind() != io::ErrorKind::BrokenPipe => Err(err),
+        _ => Ok(()),
+    }
+}
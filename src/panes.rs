@@ -0,0 +1,415 @@
+use std::{
+    io::Result,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use chrono::Local;
+use crossterm::{
+    cursor::MoveToColumn,
+    event::{poll, read},
+    queue,
+    style::{PrintStyledContent, StyledContent, Stylize},
+};
+
+use crate::error::WatchError;
+use crate::exec::{self, ExecutionResult, Step};
+use crate::input::Action;
+use crate::outcome::{ExitReason, Outcome};
+use crate::render::{Area, Renderer, Target};
+use crate::signal;
+use crate::template::Vars;
+use crate::terminal::TerminalGuard;
+use crate::theme::Theme;
+use crate::viewport::Viewport;
+use crate::watcher::{describe_status, print_final_output, step_interval, Watcher};
+use crate::width::{fit, text_width};
+use crate::MIN_INTERVAL;
+
+/// How often running panes are checked for completion, and input is handled, in pane mode.
+const PANE_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+const PANES_FOOTER: &str = "Tab to switch panes, 'q' to exit";
+
+/// How panes are arranged when watching several commands.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Layout {
+    /// Stacked top to bottom, each spanning the full width.
+    #[default]
+    Horizontal,
+    /// Side by side, each spanning the full height.
+    Vertical,
+}
+
+/// An additional command watched in its own pane, see [`Watcher::pane`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pane {
+    command: String,
+    interval: Option<Duration>,
+}
+
+impl Pane {
+    /// Creates a pane running `command` through the shell, on the watcher's interval by default.
+    pub fn new(command: impl Into<String>) -> Self {
+        Pane {
+            command: command.into(),
+            interval: None,
+        }
+    }
+
+    /// Runs this pane on its own interval. Anything below [`MIN_INTERVAL`] is clamped up to it.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = Some(interval.max(Duration::from_secs_f64(MIN_INTERVAL)));
+        self
+    }
+}
+
+/// Parses a `"[<seconds>:]<command>"` specification, e.g. `"free -m"` or `"10:df -h"`.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use watch_rs::Pane;
+///
+/// let pane: Pane = "10:df -h".parse().unwrap();
+/// assert_eq!(pane, Pane::new("df -h").interval(Duration::from_secs(10)));
+/// assert_eq!("free -m".parse::<Pane>().unwrap(), Pane::new("free -m"));
+/// ```
+impl FromStr for Pane {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let pane = match s.split_once(':') {
+            Some((secs, command)) => match secs.trim().parse::<f64>() {
+                Ok(secs) if secs.is_finite() && secs >= 0.0 => {
+                    Pane::new(command.trim()).interval(Duration::from_secs_f64(secs))
+                }
+                _ => Pane::new(s),
+            },
+            None => Pane::new(s),
+        };
+        if pane.command.trim().is_empty() {
+            return Err("the pane's command can't be empty".to_string());
+        }
+        Ok(pane)
+    }
+}
+
+/// A pane's command and what it's currently showing.
+struct PaneState {
+    command: String,
+    /// Whether this is the watcher's own command, which honors [`Watcher::exec`].
+    primary: bool,
+    interval: Duration,
+    viewport: Viewport,
+    running: Option<exec::Running>,
+    last: Option<ExecutionResult>,
+    runs: u64,
+    due: Instant,
+}
+
+impl Layout {
+    /// Splits the `width` x `height` region starting at row `top` into `count` areas, with a
+    /// column between side-by-side panes. Each pane's title row is at the top of its area.
+    fn areas(self, count: usize, width: u16, top: u16, height: u16) -> Vec<Area> {
+        let count = count.max(1) as u32;
+        // Compute in u32 so `size * i` can't overflow
+        let split = |size: u16, i: u32| (u32::from(size) * i / count) as u16;
+        (0..count)
+            .map(|i| match self {
+                Layout::Horizontal => {
+                    let (start, end) = (split(height, i), split(height, i + 1));
+                    Area {
+                        x: 0,
+                        y: top + start,
+                        width,
+                        height: end - start,
+                    }
+                }
+                Layout::Vertical => {
+                    let (start, end) = (split(width, i), split(width, i + 1));
+                    let gap = u16::from(i + 1 < count);
+                    Area {
+                        x: start,
+                        y: top,
+                        width: (end - start).saturating_sub(gap),
+                        height,
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+impl Watcher {
+    /// Watches the watcher's command and every [`Watcher::pane`] side by side until the user quits.
+    pub(crate) fn run_panes(mut self) -> std::result::Result<Outcome, WatchError> {
+        let hostname = self.hostname();
+        let now = Instant::now();
+        let mut panes = vec![PaneState {
+            command: self.full_command().trim_end().to_string(),
+            primary: true,
+            interval: self.interval,
+            viewport: Viewport::new(),
+            running: None,
+            last: None,
+            runs: 0,
+            due: now,
+        }];
+        for pane in std::mem::take(&mut self.panes) {
+            panes.push(PaneState {
+                command: pane.command,
+                primary: false,
+                interval: pane.interval.unwrap_or(self.interval),
+                viewport: Viewport::new(),
+                running: None,
+                last: None,
+                runs: 0,
+                due: now,
+            });
+        }
+        for pane in &mut panes {
+            pane.viewport.set_theme(self.theme.clone());
+            pane.viewport.set_wrap(self.wrap);
+            pane.viewport.set_line_numbers(self.line_numbers);
+        }
+        let deadline = self.run_for.map(|run_for| now + run_for);
+        let mut focus = 0;
+        let mut paused = false;
+        let mut reason = ExitReason::Quit;
+
+        let terminal = TerminalGuard::enter().map_err(WatchError::Terminal)?;
+        if self.mouse {
+            terminal.capture_mouse().map_err(WatchError::Terminal)?;
+        }
+        let mut renderer = self.renderer();
+        loop {
+            let mut dirty = false;
+            for pane in &mut panes {
+                dirty |= self.step_pane(pane, paused)?;
+            }
+            if dirty {
+                draw_panes(
+                    &mut renderer,
+                    &mut panes,
+                    focus,
+                    self.layout,
+                    &hostname,
+                    paused,
+                    &self.theme,
+                )
+                .map_err(WatchError::Terminal)?;
+            }
+            if signal::cancelled() {
+                reason = ExitReason::Interrupted;
+                break;
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                reason = ExitReason::Duration;
+                break;
+            }
+
+            if !poll(PANE_POLL_INTERVAL).map_err(WatchError::Terminal)? {
+                continue;
+            }
+            match self
+                .keymap
+                .action_for(read().map_err(WatchError::Terminal)?)
+            {
+                Some(Action::Quit) => break,
+                Some(Action::FocusNext) => focus = (focus + 1) % panes.len(),
+                Some(Action::FocusPrevious) => focus = (focus + panes.len() - 1) % panes.len(),
+                Some(Action::Scroll(scroll)) => panes[focus].viewport.scroll(scroll),
+                Some(Action::ToggleWrap) => {
+                    let viewport = &mut panes[focus].viewport;
+                    viewport.set_wrap(!viewport.is_wrapping());
+                }
+                Some(Action::ToggleStderr) => {
+                    let viewport = &mut panes[focus].viewport;
+                    viewport.set_collapse_stderr(!viewport.is_collapsing_stderr());
+                }
+                Some(Action::Pin) => panes[focus].viewport.pin_top(),
+                Some(Action::UnpinAll) => panes[focus].viewport.unpin_all(),
+                Some(Action::Sort(column)) => panes[focus].viewport.sort_by(column),
+                Some(Action::TogglePause) => paused = !paused,
+                Some(Action::Click { row: 0, .. }) => paused = !paused,
+                Some(Action::Click { column, row }) => {
+                    let Area { width, height, .. } =
+                        renderer.area().map_err(WatchError::Terminal)?;
+                    let areas = self
+                        .layout
+                        .areas(panes.len(), width, 1, height.saturating_sub(2));
+                    if let Some(clicked) = areas.iter().position(|area| area.contains(column, row))
+                    {
+                        focus = clicked;
+                    }
+                }
+                Some(Action::Refresh) => {
+                    paused = false;
+                    for pane in &mut panes {
+                        pane.due = Instant::now();
+                    }
+                }
+                Some(Action::IncreaseInterval) => {
+                    panes[focus].interval = step_interval(panes[focus].interval, true);
+                }
+                Some(Action::DecreaseInterval) => {
+                    panes[focus].interval = step_interval(panes[focus].interval, false);
+                }
+                Some(_) => {}
+                None => continue,
+            }
+            draw_panes(
+                &mut renderer,
+                &mut panes,
+                focus,
+                self.layout,
+                &hostname,
+                paused,
+                &self.theme,
+            )
+            .map_err(WatchError::Terminal)?;
+        }
+        drop(terminal);
+
+        for pane in &panes {
+            if let Some(result) = &pane.last {
+                print_final_output(
+                    &pane.command,
+                    &result.stdout,
+                    &result.stderr,
+                    self.parse_options(),
+                )?;
+            }
+        }
+        Ok(Outcome {
+            reason,
+            last: panes.swap_remove(0).last,
+        })
+    }
+
+    /// Collects a pane's finished run, or starts the next one when it's due. Returns whether the
+    /// pane needs redrawing.
+    fn step_pane(
+        &self,
+        pane: &mut PaneState,
+        paused: bool,
+    ) -> std::result::Result<bool, WatchError> {
+        if let Some(running) = &mut pane.running {
+            let Some(status) = running.try_finish()? else {
+                return Ok(false);
+            };
+            let output = pane.running.take().unwrap().finish(status);
+            let result = self.decode(output, pane.last.as_ref());
+            pane.viewport
+                .set_lines(self.result_lines(&result, false, None));
+            pane.last = Some(result);
+            pane.runs += 1;
+            pane.due = Instant::now() + pane.interval;
+            return Ok(true);
+        }
+        if paused || Instant::now() < pane.due {
+            return Ok(false);
+        }
+        let steps = match pane.primary {
+            true => {
+                let vars = Vars::new(pane.runs + 1, pane.last.as_ref());
+                self.build_steps(&pane.command, &vars)
+            }
+            false => {
+                let mut command = self.shell_command(&pane.command);
+                self.switch_user(&mut command);
+                vec![Step::from(command)]
+            }
+        };
+        pane.running = Some(exec::Running::spawn(steps, self.timeout)?);
+        Ok(true)
+    }
+}
+
+/// Draws a one-line header, every pane with its own title row, and the footer.
+fn draw_panes(
+    renderer: &mut Renderer<Target>,
+    panes: &mut [PaneState],
+    focus: usize,
+    layout: Layout,
+    hostname: &str,
+    paused: bool,
+    theme: &Theme,
+) -> Result<()> {
+    let Area { width, height, .. } = renderer.area()?;
+    let mut canvas = renderer.canvas()?;
+
+    let title = format!("Watching {} commands", panes.len());
+    let host_msg = format!(
+        "{}: {}",
+        hostname,
+        Local::now().format("%a %b %e %H:%M:%S %Y")
+    );
+    queue!(
+        canvas.row(0, 0, width),
+        PrintStyledContent(StyledContent::new(theme.interval, title)),
+        MoveToColumn(width.saturating_sub(text_width(&host_msg) as u16)),
+        PrintStyledContent(StyledContent::new(theme.host, host_msg)),
+    )?;
+
+    let areas = layout.areas(panes.len(), width, 1, height.saturating_sub(2));
+    for (i, (pane, area)) in panes.iter_mut().zip(areas).enumerate() {
+        if area.height == 0 || area.width == 0 {
+            continue;
+        }
+        let mut pane_title = format!(
+            " Every {:.1}s: {}",
+            pane.interval.as_secs_f64(),
+            pane.command
+        );
+        match &pane.last {
+            Some(result) if !result.status.success() => {
+                pane_title.push_str(&format!(" | Failed ({})", describe_status(result.status)));
+            }
+            _ => {}
+        }
+        if pane.running.is_some() {
+            pane_title.push_str(" | running…");
+        }
+        let pane_title = fit(&pane_title, area.width as usize);
+        let pane_title = if i == focus {
+            pane_title.reverse().bold()
+        } else {
+            pane_title.underlined()
+        };
+        queue!(
+            canvas.row(area.x, area.y, area.width),
+            PrintStyledContent(pane_title)
+        )?;
+
+        let mut rows = pane.viewport.rows(area.width, area.height - 1).into_iter();
+        for row_index in 1..area.height {
+            let out = canvas.row(area.x, area.y + row_index, area.width);
+            for span in rows.next().unwrap_or_default() {
+                queue!(out, PrintStyledContent(span.styled()))?;
+            }
+        }
+    }
+
+    let footer = canvas.row(0, height.saturating_sub(1), width);
+    if paused {
+        queue!(
+            footer,
+            PrintStyledContent(StyledContent::new(theme.paused, "[Paused] "))
+        )?;
+    }
+    if let Some(position) = panes.get(focus).and_then(|pane| pane.viewport.position()) {
+        queue!(
+            footer,
+            PrintStyledContent(StyledContent::new(theme.details, position))
+        )?;
+    }
+    queue!(
+        footer,
+        MoveToColumn(width.saturating_sub(PANES_FOOTER.len() as u16)),
+        PrintStyledContent(StyledContent::new(theme.footer, PANES_FOOTER)),
+    )?;
+    renderer.flush(canvas)
+}
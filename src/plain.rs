@@ -0,0 +1,68 @@
+use std::io::{stdout, Write};
+
+use chrono::{DateTime, Local};
+
+use crate::error::WatchError;
+use crate::exec::ExecutionResult;
+use crate::outcome::{ExitReason, Outcome};
+use crate::watcher::{describe_status, Watcher};
+
+impl Watcher {
+    /// Appends each execution's output to stdout under a header like the TUI's, until an exit
+    /// condition is met. Used instead of the TUI when stdout isn't a terminal, e.g. when piped.
+    pub(crate) fn run_plain(self) -> std::result::Result<Outcome, WatchError> {
+        let prefix = match (self.runs_on_interval(), self.watch_paths.is_empty()) {
+            (true, true) => format!("Every {:.1}s: ", self.interval.as_secs_f64()),
+            (true, false) => format!("Every {:.1}s or on change: ", self.interval.as_secs_f64()),
+            (false, _) => "On change: ".to_string(),
+        };
+        let command = self.full_command();
+        let hostname = self.hostname();
+        let quiet_empty = self.quiet_empty;
+        let mut stream = self.watch_iter();
+        let mut last = None;
+        for result in stream.by_ref() {
+            let result = result?;
+            if quiet_empty && result.stdout.is_empty() && result.stderr.is_empty() {
+                last = Some(result);
+                continue;
+            }
+            let mut out = stdout().lock();
+            writeln!(
+                out,
+                "{}{}  {}",
+                prefix,
+                command.trim_end(),
+                header_status(&hostname, &result)
+            )?;
+            writeln!(out)?;
+            for output in [&result.stdout, &result.stderr] {
+                if !output.is_empty() {
+                    writeln!(out, "{}", output)?;
+                }
+            }
+            writeln!(out)?;
+            out.flush()?;
+            last = Some(result);
+        }
+        Ok(Outcome {
+            reason: stream.exit_reason().unwrap_or(ExitReason::Quit),
+            last,
+        })
+    }
+}
+
+/// The right side of the header: how `result` failed, if it did, the host, and when it ran.
+fn header_status(hostname: &str, result: &ExecutionResult) -> String {
+    let started_at: DateTime<Local> = result.started_at.into();
+    let mut status = match result.status.success() {
+        true => String::new(),
+        false => format!("Failed ({}) | ", describe_status(result.status)),
+    };
+    if !hostname.is_empty() {
+        status.push_str(hostname);
+        status.push_str(": ");
+    }
+    status.push_str(&started_at.format("%a %b %e %H:%M:%S %Y").to_string());
+    status
+}
@@ -0,0 +1,183 @@
+use std::{ffi::OsStr, fmt, path::PathBuf, time::Duration};
+
+use crate::cron::Cron;
+use crate::exec::Step;
+use crate::remote::quote;
+use crate::template::Vars;
+use crate::trigger::FirstRun;
+use crate::watcher::Watcher;
+
+/// What a watch would run, and when it would stop, as worked out by [`Watcher::plan`] without
+/// running anything.
+///
+/// Its [`Display`](fmt::Display) form is what `watchr --dry-run` prints.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Plan {
+    /// The processes spawned on every run, one after the other, except that parallel ones next to
+    /// each other are spawned at the same time.
+    pub commands: Vec<PlannedCommand>,
+    /// How long between runs, or `None` if only changes to [`Plan::watch_paths`] or the
+    /// [`Plan::cron`] schedule trigger them.
+    pub interval: Option<Duration>,
+    /// When runs are due instead of on the interval, see [`Watcher::cron`].
+    pub cron: Option<Cron>,
+    /// Whether runs keep to a fixed cadence from the start, see [`Watcher::precise`].
+    pub precise: bool,
+    /// When the first run happens.
+    pub first_run: FirstRun,
+    /// The paths whose changes also trigger a run.
+    pub watch_paths: Vec<PathBuf>,
+    /// The conditions that end the watch, e.g. "after 3 runs".
+    pub exit_conditions: Vec<String>,
+}
+
+/// One process spawned on every run, with the resolved shell, if any, as its program.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlannedCommand {
+    /// The heading its output is shown under, when there's more than one command.
+    pub heading: Option<String>,
+    /// The executable, e.g. `sh`.
+    pub program: String,
+    /// Everything passed to it, e.g. `["-c", "ls -l"]`.
+    pub args: Vec<String>,
+    /// The variables set on top of watchr's own environment.
+    pub env: Vec<(String, String)>,
+    /// Where it runs, if not in watchr's own working directory.
+    pub current_dir: Option<PathBuf>,
+    /// The shell command line its stdout is piped through, see [`Watcher::pipe`].
+    pub pipe: Option<String>,
+    /// Whether it runs at the same time as the parallel commands next to it, see
+    /// [`Watcher::foreach`].
+    pub parallel: bool,
+}
+
+impl From<&Step> for PlannedCommand {
+    fn from(step: &Step) -> Self {
+        let command = &step.command;
+        let lossy = |s: &OsStr| s.to_string_lossy().into_owned();
+        PlannedCommand {
+            heading: step.heading.clone(),
+            program: lossy(command.get_program()),
+            args: command.get_args().map(lossy).collect(),
+            env: command
+                .get_envs()
+                .filter_map(|(key, value)| Some((lossy(key), lossy(value?))))
+                .collect(),
+            current_dir: command.get_current_dir().map(PathBuf::from),
+            pipe: step
+                .filter
+                .as_ref()
+                .map(|filter| filter.command_line.clone()),
+            parallel: step.parallel,
+        }
+    }
+}
+
+impl Watcher {
+    /// Works out what the watch would run, with the first execution's [`Watcher::template`]
+    /// values, and when it would stop, without running anything.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use watch_rs::Watcher;
+    ///
+    /// let plan = Watcher::new("ls").arg("-l").exec(true).count(Some(3)).plan();
+    /// assert_eq!(plan.commands[0].program, "ls");
+    /// assert_eq!(plan.commands[0].args, ["-l"]);
+    /// assert_eq!(plan.exit_conditions, ["after 3 runs"]);
+    /// ```
+    pub fn plan(&self) -> Plan {
+        let steps = self.build_steps(&self.full_command(), &Vars::new(1, None));
+        Plan {
+            commands: steps.iter().map(PlannedCommand::from).collect(),
+            interval: (self.runs_on_interval() && self.cron.is_none()).then_some(self.interval),
+            cron: self.cron.clone(),
+            first_run: self.first_run,
+            precise: self.precise,
+            watch_paths: self.watch_paths.clone(),
+            exit_conditions: self.exit_conditions(),
+        }
+    }
+
+    /// Describes each of the conditions that end the watch.
+    pub(crate) fn exit_conditions(&self) -> Vec<String> {
+        let mut conditions = Vec::new();
+        if self.chgexit {
+            conditions.push("when the output changes".to_string());
+        }
+        if self.errexit {
+            conditions.push("when the command fails".to_string());
+        }
+        if let Some(until) = &self.until {
+            conditions.push(format!("once the output matches /{}/", until));
+        }
+        if let Some(while_matches) = &self.while_matches {
+            conditions.push(format!(
+                "once the output stops matching /{}/",
+                while_matches
+            ));
+        }
+        if let Some(count) = self.count {
+            conditions.push(format!("after {} runs", count));
+        }
+        if let Some(run_for) = self.run_for {
+            conditions.push(format!("after {}s", run_for.as_secs_f64()));
+        }
+        conditions
+    }
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for command in &self.commands {
+            if let Some(heading) = &command.heading {
+                writeln!(f, "==> {} <==", heading)?;
+            }
+            let argv: Vec<String> = std::iter::once(&command.program)
+                .chain(&command.args)
+                .map(|arg| quote(arg))
+                .collect();
+            writeln!(f, "Command: {}", argv.join(" "))?;
+            for (key, value) in &command.env {
+                writeln!(f, "Environment: {}={}", key, value)?;
+            }
+            if let Some(dir) = &command.current_dir {
+                writeln!(f, "Directory: {}", dir.display())?;
+            }
+            if let Some(pipe) = &command.pipe {
+                writeln!(f, "Piped through: {}", pipe)?;
+            }
+            if command.parallel {
+                writeln!(f, "Runs: in parallel with the other items")?;
+            }
+        }
+        match (&self.cron, self.interval) {
+            (Some(cron), _) => writeln!(f, "Schedule: {}", cron)?,
+            (None, Some(interval)) => {
+                let cadence = if self.precise {
+                    ", on a fixed cadence"
+                } else {
+                    ""
+                };
+                writeln!(f, "Interval: {}s{}", interval.as_secs_f64(), cadence)?
+            }
+            (None, None) => writeln!(f, "Interval: none, only on file changes")?,
+        }
+        if self.first_run != FirstRun::Immediately {
+            writeln!(f, "First run: {}", self.first_run)?;
+        }
+        if !self.watch_paths.is_empty() {
+            let paths: Vec<String> = self
+                .watch_paths
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect();
+            writeln!(f, "Watching: {}", paths.join(", "))?;
+        }
+        match self.exit_conditions.is_empty() {
+            true => writeln!(f, "Exits: when you quit"),
+            false => writeln!(f, "Exits: {}", self.exit_conditions.join("; ")),
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+
+/// What a key press did to a [`LineEditor`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Edit {
+    /// The text changed.
+    Changed,
+    /// The user pressed Enter.
+    Submit,
+    /// The user pressed Esc or Ctrl+C.
+    Cancel,
+    /// Nothing happened, e.g. the cursor moved.
+    None,
+}
+
+/// What a prompt at the bottom of the screen is asking for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Purpose {
+    Search,
+    /// A file to save the output on screen to.
+    SaveAs,
+}
+
+impl Purpose {
+    /// Shown before the text being edited.
+    pub fn label(self) -> &'static str {
+        match self {
+            Purpose::Search => "/",
+            Purpose::SaveAs => "Save to: ",
+        }
+    }
+}
+
+/// A single-line text input, e.g. the search prompt.
+#[derive(Debug, Default)]
+pub(crate) struct LineEditor {
+    text: String,
+    /// Position of the cursor, in chars.
+    cursor: usize,
+}
+
+impl LineEditor {
+    /// An editor starting out with `text`, with the cursor at its end.
+    pub fn new(text: String) -> Self {
+        LineEditor {
+            cursor: text.chars().count(),
+            text,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The cursor's position, in chars, for drawing it.
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn handle(&mut self, event: KeyEvent) -> Edit {
+        if event.kind != KeyEventKind::Press {
+            return Edit::None;
+        }
+        match event.code {
+            KeyCode::Enter => Edit::Submit,
+            KeyCode::Esc => Edit::Cancel,
+            KeyCode::Char('c') if event.modifiers == KeyModifiers::CONTROL => Edit::Cancel,
+            KeyCode::Char('u') if event.modifiers == KeyModifiers::CONTROL => {
+                self.text.clear();
+                self.cursor = 0;
+                Edit::Changed
+            }
+            KeyCode::Char(c) => {
+                self.text.insert(self.byte_index(self.cursor), c);
+                self.cursor += 1;
+                Edit::Changed
+            }
+            KeyCode::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.text.remove(self.byte_index(self.cursor));
+                Edit::Changed
+            }
+            KeyCode::Delete if self.cursor < self.text.chars().count() => {
+                self.text.remove(self.byte_index(self.cursor));
+                Edit::Changed
+            }
+            KeyCode::Left => {
+                self.cursor = self.cursor.saturating_sub(1);
+                Edit::None
+            }
+            KeyCode::Right => {
+                self.cursor = (self.cursor + 1).min(self.text.chars().count());
+                Edit::None
+            }
+            KeyCode::Home => {
+                self.cursor = 0;
+                Edit::None
+            }
+            KeyCode::End => {
+                self.cursor = self.text.chars().count();
+                Edit::None
+            }
+            _ => Edit::None,
+        }
+    }
+
+    /// Inserts pasted `text` at the cursor, on one line.
+    pub fn paste(&mut self, text: &str) -> Edit {
+        let text: String = text.chars().filter(|c| !c.is_control()).collect();
+        if text.is_empty() {
+            return Edit::None;
+        }
+        self.text.insert_str(self.byte_index(self.cursor), &text);
+        self.cursor += text.chars().count();
+        Edit::Changed
+    }
+
+    fn byte_index(&self, chars: usize) -> usize {
+        self.text
+            .char_indices()
+            .nth(chars)
+            .map_or(self.text.len(), |(i, _)| i)
+    }
+}
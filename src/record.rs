@@ -0,0 +1,102 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Result, Write},
+    path::Path,
+    sync::Mutex,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crossterm::terminal::size;
+
+use crate::json::push_json_string;
+
+/// The recording in progress, fed by [`Screen`](crate::terminal::Screen).
+static RECORDING: Mutex<Option<Recording>> = Mutex::new(None);
+
+/// Records everything drawn on the screen to an asciinema v2 file, with its timing.
+struct Recording {
+    file: BufWriter<File>,
+    start: Instant,
+    /// Output drawn since the last flush, written as a single event.
+    pending: Vec<u8>,
+    size: (u16, u16),
+}
+
+/// Stops the recording and finishes writing the file when dropped.
+pub(crate) struct RecordingGuard(());
+
+/// Starts recording the screen to `path`, replacing the file if it exists.
+pub(crate) fn start(path: &Path, title: &str) -> Result<RecordingGuard> {
+    let (width, height) = size()?;
+    let mut file = BufWriter::new(File::create(path)?);
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let mut header = format!(
+        r#"{{"version": 2, "width": {}, "height": {}, "timestamp": {}, "title": "#,
+        width, height, timestamp
+    );
+    push_json_string(&mut header, title);
+    header.push_str(", \"env\": {\"TERM\": ");
+    push_json_string(&mut header, &std::env::var("TERM").unwrap_or_default());
+    header.push_str("}}");
+    writeln!(file, "{}", header)?;
+
+    let recording = Recording {
+        file,
+        start: Instant::now(),
+        pending: Vec::new(),
+        size: (width, height),
+    };
+    *RECORDING.lock().unwrap_or_else(|err| err.into_inner()) = Some(recording);
+    Ok(RecordingGuard(()))
+}
+
+impl Drop for RecordingGuard {
+    fn drop(&mut self) {
+        let recording = RECORDING.lock().map(|mut recording| recording.take());
+        if let Ok(Some(mut recording)) = recording {
+            // There's nowhere left to report a failure to
+            let _ = recording
+                .write_event()
+                .and_then(|()| recording.file.flush());
+        }
+    }
+}
+
+/// Adds `bytes` drawn on the screen to the recording, if there is one.
+pub(crate) fn capture(bytes: &[u8]) {
+    if let Ok(Some(recording)) = RECORDING.lock().as_deref_mut() {
+        recording.pending.extend_from_slice(bytes);
+    }
+}
+
+/// Writes what was drawn since the last flush to the recording as one frame.
+pub(crate) fn flush() -> Result<()> {
+    match RECORDING.lock().as_deref_mut() {
+        Ok(Some(recording)) => recording.write_event(),
+        _ => Ok(()),
+    }
+}
+
+impl Recording {
+    fn write_event(&mut self) -> Result<()> {
+        let time = self.start.elapsed().as_secs_f64();
+        if let Ok(size) = size() {
+            if size != self.size {
+                self.size = size;
+                writeln!(self.file, r#"[{:.6}, "r", "{}x{}"]"#, time, size.0, size.1)?;
+            }
+        }
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let mut event = format!("[{:.6}, \"o\", ", time);
+        push_json_string(&mut event, &String::from_utf8_lossy(&self.pending));
+        event.push(']');
+        self.pending.clear();
+        // Flush every frame so the recording survives the watch being killed
+        writeln!(self.file, "{}", event)?;
+        self.file.flush()
+    }
+}
@@ -0,0 +1,182 @@
+use std::{env, process::Command};
+
+use crate::exec::{Output, Status};
+use crate::shell::on_path;
+use crate::watcher::Watcher;
+
+/// The exit code `ssh` reports when it couldn't connect, rather than the command's own.
+const SSH_CONNECTION_FAILED: i32 = 255;
+
+/// What the container runtime prints when the container can't run the command, rather than the
+/// command failing. Matched case-insensitively against its stderr.
+const CONTAINER_UNAVAILABLE: [&str; 4] = [
+    "is not running",
+    "no such container",
+    "state improper",
+    "cannot connect to the docker daemon",
+];
+
+/// Somewhere other than this machine that the command runs, see [`Watcher::ssh`] and
+/// [`Watcher::container`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Remote {
+    /// Over SSH, on `destination`, e.g. `user@host` or `ssh://host:2222`.
+    Ssh { destination: String },
+    /// In the running container `name`, with `runtime exec`.
+    Container {
+        name: String,
+        runtime: ContainerRuntime,
+    },
+}
+
+/// The tool used to run the command in a [`Watcher::container`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum ContainerRuntime {
+    /// `docker exec`.
+    Docker,
+    /// `podman exec`.
+    Podman,
+}
+
+impl ContainerRuntime {
+    /// Docker if it's on `PATH`, otherwise Podman.
+    pub fn detect() -> Self {
+        let docker = if cfg!(windows) {
+            "docker.exe"
+        } else {
+            "docker"
+        };
+        if on_path(docker) {
+            ContainerRuntime::Docker
+        } else {
+            ContainerRuntime::Podman
+        }
+    }
+
+    /// The runtime's executable.
+    pub fn program(self) -> &'static str {
+        match self {
+            ContainerRuntime::Docker => "docker",
+            ContainerRuntime::Podman => "podman",
+        }
+    }
+}
+
+impl Remote {
+    /// The host shown in the header in place of this machine's.
+    pub fn host(&self) -> String {
+        match self {
+            Remote::Ssh { destination } => {
+                let host = match destination.strip_prefix("ssh://") {
+                    Some(url) => url.split(':').next().unwrap_or(url),
+                    None => destination,
+                };
+                host.rsplit('@').next().unwrap_or(host).to_string()
+            }
+            Remote::Container { name, runtime } => format!("{} ({})", name, runtime.program()),
+        }
+    }
+
+    /// Whether `output` means the command couldn't be reached, rather than that it ran and
+    /// failed.
+    pub fn unreachable(&self, output: &Output) -> bool {
+        match self {
+            Remote::Ssh { .. } => output.status.code() == Some(SSH_CONNECTION_FAILED),
+            Remote::Container { .. } => {
+                let stderr = String::from_utf8_lossy(&output.stderr).to_lowercase();
+                !output.status.success()
+                    && CONTAINER_UNAVAILABLE
+                        .iter()
+                        .any(|message| stderr.contains(message))
+            }
+        }
+    }
+}
+
+impl Watcher {
+    /// Builds the process running `command_line` on `remote`, through the remote user's shell,
+    /// or `sh` in a container, with [`Watcher::env`] and [`Watcher::current_dir`] applied there.
+    pub(crate) fn remote_command(&self, remote: &Remote, command_line: &str) -> Command {
+        if let Remote::Container { .. } = remote {
+            let words = ["sh", "-c", command_line].map(String::from);
+            return self.remote_exec(remote, &words);
+        }
+        let mut line = String::new();
+        for (key, value) in &self.env {
+            line.push_str(&format!("export {}={}; ", key, quote(value)));
+        }
+        if let Some(dir) = &self.current_dir {
+            line.push_str(&format!("cd {} && ", quote(&dir.to_string_lossy())));
+        }
+        line.push_str(command_line);
+
+        match remote {
+            Remote::Ssh { destination } => {
+                let mut command = Command::new("ssh");
+                // ssh uses the first value given for an option, so the user's come first
+                for option in self.ssh_options.iter().cloned().chain(ssh_defaults()) {
+                    command.arg("-o").arg(option);
+                }
+                command.arg(destination).arg("--").arg(line);
+                command
+            }
+            Remote::Container { .. } => unreachable!("containers run the command directly"),
+        }
+    }
+
+    /// Builds the process running the program and arguments in `words` on `remote`. Over SSH
+    /// they're quoted for the remote shell; a container runs them as they are.
+    pub(crate) fn remote_exec(&self, remote: &Remote, words: &[String]) -> Command {
+        let Remote::Container { name, runtime } = remote else {
+            let words: Vec<String> = words.iter().map(|word| quote(word)).collect();
+            return self.remote_command(remote, &words.join(" "));
+        };
+        let mut command = Command::new(runtime.program());
+        command.arg("exec");
+        for (key, value) in &self.env {
+            command.arg("-e").arg(format!("{}={}", key, value));
+        }
+        if let Some(dir) = &self.current_dir {
+            command.arg("-w").arg(dir);
+        }
+        command.arg(name).args(words);
+        command
+    }
+
+    /// Marks an execution that couldn't reach the [`Watcher::ssh`] host or
+    /// [`Watcher::container`] as such.
+    pub(crate) fn classify(&self, mut output: Output) -> Output {
+        if let (Some(remote), Status::Exited(status)) = (&self.remote, output.status) {
+            if remote.unreachable(&output) {
+                output.status = Status::Unreachable(status);
+            }
+        }
+        output
+    }
+}
+
+/// The options every connection is made with: never prompting, since the TUI has the terminal,
+/// and on Unix sharing one connection across executions, reconnecting when it drops.
+fn ssh_defaults() -> Vec<String> {
+    let mut options = vec!["BatchMode=yes".to_string(), "ConnectTimeout=10".to_string()];
+    if cfg!(unix) {
+        let control_path = env::temp_dir().join("watchr-ssh-%C");
+        options.push("ControlMaster=auto".to_string());
+        options.push(format!("ControlPath={}", control_path.display()));
+        options.push("ControlPersist=60".to_string());
+    }
+    options
+}
+
+/// Quotes `arg` so that a POSIX shell reads it back as a single word, exactly as it is, e.g.
+/// `it's` as `'it'\''s'`.
+pub(crate) fn quote(arg: &str) -> String {
+    if !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,@%+".contains(c))
+    {
+        return arg.to_string();
+    }
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
@@ -0,0 +1,724 @@
+//! Drawing the watch's screen, for other crossterm applications that want to show a command's
+//! output the way `watchr` does from their own event loop.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use watch_rs::render::{self, Area, Frame};
+//! use watch_rs::Watcher;
+//!
+//! # fn main() -> Result<(), watch_rs::WatchError> {
+//! for result in Watcher::new("uptime").count(Some(3)).watch_iter() {
+//!     let frame = Frame::new("uptime").hostname("build-box");
+//!     render::draw(&result?, &frame, Area::screen()?)?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! A [`Renderer`] draws the same screen to any writer instead, e.g. a buffer in tests, a PTY, or a
+//! socket.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    io::{Result, Write},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        LazyLock, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Local};
+use crossterm::{
+    cursor::{MoveTo, MoveToColumn},
+    queue,
+    style::{Print, PrintStyledContent, StyledContent},
+    terminal::{size, Clear, ClearType},
+};
+
+use crate::diff::{DiffHighlighter, Differences};
+use crate::error::WatchError;
+use crate::exec::{ExecutionResult, Status};
+use crate::overlay::Overlay;
+use crate::stats::HealthStats;
+use crate::terminal::{screen, Screen};
+use crate::theme::Theme;
+use crate::viewport::Viewport;
+use crate::watcher::{describe_status, Watcher};
+use crate::width::{ellipsize, text_width, truncate};
+
+/// Spinner frames shown while a command is running in streaming mode.
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+static DEFAULT_THEME: LazyLock<Theme> = LazyLock::new(Theme::default);
+
+/// Counts the times something else drew over the terminal, see [`invalidate`]. Renderers drawing
+/// to the terminal redraw everything once it changes.
+static DRAWN_OVER: AtomicU64 = AtomicU64::new(0);
+
+/// What [`draw`] draws to the terminal with.
+static TERMINAL: LazyLock<Mutex<Renderer<Screen>>> =
+    LazyLock::new(|| Mutex::new(Renderer::terminal(screen())));
+
+/// Where the TUI is drawn when it isn't the terminal, see [`Watcher::draw_to`].
+pub(crate) type Target = Box<dyn Write + Send>;
+
+/// What a renderer's screen shows, as of its last flush.
+#[derive(Debug, Default)]
+struct Drawn {
+    /// The terminal's size when it was drawn, or `None` if what it shows is unknown.
+    size: Option<(u16, u16)>,
+    /// The bytes each row was drawn with, by its row, column, and width.
+    rows: Option<HashMap<(u16, u16, u16), Vec<u8>>>,
+}
+
+/// A rectangle of the terminal, in cells.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Area {
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl Area {
+    pub fn new(x: u16, y: u16, width: u16, height: u16) -> Self {
+        Area {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Whether the cell at `column` and `row` is inside this area.
+    pub fn contains(&self, column: u16, row: u16) -> bool {
+        (self.x..self.x.saturating_add(self.width)).contains(&column)
+            && (self.y..self.y.saturating_add(self.height)).contains(&row)
+    }
+
+    /// The whole terminal.
+    pub fn screen() -> std::result::Result<Self, WatchError> {
+        let (width, height) = size().map_err(WatchError::Terminal)?;
+        Ok(Area::new(0, 0, width, height))
+    }
+}
+
+/// Everything shown around the command's output: the header with the command, its interval and
+/// status, and the footer.
+#[derive(Clone, Debug)]
+pub struct Frame<'a> {
+    pub(crate) command: &'a str,
+    pub(crate) interval: Duration,
+    /// How the command shown exited, or `None` while it's still running.
+    pub(crate) status: Option<Status>,
+    pub(crate) footer: &'a str,
+    pub(crate) paused: bool,
+    /// Whether the header and footer are shown.
+    pub(crate) title: bool,
+    pub(crate) hostname: &'a str,
+    /// When the command shown was started.
+    pub(crate) last_run: DateTime<Local>,
+    /// How long the command has been running, in streaming mode.
+    pub(crate) running: Option<Duration>,
+    /// Which older run is shown, e.g. "run 47/50, 12s ago", or `None` for the latest.
+    pub(crate) history: Option<String>,
+    /// How long the command shown took, e.g. "took 420ms (min 401ms, avg 415ms, max 450ms)".
+    pub(crate) timing: Option<String>,
+    /// What triggers executions: the interval, file changes, or both, or the cron expression
+    /// that replaces the interval.
+    pub(crate) cron: Option<String>,
+    pub(crate) on_interval: bool,
+    pub(crate) watching_files: bool,
+    /// The open prompt's label, text, and cursor position.
+    pub(crate) prompt: Option<(&'static str, String, usize)>,
+    /// A short message for the status bar, and when it should disappear.
+    pub(crate) toast: Option<(String, Instant)>,
+    /// The CPU and memory the command is using in streaming mode, or used at most once it's done.
+    pub(crate) usage: Option<String>,
+    /// When the command stopped printing anything, with [`Watcher::quiet_empty`].
+    pub(crate) quiet_since: Option<DateTime<Local>>,
+    /// When the wait for the next run started and when the run is due, for counting down to it.
+    pub(crate) next_run: Option<(Instant, Instant)>,
+    /// The dashboard shown above the output, with [`Watcher::stats`].
+    pub(crate) health: Option<HealthStats>,
+    /// The box shown over the output, like the help, until the next key press.
+    pub(crate) overlay: Option<Overlay>,
+    pub(crate) theme: &'a Theme,
+}
+
+impl<'a> Frame<'a> {
+    /// A frame for `command`, run every 2 seconds, with an empty footer and the default theme.
+    pub fn new(command: &'a str) -> Self {
+        Frame {
+            command,
+            interval: Duration::from_secs(2),
+            status: None,
+            footer: "",
+            paused: false,
+            title: true,
+            hostname: "",
+            last_run: Local::now(),
+            running: None,
+            history: None,
+            timing: None,
+            on_interval: true,
+            watching_files: false,
+            prompt: None,
+            toast: None,
+            usage: None,
+            quiet_since: None,
+            cron: None,
+            next_run: None,
+            health: None,
+            overlay: None,
+            theme: &DEFAULT_THEME,
+        }
+    }
+
+    /// The interval shown in the header, as in "Every 2.0s: ...".
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// The hostname shown before the time at the end of the header.
+    pub fn hostname(mut self, hostname: &'a str) -> Self {
+        self.hostname = hostname;
+        self
+    }
+
+    /// The message at the bottom right, e.g. how to exit.
+    pub fn footer(mut self, footer: &'a str) -> Self {
+        self.footer = footer;
+        self
+    }
+
+    /// Whether "[Paused]" is shown at the bottom left.
+    pub fn paused(mut self, paused: bool) -> Self {
+        self.paused = paused;
+        self
+    }
+
+    /// Show the header and footer around the output (the default). Without them the output gets
+    /// the whole area.
+    pub fn title(mut self, title: bool) -> Self {
+        self.title = title;
+        self
+    }
+
+    pub fn theme(mut self, theme: &'a Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+}
+
+/// Forgets what was drawn on the terminal, so the next draw there redraws everything instead of
+/// only what changed.
+///
+/// Call this after drawing anything else where [`draw`] draws, or clearing the screen.
+pub fn invalidate() {
+    DRAWN_OVER.fetch_add(1, Ordering::SeqCst);
+}
+
+/// A frame drawn row by row, then written at once by [`Renderer::flush`].
+///
+/// Only the rows that differ from the last frame are rewritten, which keeps the output small and
+/// avoids the flicker of clearing the whole screen on every update.
+pub(crate) struct Canvas {
+    size: (u16, u16),
+    rows: BTreeMap<(u16, u16, u16), Vec<u8>>,
+}
+
+impl Canvas {
+    fn with_size(size: (u16, u16)) -> Self {
+        Canvas {
+            size,
+            rows: BTreeMap::new(),
+        }
+    }
+
+    /// Starts the `width` cells of row `y` from column `x`, which are blank unless something is
+    /// queued to the buffer returned. The cursor starts at `(x, y)`.
+    pub fn row(&mut self, x: u16, y: u16, width: u16) -> &mut Vec<u8> {
+        let row = self.rows.entry((y, x, width)).or_default();
+        row.clear();
+        row
+    }
+
+    /// Writes the rows that differ from what `drawn` says `out` shows to it, and flushes it.
+    fn flush_to(self, out: &mut impl Write, drawn: &mut Drawn) -> Result<()> {
+        if drawn.size != Some(self.size) {
+            queue!(out, Clear(ClearType::All))?;
+        }
+        let previous = match drawn.size == Some(self.size) {
+            true => drawn.rows.take().unwrap_or_default(),
+            false => HashMap::new(),
+        };
+        // Mark the screen unknown until everything has been written
+        drawn.size = None;
+        let mut rows = previous;
+        for ((y, x, width), bytes) in self.rows {
+            if rows.get(&(y, x, width)) == Some(&bytes) {
+                continue;
+            }
+            queue!(out, MoveTo(x, y))?;
+            if x.saturating_add(width) >= self.size.0 {
+                queue!(out, Clear(ClearType::UntilNewLine))?;
+            } else {
+                queue!(out, Print(" ".repeat(width as usize)), MoveTo(x, y))?;
+            }
+            out.write_all(&bytes)?;
+            rows.insert((y, x, width), bytes);
+        }
+        out.flush()?;
+        drawn.size = Some(self.size);
+        drawn.rows = Some(rows);
+        Ok(())
+    }
+}
+
+/// Draws `result` in `area`, surrounded by `frame`: the header on the first row, then the output,
+/// and the footer on the last row. The output is shown from the top, wrapped at the area's width.
+///
+/// Nothing outside `area` is touched, only the rows that changed since the last draw are
+/// rewritten, and the result is flushed to the terminal.
+///
+/// # Errors
+///
+/// Returns [`WatchError::Terminal`] if drawing fails.
+pub fn draw(
+    result: &ExecutionResult,
+    frame: &Frame,
+    area: Area,
+) -> std::result::Result<(), WatchError> {
+    let mut terminal = TERMINAL.lock().unwrap_or_else(|err| err.into_inner());
+    terminal.draw(result, frame, area)
+}
+
+/// `frame` showing how `result` exited, and a viewport showing its output, with what changed in it
+/// highlighted by `highlighter`.
+fn result_frame<'a>(
+    result: &ExecutionResult,
+    frame: &Frame<'a>,
+    highlighter: Option<&mut DiffHighlighter>,
+) -> (Frame<'a>, Viewport) {
+    let mut frame = frame.clone();
+    frame.status = Some(result.status);
+    frame.last_run = result.started_at.into();
+    let watcher = Watcher::new(frame.command).theme(frame.theme.clone());
+    let mut viewport = Viewport::new();
+    viewport.set_theme(frame.theme.clone());
+    viewport.set_lines(watcher.result_lines(result, frame.title, highlighter));
+    (frame, viewport)
+}
+
+/// Draws the watch's screen to any writer, as [`draw`] does to the terminal, e.g. to capture
+/// frames in tests or to serve them over a PTY or socket.
+///
+/// It remembers what it drew, so each draw only rewrites the rows that changed since the last.
+///
+/// # Examples
+///
+/// ```
+/// use watch_rs::render::{Area, Frame, Renderer};
+/// use watch_rs::{RunOutput, Watcher};
+///
+/// # fn main() -> Result<(), watch_rs::WatchError> {
+/// let result = Watcher::new("uptime")
+///     .runner(|_: &_| Ok(RunOutput::success("up 3 days")))
+///     .watch_iter()
+///     .next()
+///     .unwrap()?;
+/// let mut renderer = Renderer::new(Vec::new(), 40, 5);
+/// renderer.draw(&result, &Frame::new("uptime"), Area::new(0, 0, 40, 5))?;
+/// assert!(String::from_utf8_lossy(renderer.get_ref()).contains("up 3 days"));
+/// # Ok(())
+/// # }
+/// ```
+pub struct Renderer<W: Write> {
+    out: W,
+    /// The screen's size, or `None` to follow the terminal's.
+    size: Option<(u16, u16)>,
+    drawn: Drawn,
+    /// [`DRAWN_OVER`] as of the last draw, when drawing to the terminal.
+    drawn_over: u64,
+    differences: Option<Differences>,
+    /// Compares each result drawn to the one before, once there's been one.
+    highlighter: Option<DiffHighlighter>,
+}
+
+impl<W: Write> Renderer<W> {
+    /// A renderer for a `width` x `height` screen shown by `out`.
+    pub fn new(out: W, width: u16, height: u16) -> Self {
+        Renderer {
+            out,
+            size: Some((width, height)),
+            drawn: Drawn::default(),
+            drawn_over: 0,
+            differences: None,
+            highlighter: None,
+        }
+    }
+
+    /// A renderer for the terminal, through `out`, which is as big as the terminal is and redraws
+    /// everything after [`invalidate`].
+    pub(crate) fn terminal(out: W) -> Self {
+        Renderer {
+            out,
+            size: None,
+            drawn: Drawn::default(),
+            drawn_over: DRAWN_OVER.load(Ordering::SeqCst),
+            differences: None,
+            highlighter: None,
+        }
+    }
+
+    /// Highlights what changed in each result drawn since the one drawn before it, like
+    /// [`Watcher::differences`]. Off by default.
+    pub fn differences(mut self, differences: Option<Differences>) -> Self {
+        self.differences = differences;
+        self.highlighter = None;
+        self
+    }
+
+    /// Changes the size of the screen, which is redrawn in full on the next draw.
+    pub fn resize(&mut self, width: u16, height: u16) {
+        self.size = Some((width, height));
+    }
+
+    /// The whole screen.
+    pub(crate) fn area(&self) -> Result<Area> {
+        let (width, height) = match self.size {
+            Some(size) => size,
+            None => size()?,
+        };
+        Ok(Area::new(0, 0, width, height))
+    }
+
+    /// A blank canvas the size of the screen.
+    pub(crate) fn canvas(&self) -> Result<Canvas> {
+        let Area { width, height, .. } = self.area()?;
+        Ok(Canvas::with_size((width, height)))
+    }
+
+    /// Writes the rows of `canvas` that changed since the last flush, and flushes the writer.
+    pub(crate) fn flush(&mut self, canvas: Canvas) -> Result<()> {
+        if self.size.is_none() {
+            let drawn_over = DRAWN_OVER.load(Ordering::SeqCst);
+            if drawn_over != self.drawn_over {
+                self.drawn = Drawn::default();
+                self.drawn_over = drawn_over;
+            }
+        }
+        canvas.flush_to(&mut self.out, &mut self.drawn)
+    }
+
+    /// Draws the header, the visible part of the output, and the footer in `area`.
+    pub(crate) fn draw_frame(
+        &mut self,
+        frame: &Frame,
+        viewport: &mut Viewport,
+        area: Area,
+    ) -> Result<()> {
+        let mut canvas = self.canvas()?;
+        paint(frame, viewport, area, &mut canvas)?;
+        self.flush(canvas)
+    }
+
+    /// Draws `result` in `area`, surrounded by `frame`, like [`draw`], and flushes the writer.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`WatchError::Terminal`] if writing fails.
+    pub fn draw(
+        &mut self,
+        result: &ExecutionResult,
+        frame: &Frame,
+        area: Area,
+    ) -> std::result::Result<(), WatchError> {
+        if self.highlighter.is_none() {
+            self.highlighter = self
+                .differences
+                .map(|mode| DiffHighlighter::new(mode, frame.theme.diff_changed));
+        }
+        let (frame, mut viewport) = result_frame(result, frame, self.highlighter.as_mut());
+        self.draw_frame(&frame, &mut viewport, area)
+            .map_err(WatchError::Terminal)
+    }
+
+    /// Forgets what was drawn, like [`invalidate`], so the next draw redraws everything.
+    pub fn invalidate(&mut self) {
+        self.drawn = Drawn::default();
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.out
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.out
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+impl<W: Write> fmt::Debug for Renderer<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Renderer")
+            .field("size", &self.size)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Queues the header, the visible part of the output, and the footer in `area` of `canvas`.
+fn paint(frame: &Frame, viewport: &mut Viewport, area: Area, canvas: &mut Canvas) -> Result<()> {
+    let Area {
+        x,
+        y,
+        width,
+        height,
+    } = area;
+
+    // Without the title the output gets the whole area, otherwise leave room for the header,
+    // the blank line below it, and the footer
+    let (body_top, body_height) = if frame.title {
+        draw_header(frame, x, width, canvas.row(x, y, width))?;
+        canvas.row(x, y + 1, width);
+        (y + 2, height.saturating_sub(3))
+    } else {
+        (y, height)
+    };
+    let (body_top, body_height) = match &frame.health {
+        // Leave at least a row for the output
+        Some(health) if body_height > 3 => {
+            let used = draw_dashboard(health, frame.theme, canvas, x, body_top, width)?;
+            (body_top + used, body_height.saturating_sub(used))
+        }
+        _ => (body_top, body_height),
+    };
+    let mut rows = viewport.rows(width, body_height).into_iter();
+    let (overlay_x, overlay_y, overlay) = match &frame.overlay {
+        Some(overlay) => overlay.layout(width, body_height, frame.theme.title),
+        None => (0, 0, Vec::new()),
+    };
+    for i in 0..body_height {
+        let out = canvas.row(x, body_top + i, width);
+        for span in rows.next().unwrap_or_default() {
+            queue!(out, PrintStyledContent(span.styled()))?;
+        }
+        // The overlay is drawn over the output's row, so it's redrawn with the row once it's gone
+        if let Some(row) = i
+            .checked_sub(overlay_y)
+            .and_then(|i| overlay.get(i as usize))
+        {
+            queue!(out, MoveToColumn(x + overlay_x))?;
+            for span in row {
+                queue!(out, PrintStyledContent(span.styled()))?;
+            }
+        }
+    }
+
+    if frame.title && height > 0 {
+        let bottom = y + height - 1;
+        draw_footer(frame, viewport, x, width, canvas.row(x, bottom, width))?;
+    }
+    Ok(())
+}
+
+/// Draws the summary of the runs so far and the sparkline of their durations, followed by a blank
+/// row, from row `y`. Returns how many rows it took.
+fn draw_dashboard(
+    health: &HealthStats,
+    theme: &Theme,
+    canvas: &mut Canvas,
+    x: u16,
+    y: u16,
+    width: u16,
+) -> Result<u16> {
+    let clip = |text: String| truncate(&text, width as usize).to_string();
+    queue!(
+        canvas.row(x, y, width),
+        PrintStyledContent(StyledContent::new(theme.interval, clip(health.summary()))),
+    )?;
+    queue!(
+        canvas.row(x, y + 1, width),
+        Print(clip(health.sparkline(width as usize))),
+    )?;
+    canvas.row(x, y + 2, width);
+    Ok(3)
+}
+
+/// Draws the last row: the prompt while it's open, otherwise the status on the left and the
+/// footer on the right.
+fn draw_footer(
+    frame: &Frame,
+    viewport: &Viewport,
+    x: u16,
+    width: u16,
+    out: &mut Vec<u8>,
+) -> Result<()> {
+    if let Some((label, text, cursor)) = &frame.prompt {
+        // The cursor is hidden while watching, so draw one
+        let (before, after) = text.split_at(
+            text.char_indices()
+                .nth(*cursor)
+                .map_or(text.len(), |(i, _)| i),
+        );
+        let mut after = after.chars();
+        let at_cursor = after.next().unwrap_or(' ');
+        return queue!(
+            out,
+            Print(label),
+            Print(before),
+            PrintStyledContent(StyledContent::new(frame.theme.cursor, at_cursor)),
+            Print(after.as_str()),
+        );
+    }
+    let theme = frame.theme;
+    // The status, cut to fit beside the footer
+    let mut status: Vec<StyledContent<String>> = Vec::new();
+    if let Some(elapsed) = frame.running {
+        let spinner = SPINNER[(elapsed.as_millis() / 100) as usize % SPINNER.len()];
+        let running_msg = format!("{} Running {:.1}s ", spinner, elapsed.as_secs_f64());
+        status.push(StyledContent::new(theme.running, running_msg));
+    }
+    if frame.paused {
+        status.push(StyledContent::new(theme.paused, "[Paused] ".to_string()));
+    }
+    if let Some((message, _)) = &frame.toast {
+        status.push(StyledContent::new(theme.toast, format!("{} ", message)));
+    }
+    if let Some(since) = frame.quiet_since {
+        let quiet_msg = format!("no output since {} ", since.format("%H:%M:%S"));
+        status.push(StyledContent::new(theme.notice, quiet_msg));
+    }
+    if let Some(position) = viewport.position() {
+        status.push(StyledContent::new(theme.details, format!("{} ", position)));
+    }
+    if let Some(search) = viewport.search_status() {
+        status.push(StyledContent::new(theme.notice, format!("{} ", search)));
+    }
+    if let (Some(timing), None) = (&frame.timing, frame.running) {
+        status.push(StyledContent::new(theme.details, format!("{} ", timing)));
+    }
+    if let Some(usage) = &frame.usage {
+        status.push(StyledContent::new(theme.details, format!("{} ", usage)));
+    }
+    if let (Some((since, due)), None) = (frame.next_run, frame.running) {
+        // The header counts down to cron runs already
+        if frame.cron.is_none() {
+            let next_msg = format!("next run in {} ", countdown(due));
+            status.push(StyledContent::new(theme.details, next_msg));
+        }
+        status.push(StyledContent::new(theme.details, progress_bar(since, due)));
+    }
+    let footer = truncate(frame.footer, width as usize);
+    let footer_width = text_width(footer);
+    print_clipped(out, status, width as usize - footer_width)?;
+    queue!(
+        out,
+        MoveToColumn(x + width - footer_width as u16),
+        PrintStyledContent(StyledContent::new(theme.footer, footer)),
+    )
+}
+
+/// Queues `pieces` one after the other, cutting off whatever doesn't fit in `width` columns.
+fn print_clipped(
+    out: &mut Vec<u8>,
+    pieces: impl IntoIterator<Item = StyledContent<String>>,
+    width: usize,
+) -> Result<()> {
+    let mut room = width;
+    for piece in pieces {
+        let text = truncate(piece.content(), room);
+        room -= text_width(text);
+        queue!(
+            out,
+            PrintStyledContent(StyledContent::new(*piece.style(), text))
+        )?;
+    }
+    Ok(())
+}
+
+/// The time left until `next_run`, rounded up, e.g. "42s", "4m 10s", or "3h 5m".
+fn countdown(next_run: Instant) -> String {
+    let remaining = next_run.saturating_duration_since(Instant::now());
+    let secs = remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0);
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m {}s", secs / 60, secs % 60),
+        3600..=86399 => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+        _ => format!("{}d {}h", secs / 86400, secs % 86400 / 3600),
+    }
+}
+
+/// How much of the wait from `since` until `due` has passed, as a bar like "━━━───────".
+fn progress_bar(since: Instant, due: Instant) -> String {
+    const WIDTH: usize = 10;
+    let total = due.saturating_duration_since(since).as_secs_f64();
+    let waited = since.elapsed().as_secs_f64();
+    let done = match total > 0.0 {
+        true => ((waited / total).min(1.0) * WIDTH as f64) as usize,
+        false => WIDTH,
+    };
+    format!("{}{}", "━".repeat(done), "─".repeat(WIDTH - done))
+}
+
+/// Draws the first row: "Every Ns: <command>" on the left, and the command's status, the
+/// hostname, and the time of the last execution on the right.
+fn draw_header(frame: &Frame, x: u16, width: u16, out: &mut Vec<u8>) -> Result<()> {
+    let width = width as usize;
+    let theme = frame.theme;
+    let countdown = frame
+        .next_run
+        .map(|(_, due)| format!(" (next in {})", countdown(due)))
+        .unwrap_or_default();
+    let prefix = match (&frame.cron, frame.on_interval, frame.watching_files) {
+        (Some(cron), _, false) => format!("Cron {}{}: ", cron, countdown),
+        (Some(cron), _, true) => format!("Cron {} or on change{}: ", cron, countdown),
+        (None, true, false) => format!("Every {:.1}s: ", frame.interval.as_secs_f64()),
+        (None, true, true) => format!("Every {:.1}s or on change: ", frame.interval.as_secs_f64()),
+        (None, false, _) => "On change: ".to_string(),
+    };
+    let status_msg = match frame.status {
+        None => String::new(),
+        Some(status) if status.success() => String::new(),
+        Some(Status::TimedOut(timeout)) => {
+            format!("Timed out after {}s | ", timeout.as_secs_f64())
+        }
+        Some(Status::Unreachable(_)) => "Unreachable, retrying | ".to_string(),
+        Some(status) => format!("Failed ({}) | ", describe_status(status)),
+    };
+    let time = frame.last_run.format("%a %b %e %H:%M:%S %Y");
+    let host_msg = match frame.hostname {
+        "" => time.to_string(),
+        hostname => format!("{}: {}", hostname, time),
+    };
+    let history_msg = match &frame.history {
+        Some(label) => format!("{} | ", label),
+        None => String::new(),
+    };
+    // The right side gives way to the prefix, and the command to both, when space runs out
+    let right_len = (text_width(&history_msg) + text_width(&status_msg) + text_width(&host_msg))
+        .min(width.saturating_sub(text_width(&prefix)));
+    let available = width.saturating_sub(right_len + 1);
+    let command = ellipsize(frame.command, available.saturating_sub(text_width(&prefix)));
+
+    let left = [
+        StyledContent::new(theme.interval, prefix),
+        StyledContent::new(theme.command, command),
+    ];
+    print_clipped(out, left, available)?;
+    queue!(out, MoveToColumn(x + (width - right_len) as u16))?;
+    let right = [
+        StyledContent::new(theme.history, history_msg),
+        StyledContent::new(theme.status, status_msg),
+        StyledContent::new(theme.host, host_msg),
+    ];
+    print_clipped(out, right, right_len)
+}
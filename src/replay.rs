@@ -0,0 +1,201 @@
+use std::{
+    fs,
+    io::{stdout, Result, Write},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use crossterm::{
+    cursor::MoveTo,
+    event::{poll, read},
+    queue,
+    style::{Print, Stylize},
+    terminal::{size, Clear, ClearType},
+};
+
+use crate::error::WatchError;
+use crate::input::{Action, Keymap};
+use crate::terminal::TerminalGuard;
+use crate::width::text_width;
+
+/// The slowest and fastest playback speeds, as multiples of the recorded speed.
+const MIN_SPEED: f64 = 0.25;
+const MAX_SPEED: f64 = 16.0;
+
+/// The longest to go without checking for input or redrawing the status, during long pauses.
+const REPLAY_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Something drawn on the screen, `time` seconds into a recording.
+struct Frame {
+    time: f64,
+    output: String,
+}
+
+/// Plays back a session recorded with [`Watcher::record`](crate::Watcher::record), or any other
+/// asciinema v2 file, honoring its timing.
+///
+/// While playing, '+'/'-' double or halve the speed, space pauses, and 'q' or 'Ctrl+C' exits.
+/// Recordings look best in a terminal at least as large as the one they were made in.
+///
+/// # Errors
+///
+/// Returns [`WatchError::Io`] if the file can't be read, [`WatchError::Decode`] if it isn't an
+/// asciinema v2 recording, and [`WatchError::Terminal`] if it can't be played.
+pub fn replay(path: impl AsRef<Path>) -> std::result::Result<(), WatchError> {
+    let frames = parse(&fs::read_to_string(path)?)?;
+    play(&frames).map_err(WatchError::Terminal)
+}
+
+fn play(frames: &[Frame]) -> Result<()> {
+    let duration = frames.last().map_or(0.0, |frame| frame.time);
+
+    let _terminal = TerminalGuard::enter()?;
+    let mut speed = 1.0;
+    let mut paused = false;
+    let keymap = Keymap::default();
+    // How far into the recording playback is, in seconds
+    let mut position = 0.0;
+    let mut next = 0;
+    loop {
+        let mut out = stdout();
+        while let Some(frame) = frames.get(next).filter(|frame| frame.time <= position) {
+            queue!(out, Print(&frame.output))?;
+            next += 1;
+        }
+        let finished = next == frames.len();
+        draw_status(speed, paused, finished, position.min(duration), duration)?;
+
+        let timeout = match frames.get(next) {
+            Some(frame) if !paused => {
+                Duration::from_secs_f64((frame.time - position).max(0.0) / speed)
+                    .min(REPLAY_POLL_INTERVAL)
+            }
+            _ => REPLAY_POLL_INTERVAL,
+        };
+        let tick = Instant::now();
+        if poll(timeout)? {
+            match keymap.action_for(read()?) {
+                Some(Action::Quit) => return Ok(()),
+                Some(Action::TogglePause) => paused = !paused,
+                Some(Action::IncreaseInterval) => speed = (speed * 2.0).min(MAX_SPEED),
+                Some(Action::DecreaseInterval) => speed = (speed / 2.0).max(MIN_SPEED),
+                _ => {}
+            }
+        }
+        if !paused && !finished {
+            position += tick.elapsed().as_secs_f64() * speed;
+        }
+    }
+}
+
+/// Overwrites the end of the last row with the playback speed and position.
+fn draw_status(
+    speed: f64,
+    paused: bool,
+    finished: bool,
+    position: f64,
+    duration: f64,
+) -> Result<()> {
+    let (width, height) = size()?;
+    let state = match (finished, paused) {
+        (true, _) => " [Finished]",
+        (false, true) => " [Paused]",
+        (false, false) => "",
+    };
+    let status = format!(
+        " Replay {}x {:.1}s/{:.1}s{}, press 'q' to exit",
+        speed, position, duration, state
+    );
+    let mut out = stdout();
+    queue!(
+        out,
+        MoveTo(
+            width.saturating_sub(text_width(&status) as u16),
+            height.saturating_sub(1)
+        ),
+        Clear(ClearType::UntilNewLine),
+        Print(status.reverse()),
+    )?;
+    out.flush()
+}
+
+/// Reads the output events out of an asciinema v2 recording, ignoring the header and any input,
+/// resize, or marker events.
+fn parse(recording: &str) -> std::result::Result<Vec<Frame>, WatchError> {
+    let invalid =
+        |line: usize| WatchError::Decode(format!("line {} isn't an asciinema v2 event", line));
+    let mut lines = recording.lines().enumerate();
+    match lines.next() {
+        Some((_, header)) if header.contains("\"version\"") && header.contains('2') => {}
+        _ => {
+            return Err(WatchError::Decode(
+                "not an asciinema v2 recording".to_string(),
+            ))
+        }
+    }
+
+    let mut frames = Vec::new();
+    for (i, line) in lines {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let event = line
+            .strip_prefix('[')
+            .and_then(|line| line.strip_suffix(']'))
+            .ok_or_else(|| invalid(i + 1))?;
+        let (time, rest) = event.split_once(',').ok_or_else(|| invalid(i + 1))?;
+        let time: f64 = time.trim().parse().map_err(|_| invalid(i + 1))?;
+        let (kind, rest) = parse_string(rest.trim_start()).ok_or_else(|| invalid(i + 1))?;
+        let rest = rest
+            .trim_start()
+            .strip_prefix(',')
+            .ok_or_else(|| invalid(i + 1))?;
+        let (data, _) = parse_string(rest.trim_start()).ok_or_else(|| invalid(i + 1))?;
+        if kind == "o" {
+            frames.push(Frame { time, output: data });
+        }
+    }
+    Ok(frames)
+}
+
+/// Parses the JSON string at the start of `s`, returning it unescaped along with what follows.
+fn parse_string(s: &str) -> Option<(String, &str)> {
+    let mut chars = s.strip_prefix('"')?.char_indices();
+    let mut string = String::new();
+    // Set while the second half of a UTF-16 surrogate pair is expected
+    let mut high_surrogate: Option<u32> = None;
+    while let Some((i, c)) = chars.next() {
+        let c = match c {
+            '"' => return Some((string, &s[i + 2..])),
+            '\\' => match chars.next()?.1 {
+                'n' => '\n',
+                'r' => '\r',
+                't' => '\t',
+                'b' => '\x08',
+                'f' => '\x0c',
+                'u' => {
+                    let hex: String = (0..4)
+                        .filter_map(|_| chars.next())
+                        .map(|(_, c)| c)
+                        .collect();
+                    let unit = u32::from_str_radix(&hex, 16).ok()?;
+                    match (high_surrogate.take(), unit) {
+                        (None, 0xD800..=0xDBFF) => {
+                            high_surrogate = Some(unit);
+                            continue;
+                        }
+                        (Some(high), 0xDC00..=0xDFFF) => {
+                            char::from_u32(0x10000 + ((high - 0xD800) << 10) + (unit - 0xDC00))?
+                        }
+                        (_, unit) => char::from_u32(unit).unwrap_or(char::REPLACEMENT_CHARACTER),
+                    }
+                }
+                c => c,
+            },
+            c => c,
+        };
+        string.push(c);
+    }
+    None
+}
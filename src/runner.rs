@@ -0,0 +1,144 @@
+use std::{
+    fmt,
+    io::{Error, Result},
+    process::ExitStatus,
+    time::{Instant, SystemTime},
+};
+
+use crate::error::WatchError;
+use crate::exec::{Output, Status, Step};
+use crate::plan::PlannedCommand;
+
+/// Runs the command for each execution in place of spawning it, e.g. to script its output in
+/// tests. See [`Watcher::runner`](crate::Watcher::runner).
+///
+/// Closures taking a [`PlannedCommand`] implement it, too.
+///
+/// # Examples
+///
+/// ```
+/// use watch_rs::{RunOutput, Watcher};
+///
+/// let mut runs = 0;
+/// let results: Vec<_> = Watcher::new("uptime")
+///     .count(Some(2))
+///     .runner(move |_: &_| {
+///         runs += 1;
+///         Ok(RunOutput::success(format!("run {runs}")))
+///     })
+///     .watch_iter()
+///     .collect::<Result<_, _>>()
+///     .unwrap();
+/// assert_eq!(results[1].stdout, "run 2");
+/// assert!(results[1].changed);
+/// ```
+pub trait CommandRunner: Send {
+    /// Runs `command` to completion, returning what it printed and how it ended.
+    fn run(&mut self, command: &PlannedCommand) -> Result<RunOutput>;
+}
+
+impl<F> CommandRunner for F
+where
+    F: FnMut(&PlannedCommand) -> Result<RunOutput> + Send,
+{
+    fn run(&mut self, command: &PlannedCommand) -> Result<RunOutput> {
+        self(command)
+    }
+}
+
+impl fmt::Debug for dyn CommandRunner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CommandRunner")
+    }
+}
+
+/// What a [`CommandRunner`] captured from one command.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RunOutput {
+    pub status: Status,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+impl RunOutput {
+    /// A run that exited successfully after printing `stdout`.
+    pub fn success(stdout: impl Into<Vec<u8>>) -> Self {
+        RunOutput {
+            status: Status::Exited(ExitStatus::default()),
+            stdout: stdout.into(),
+            stderr: Vec::new(),
+        }
+    }
+
+    /// A run that failed with exit code 1 after printing `stderr`.
+    pub fn failure(stderr: impl Into<Vec<u8>>) -> Self {
+        RunOutput {
+            status: Status::Exited(exit_code_one()),
+            stdout: Vec::new(),
+            stderr: stderr.into(),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn exit_code_one() -> ExitStatus {
+    std::os::unix::process::ExitStatusExt::from_raw(1 << 8)
+}
+
+#[cfg(windows)]
+fn exit_code_one() -> ExitStatus {
+    std::os::windows::process::ExitStatusExt::from_raw(1)
+}
+
+/// Calls a function for each execution in place of running the command, see
+/// [`Watcher::from_fn`](crate::Watcher::from_fn).
+pub(crate) struct FnRunner<F>(pub F);
+
+impl<F, E> CommandRunner for FnRunner<F>
+where
+    F: FnMut() -> std::result::Result<String, E> + Send,
+    E: fmt::Display,
+{
+    fn run(&mut self, _: &PlannedCommand) -> Result<RunOutput> {
+        Ok(match (self.0)() {
+            Ok(stdout) => RunOutput::success(stdout),
+            Err(err) => RunOutput::failure(err.to_string()),
+        })
+    }
+}
+
+/// Runs `steps` one after the other with `runner`, timing and combining them like
+/// [`exec::run`](crate::exec::run). The
+/// [`Watcher::pipe`](crate::Watcher::pipe) filter is spawned, though, and fed what the runner
+/// returns.
+pub(crate) fn run(
+    runner: &mut dyn CommandRunner,
+    steps: Vec<Step>,
+) -> std::result::Result<Output, WatchError> {
+    let mut output: Option<Output> = None;
+    for step in steps {
+        let started_at = SystemTime::now();
+        let start_time = Instant::now();
+        let run = runner.run(&PlannedCommand::from(&step))?;
+        let next = Output {
+            status: run.status,
+            stdout: run.stdout,
+            stderr: run.stderr,
+            // Nothing records the order the pipes were written in
+            chunks: Vec::new(),
+            started_at,
+            duration: start_time.elapsed(),
+        };
+        let next = match step.filter {
+            Some(filter) => next.filter_through(filter)?,
+            None => next,
+        }
+        .capped(step.cap)
+        .headed(step.heading.as_deref(), step.parallel);
+        output = Some(match output {
+            Some(output) => output.then(next),
+            None => next,
+        });
+    }
+    output.ok_or_else(|| Error::other("there's no command to run").into())
+}
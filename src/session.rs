@@ -0,0 +1,250 @@
+use std::{
+    env, fs,
+    io::{Error, ErrorKind, Result},
+    path::{Path, PathBuf},
+    process::ExitStatus,
+    time::{Duration, SystemTime},
+};
+
+use chrono::{DateTime, Local};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use watch_rs::{Differences, ExecutionResult, Status};
+
+use crate::Args;
+
+/// A watch saved under a name with `--session`, so that running with the same name again resumes
+/// it: the same command and options, with the runs it ended with back in the history.
+///
+/// ```json
+/// {
+///   "command": "kubectl get pods",
+///   "args": [],
+///   "interval": 2.0,
+///   "differences": "transient",
+///   "highlight": ["CrashLoopBackOff:red"],
+///   "runs": [
+///     {
+///       "ts": "2024-06-01T14:22:33.120+02:00",
+///       "exit_code": 0,
+///       "timed_out": false,
+///       "duration_ms": 412,
+///       "changed": true,
+///       "stdout": "...",
+///       "stderr": ""
+///     }
+///   ]
+/// }
+/// ```
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Session {
+    #[serde(skip)]
+    path: PathBuf,
+    command: Option<String>,
+    args: Vec<String>,
+    interval: Option<f64>,
+    differences: Option<String>,
+    highlight: Vec<String>,
+    /// The most recent runs, oldest first.
+    runs: Vec<Run>,
+}
+
+/// One run kept in a session, described like in an `--export` index.
+#[derive(Debug, Deserialize, Serialize)]
+struct Run {
+    ts: String,
+    /// The exit code a shell would report, so 128 plus the signal for a command killed by one.
+    exit_code: Option<i32>,
+    timed_out: bool,
+    duration_ms: u64,
+    changed: bool,
+    stdout: String,
+    stderr: String,
+}
+
+impl Session {
+    /// Reads the session called `name`, or starts a new one if there isn't one yet, filling in
+    /// whatever `args` left unset from it. A session given a different command than it was saved
+    /// with starts over with that command.
+    pub fn resume(name: &str, args: &mut Args) -> Result<Session> {
+        let path = sessions_dir()
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::NotFound,
+                    "there's no data directory to keep sessions in",
+                )
+            })?
+            .join(format!("{name}.json"));
+        let mut session = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).map_err(|err| invalid(&path, err))?,
+            Err(err) if err.kind() == ErrorKind::NotFound => Session::default(),
+            Err(err) => return Err(invalid(&path, err)),
+        };
+        session.path = path;
+
+        match &args.command {
+            None if session.command.is_none() => {
+                return Err(Error::new(
+                    ErrorKind::NotFound,
+                    format!("there's no session `{name}` to resume, give a command to start it"),
+                ))
+            }
+            None => {
+                args.command = session.command.clone();
+                args.args = std::mem::take(&mut session.args);
+            }
+            Some(command)
+                if session.command.as_ref() != Some(command) || session.args != args.args =>
+            {
+                session = Session {
+                    path: session.path,
+                    ..Session::default()
+                };
+            }
+            Some(_) => {}
+        }
+        args.interval = args.interval.or(session.interval);
+        if args.differences.is_none() {
+            args.differences = session
+                .differences
+                .as_deref()
+                .and_then(|differences| Differences::from_str(differences, true).ok());
+        }
+        if args.highlights.is_empty() {
+            args.highlights = session
+                .highlight
+                .iter()
+                .filter_map(|rule| rule.parse().ok())
+                .collect();
+        }
+
+        // What's saved is what this watch runs with, before the config file adds its defaults
+        session.command = args.command.clone();
+        session.args = args.args.clone();
+        session.interval = args.interval;
+        session.differences = args
+            .differences
+            .and_then(|differences| differences.to_possible_value())
+            .map(|value| value.get_name().to_string());
+        session.highlight = args.highlights.iter().map(ToString::to_string).collect();
+        Ok(session)
+    }
+
+    /// Takes the runs saved with the session, oldest first.
+    pub fn take_runs(&mut self) -> Vec<ExecutionResult> {
+        std::mem::take(&mut self.runs)
+            .into_iter()
+            .filter_map(Run::into_result)
+            .collect()
+    }
+
+    /// Writes the session with `runs` as its history, creating its directory if needed.
+    pub fn save(mut self, runs: &[ExecutionResult]) -> Result<()> {
+        self.runs = runs.iter().map(Run::from_result).collect();
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut contents = serde_json::to_string_pretty(&self)?;
+        contents.push('\n');
+        fs::write(&self.path, contents).map_err(|err| invalid(&self.path, err))
+    }
+}
+
+impl Run {
+    fn from_result(result: &ExecutionResult) -> Run {
+        let started_at: DateTime<Local> = result.started_at.into();
+        let exit_code = match result.status {
+            Status::Exited(status) | Status::Unreachable(status) => shell_code(status),
+            Status::TimedOut(_) => None,
+        };
+        Run {
+            ts: started_at.to_rfc3339(),
+            exit_code,
+            timed_out: matches!(result.status, Status::TimedOut(_)),
+            duration_ms: result.duration.as_millis() as u64,
+            changed: result.changed,
+            stdout: result.stdout.clone(),
+            stderr: result.stderr.clone(),
+        }
+    }
+
+    /// The run as it was recorded, or `None` if its time can't be read.
+    fn into_result(self) -> Option<ExecutionResult> {
+        let started_at: SystemTime = DateTime::parse_from_rfc3339(&self.ts).ok()?.into();
+        let duration = Duration::from_millis(self.duration_ms);
+        let status = match (self.timed_out, self.exit_code) {
+            (true, _) => Status::TimedOut(duration),
+            (false, code) => Status::Exited(exit_status(code.unwrap_or(1))),
+        };
+        Some(ExecutionResult {
+            stdout: self.stdout,
+            stderr: self.stderr,
+            interleaved: Vec::new(),
+            status,
+            started_at,
+            duration,
+            changed: self.changed,
+        })
+    }
+}
+
+fn shell_code(status: ExitStatus) -> Option<i32> {
+    #[cfg(unix)]
+    if let Some(signal) = std::os::unix::process::ExitStatusExt::signal(&status) {
+        return Some(128 + signal);
+    }
+    status.code()
+}
+
+/// The status of a command that exited with what a shell would report as `code`.
+#[cfg(unix)]
+fn exit_status(code: i32) -> ExitStatus {
+    use std::os::unix::process::ExitStatusExt;
+    match code {
+        129.. => ExitStatus::from_raw(code - 128),
+        code => ExitStatus::from_raw(code << 8),
+    }
+}
+
+#[cfg(windows)]
+fn exit_status(code: i32) -> ExitStatus {
+    std::os::windows::process::ExitStatusExt::from_raw(code as u32)
+}
+
+/// Parses the `--session` name, which becomes a file name.
+pub fn parse_name(s: &str) -> std::result::Result<String, String> {
+    let valid = !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        && !s.starts_with('.');
+    match valid {
+        true => Ok(s.to_string()),
+        false => Err(format!(
+            "`{s}` isn't a session name, which takes letters, digits, '-', '_', and '.'"
+        )),
+    }
+}
+
+/// Where sessions are kept: `watch-rs/sessions` under `$XDG_DATA_HOME` or `~/.local/share` on
+/// Unix, and under `%LOCALAPPDATA%` on Windows.
+fn sessions_dir() -> Option<PathBuf> {
+    let dir = if cfg!(windows) {
+        env::var_os("LOCALAPPDATA").map(PathBuf::from)
+    } else {
+        env::var_os("XDG_DATA_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| {
+                env::var_os("HOME").map(|home| Path::new(&home).join(".local").join("share"))
+            })
+    }?;
+    Some(dir.join("watch-rs").join("sessions"))
+}
+
+fn invalid(path: &Path, err: impl std::fmt::Display) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("{}: {}", path.display(), err),
+    )
+}
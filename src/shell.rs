@@ -0,0 +1,229 @@
+use std::{env, fmt, process::Command, str::FromStr};
+
+/// The shell used to run the watched command, e.g. `sh -c` or `pwsh -NoProfile -Command`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Shell {
+    program: String,
+    args: Vec<String>,
+}
+
+impl Shell {
+    /// Creates a shell that runs commands as `<program> <args...> <command>`.
+    pub fn new<I, S>(program: impl Into<String>, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Shell {
+            program: program.into(),
+            args: args.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// The shell used when none is configured: `sh -c` on Unix. On Windows it's the first of
+    /// PowerShell 7 (`pwsh`), Windows PowerShell, and `cmd` (from `%COMSPEC%`) that's installed.
+    pub fn platform_default() -> Self {
+        if !cfg!(windows) {
+            return Shell::new("sh", ["-c"]);
+        }
+        for powershell in ["pwsh", "powershell"] {
+            if on_path(&format!("{}.exe", powershell)) {
+                return Shell::new(powershell, ["-NoProfile", "-Command"]);
+            }
+        }
+        let comspec = env::var("COMSPEC").unwrap_or_else(|_| "cmd.exe".to_string());
+        Shell::new(comspec, ["/S", "/C"])
+    }
+
+    pub fn program(&self) -> &str {
+        &self.program
+    }
+
+    /// The arguments passed before the command, e.g. `-c`.
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+
+    /// The arguments for running `command`, as they're written on a Windows command line.
+    ///
+    /// Windows programs parse their own command line, which is where shells differ: most follow
+    /// the C runtime's quoting rules, while `cmd /S /C` runs everything between the outer quotes
+    /// as is, so the command's own quotes must be left alone.
+    #[cfg(any(windows, test))]
+    pub(crate) fn windows_command_line(&self, command: &str) -> String {
+        let mut line: Vec<String> = self.args.iter().map(|arg| quote_windows_arg(arg)).collect();
+        line.push(match self.is_cmd() {
+            true => format!("\"{}\"", command),
+            false => quote_windows_arg(command),
+        });
+        line.join(" ")
+    }
+
+    /// Builds the process that runs `command` through the shell.
+    pub(crate) fn command(&self, command: &str) -> Command {
+        let mut process = Command::new(&self.program);
+        #[cfg(windows)]
+        {
+            use std::os::windows::process::CommandExt;
+            process.raw_arg(self.windows_command_line(command));
+        }
+        #[cfg(not(windows))]
+        process.args(&self.args).arg(command);
+        process
+    }
+
+    /// Whether the shell is `cmd`, which doesn't unquote its arguments like other programs.
+    #[cfg(any(windows, test))]
+    fn is_cmd(&self) -> bool {
+        // Split on both separators, since `Path` only knows the host's
+        let name = self.program.rsplit(['/', '\\']).next().unwrap_or_default();
+        name.eq_ignore_ascii_case("cmd") || name.eq_ignore_ascii_case("cmd.exe")
+    }
+}
+
+impl Default for Shell {
+    fn default() -> Self {
+        Shell::platform_default()
+    }
+}
+
+/// Parses a whitespace-separated `"<program> <flag>..."` specification, e.g. `"bash -c"`.
+///
+/// # Examples
+///
+/// ```
+/// use watch_rs::Shell;
+///
+/// let shell: Shell = "bash -lc".parse().unwrap();
+/// assert_eq!(shell.program(), "bash");
+/// assert_eq!(shell.args(), ["-lc"]);
+/// ```
+impl FromStr for Shell {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split_whitespace();
+        let program = parts.next().ok_or("the shell can't be empty")?;
+        Ok(Shell::new(program, parts))
+    }
+}
+
+impl fmt::Display for Shell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.program)?;
+        for arg in &self.args {
+            write!(f, " {}", arg)?;
+        }
+        Ok(())
+    }
+}
+
+/// Quotes `arg` so the C runtime's command line parsing reads it back unchanged: wrapped in quotes
+/// if it has whitespace or quotes, with the backslashes before a quote doubled.
+#[cfg(any(windows, test))]
+fn quote_windows_arg(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains([' ', '\t', '"']) {
+        return arg.to_string();
+    }
+    let mut quoted = String::from('"');
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        match c {
+            '\\' => {
+                backslashes += 1;
+                continue;
+            }
+            '"' => quoted.push_str(&"\\".repeat(backslashes * 2 + 1)),
+            _ => quoted.push_str(&"\\".repeat(backslashes)),
+        }
+        backslashes = 0;
+        quoted.push(c);
+    }
+    // Backslashes right before the closing quote would escape it
+    quoted.push_str(&"\\".repeat(backslashes * 2));
+    quoted.push('"');
+    quoted
+}
+
+/// Whether `program` is in one of the directories on `PATH`.
+pub(crate) fn on_path(program: &str) -> bool {
+    env::var_os("PATH")
+        .is_some_and(|path| env::split_paths(&path).any(|dir| dir.join(program).is_file()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Shell;
+
+    fn cmd(command: &str) -> String {
+        Shell::new(r"C:\Windows\system32\cmd.exe", ["/S", "/C"]).windows_command_line(command)
+    }
+
+    fn pwsh(command: &str) -> String {
+        Shell::new("pwsh", ["-NoProfile", "-Command"]).windows_command_line(command)
+    }
+
+    #[test]
+    fn cmd_runs_whatever_is_between_the_outer_quotes_as_is() {
+        assert_eq!(cmd(""), r#"/S /C """#);
+        assert_eq!(cmd("echo  a\tb"), "/S /C \"echo  a\tb\"");
+        assert_eq!(
+            cmd(r#"dir "C:\Program Files""#),
+            r#"/S /C "dir "C:\Program Files"""#
+        );
+        assert_eq!(cmd(r"dir C:\my dir\"), r#"/S /C "dir C:\my dir\""#);
+        assert_eq!(
+            cmd("echo %PATH% ^& ver ^| more"),
+            r#"/S /C "echo %PATH% ^& ver ^| more""#
+        );
+    }
+
+    #[test]
+    fn cmd_is_recognized_whatever_its_path_and_case() {
+        for program in [
+            "cmd",
+            "CMD.EXE",
+            r"C:\Windows\System32\Cmd.exe",
+            "/mnt/c/cmd.exe",
+        ] {
+            let shell = Shell::new(program, ["/C"]);
+            assert_eq!(
+                shell.windows_command_line(r#"echo "a""#),
+                r#"/C "echo "a"""#
+            );
+        }
+    }
+
+    #[test]
+    fn other_shells_get_the_c_runtime_quoting() {
+        assert_eq!(pwsh(""), r#"-NoProfile -Command """#);
+        assert_eq!(pwsh("Get-Date"), "-NoProfile -Command Get-Date");
+        assert_eq!(pwsh("echo  a\tb"), "-NoProfile -Command \"echo  a\tb\"");
+        assert_eq!(
+            pwsh(r#"Write-Output "a b""#),
+            r#"-NoProfile -Command "Write-Output \"a b\"""#
+        );
+        // Backslashes are only escaped before a quote, including the closing one
+        assert_eq!(pwsh(r"C:\dir\"), r"-NoProfile -Command C:\dir\");
+        assert_eq!(
+            pwsh(r"ls C:\my dir\"),
+            r#"-NoProfile -Command "ls C:\my dir\\""#
+        );
+        assert_eq!(
+            pwsh(r#"echo C:\a\"b"#),
+            r#"-NoProfile -Command "echo C:\a\\\"b""#
+        );
+        // Only cmd gives meaning to these, so they're left alone
+        assert_eq!(pwsh("echo%PATH%^&"), "-NoProfile -Command echo%PATH%^&");
+        assert_eq!(pwsh("echo 50% ^"), r#"-NoProfile -Command "echo 50% ^""#);
+    }
+
+    #[test]
+    fn the_shells_own_arguments_are_quoted_too() {
+        let shell = Shell::new("pwsh", ["-File", r"C:\my scripts\", ""]);
+        assert_eq!(
+            shell.windows_command_line("x"),
+            r#"-File "C:\my scripts\\" "" x"#
+        );
+    }
+}
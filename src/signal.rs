@@ -0,0 +1,136 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+/// How often long waits check whether the watch was interrupted.
+pub(crate) const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Set by the handler once the process is asked to stop.
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+
+/// Set while another program runs in the foreground, which Ctrl+C is meant for instead.
+static PASSING_INTERRUPTS: AtomicBool = AtomicBool::new(false);
+
+/// How many watches are catching signals, and how they were handled before the first of them.
+static INSTALLED: Mutex<(usize, Option<Previous>)> = Mutex::new((0, None));
+
+/// Catches Ctrl+C, termination and hangup signals (console control events on Windows) until the
+/// returned guard is dropped, so the watch can kill the command, restore the terminal, and exit
+/// cleanly instead of dying wherever it happens to be.
+///
+/// In the TUI, Ctrl+C is read as a key press since the terminal is in raw mode; this covers
+/// signals sent by other processes, and Ctrl+C outside the TUI.
+pub(crate) fn install() -> SignalGuard {
+    let mut installed = INSTALLED.lock().unwrap_or_else(|err| err.into_inner());
+    if installed.0 == 0 {
+        // A signal that stopped an earlier watch doesn't stop this one
+        CANCELLED.store(false, Ordering::SeqCst);
+        installed.1 = Some(install_handler());
+    }
+    installed.0 += 1;
+    SignalGuard(())
+}
+
+/// Puts back how signals were handled before [`install`] once the last watch catching them is
+/// done.
+pub(crate) struct SignalGuard(());
+
+impl Drop for SignalGuard {
+    fn drop(&mut self) {
+        let mut installed = INSTALLED.lock().unwrap_or_else(|err| err.into_inner());
+        installed.0 -= 1;
+        if installed.0 == 0 {
+            if let Some(previous) = installed.1.take() {
+                restore_handler(previous);
+            }
+        }
+    }
+}
+
+/// Whether the process was asked to stop since [`install`] was called.
+pub(crate) fn cancelled() -> bool {
+    CANCELLED.load(Ordering::SeqCst)
+}
+
+/// Runs `f`, which runs another program in the foreground like the pager, without stopping the
+/// watch on Ctrl+C meanwhile. Termination and hangup signals still stop it.
+pub(crate) fn passing_interrupts<T>(f: impl FnOnce() -> T) -> T {
+    PASSING_INTERRUPTS.store(true, Ordering::SeqCst);
+    let result = f();
+    PASSING_INTERRUPTS.store(false, Ordering::SeqCst);
+    result
+}
+
+/// The actions the signals had before [`install`], for each signal, or on Windows whether the
+/// handler was added and so has to be taken off again.
+#[cfg(unix)]
+type Previous = Vec<(libc::c_int, libc::sigaction)>;
+#[cfg(windows)]
+type Previous = bool;
+
+#[cfg(unix)]
+fn install_handler() -> Previous {
+    extern "C" fn handle(signal: libc::c_int) {
+        if signal == libc::SIGINT && PASSING_INTERRUPTS.load(Ordering::SeqCst) {
+            return;
+        }
+        CANCELLED.store(true, Ordering::SeqCst);
+    }
+
+    let mut previous = Vec::new();
+    for signal in [libc::SIGINT, libc::SIGTERM, libc::SIGHUP] {
+        // SAFETY: the handler only stores to an atomic, which is async-signal-safe
+        unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = handle as extern "C" fn(libc::c_int) as libc::sighandler_t;
+            // Restart interrupted reads and writes instead of failing them
+            action.sa_flags = libc::SA_RESTART;
+            libc::sigemptyset(&mut action.sa_mask);
+            let mut old: libc::sigaction = std::mem::zeroed();
+            if libc::sigaction(signal, &action, &mut old) == 0 {
+                previous.push((signal, old));
+            }
+        }
+    }
+    previous
+}
+
+#[cfg(unix)]
+fn restore_handler(previous: Previous) {
+    for (signal, action) in previous {
+        // SAFETY: `action` is what `sigaction` returned for this signal
+        unsafe { libc::sigaction(signal, &action, std::ptr::null_mut()) };
+    }
+}
+
+#[cfg(windows)]
+unsafe extern "system" fn handle(event: u32) -> windows_sys::Win32::Foundation::BOOL {
+    if event == windows_sys::Win32::System::Console::CTRL_C_EVENT
+        && PASSING_INTERRUPTS.load(Ordering::SeqCst)
+    {
+        return 1;
+    }
+    CANCELLED.store(true, Ordering::SeqCst);
+    // Handled; don't let the default handler end the process
+    1
+}
+
+#[cfg(windows)]
+fn install_handler() -> Previous {
+    use windows_sys::Win32::System::Console::SetConsoleCtrlHandler;
+    // SAFETY: `handle` is a valid handler routine for the life of the process
+    unsafe { SetConsoleCtrlHandler(Some(handle), 1) != 0 }
+}
+
+#[cfg(windows)]
+fn restore_handler(added: Previous) {
+    use windows_sys::Win32::System::Console::SetConsoleCtrlHandler;
+    if added {
+        // SAFETY: this only takes `handle` off the list of handlers
+        unsafe { SetConsoleCtrlHandler(Some(handle), 0) };
+    }
+}
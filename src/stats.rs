@@ -0,0 +1,129 @@
+use std::{collections::VecDeque, time::Duration};
+
+use chrono::{DateTime, Local};
+
+use crate::exec::ExecutionResult;
+
+/// How many of the most recent durations the sparkline is drawn from.
+const RECENT_RUNS: usize = 120;
+
+/// Bars from shortest to longest, for the sparkline.
+const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Running min/avg/max of how long the command takes, across the whole watch.
+#[derive(Debug, Default)]
+pub(crate) struct DurationStats {
+    count: u32,
+    total: Duration,
+    min: Duration,
+    max: Duration,
+}
+
+impl DurationStats {
+    pub fn record(&mut self, duration: Duration) {
+        if self.count == 0 || duration < self.min {
+            self.min = duration;
+        }
+        self.max = self.max.max(duration);
+        self.total += duration;
+        self.count += 1;
+    }
+
+    /// Describes `duration` alongside the session's statistics, e.g.
+    /// "took 420ms (min 401ms, avg 415ms, max 450ms)". The statistics are left out until there
+    /// are at least two runs to compare.
+    pub fn describe(&self, duration: Duration) -> String {
+        if self.count < 2 {
+            return format!("took {}", format_duration(duration));
+        }
+        format!(
+            "took {} (min {}, avg {}, max {})",
+            format_duration(duration),
+            format_duration(self.min),
+            format_duration(self.total / self.count),
+            format_duration(self.max)
+        )
+    }
+}
+
+/// How the watch has gone so far, for the dashboard shown with [`Watcher::stats`].
+///
+/// [`Watcher::stats`]: crate::Watcher::stats
+#[derive(Clone, Debug, Default)]
+pub(crate) struct HealthStats {
+    runs: u64,
+    failures: u64,
+    last_failure: Option<DateTime<Local>>,
+    /// The durations of the last [`RECENT_RUNS`] runs, oldest first.
+    recent: VecDeque<Duration>,
+}
+
+impl HealthStats {
+    pub fn record(&mut self, result: &ExecutionResult) {
+        self.runs += 1;
+        if !result.status.success() {
+            self.failures += 1;
+            self.last_failure = Some(result.started_at.into());
+        }
+        if self.recent.len() == RECENT_RUNS {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(result.duration);
+    }
+
+    /// The counts, e.g. "Runs: 42  Succeeded: 40  Failed: 2 (95.2% success)  Last failure:
+    /// 14:03:12".
+    pub fn summary(&self) -> String {
+        let successes = self.runs - self.failures;
+        let mut summary = format!(
+            "Runs: {}  Succeeded: {}  Failed: {}",
+            self.runs, successes, self.failures
+        );
+        if self.runs > 0 {
+            let rate = successes as f64 * 100.0 / self.runs as f64;
+            summary.push_str(&format!(" ({:.1}% success)", rate));
+        }
+        if let Some(last_failure) = self.last_failure {
+            let time = last_failure.format("%H:%M:%S");
+            summary.push_str(&format!("  Last failure: {}", time));
+        }
+        summary
+    }
+
+    /// The durations of as many of the most recent runs as fit in `width` characters, as bars
+    /// scaled between the shortest and longest of them, with the longest at the end, e.g.
+    /// "Durations: ▁▁▂▁█▁ max 1.42s".
+    pub fn sparkline(&self, width: usize) -> String {
+        // Leave room for the label of the longest run overall, which is at least as long
+        let Some(&longest) = self.recent.iter().max() else {
+            return "Durations:".to_string();
+        };
+        let room =
+            width.saturating_sub(format!("Durations:  max {}", format_duration(longest)).len());
+        let shown: Vec<Duration> = self.recent.iter().rev().take(room).rev().copied().collect();
+        let shortest = shown.iter().min().copied().unwrap_or_default();
+        let longest = shown.iter().max().copied().unwrap_or_default();
+        let label = format!(" max {}", format_duration(longest));
+        let range = (longest - shortest).as_secs_f64();
+        let bars: String = shown
+            .iter()
+            .map(|duration| {
+                let level = match range > 0.0 {
+                    true => (*duration - shortest).as_secs_f64() / range,
+                    false => 0.0,
+                };
+                BARS[((level * (BARS.len() - 1) as f64).round() as usize).min(BARS.len() - 1)]
+            })
+            .collect();
+        format!("Durations: {}{}", bars, label)
+    }
+}
+
+/// Formats a command duration compactly: "420ms" under a second, "1.42s" above.
+fn format_duration(duration: Duration) -> String {
+    if duration < Duration::from_secs(1) {
+        format!("{}ms", duration.as_millis())
+    } else {
+        format!("{:.2}s", duration.as_secs_f64())
+    }
+}
@@ -0,0 +1,208 @@
+use std::{cmp::Ordering, mem};
+
+use crossterm::style::ContentStyle;
+
+use crate::theme::Theme;
+use crate::viewport::{Line, Span};
+use crate::width::text_width;
+
+/// How [`Watcher::table`](crate::Watcher::table) splits the output into columns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum TableFormat {
+    /// Comma-separated values, with double quotes around fields containing commas or newlines.
+    Csv,
+    /// Tab-separated values, one row per line.
+    Tsv,
+}
+
+/// Which column a table is sorted by, counting from 0.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Sort {
+    pub column: usize,
+    pub descending: bool,
+}
+
+/// The separator between columns.
+const GAP: &str = "  ";
+
+/// Drawn after the last column when the ones after it don't fit.
+const ELIDED: &str = "  …";
+
+/// Output split into cells, the first row being the header.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Table {
+    header: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    /// Splits `text` into rows and cells, passing each cell through `clean`, e.g. to drop control
+    /// characters. Blank lines are skipped.
+    pub fn parse(text: &str, format: TableFormat, clean: impl Fn(&str) -> String) -> Self {
+        let records = match format {
+            TableFormat::Csv => parse_csv(text),
+            TableFormat::Tsv => text
+                .lines()
+                .map(|line| line.strip_suffix('\r').unwrap_or(line))
+                .map(|line| line.split('\t').map(str::to_string).collect())
+                .collect(),
+        };
+        let mut records = records
+            .into_iter()
+            .filter(|record| record.iter().any(|cell| !cell.is_empty()))
+            .map(|record| record.iter().map(|cell| clean(cell)).collect());
+        Table {
+            header: records.next().unwrap_or_default(),
+            rows: records.collect(),
+        }
+    }
+
+    /// How many lines it's drawn as: the header and each row.
+    pub fn len(&self) -> usize {
+        self.rows.len() + 1
+    }
+
+    /// How many columns the widest row has.
+    pub fn columns(&self) -> usize {
+        self.rows
+            .iter()
+            .map(Vec::len)
+            .chain([self.header.len()])
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Draws the table with its columns aligned, sorted by `sort`, leaving out the columns on the
+    /// right that don't fit in `width`.
+    pub fn render(&self, width: usize, sort: Option<Sort>, theme: &Theme) -> Vec<Line> {
+        let columns = self.columns();
+        let cell = |row: &[String], column: usize| -> String {
+            row.get(column).cloned().unwrap_or_default()
+        };
+        let mut header: Vec<String> = (0..columns).map(|i| cell(&self.header, i)).collect();
+        if let Some(sort) = sort.filter(|sort| sort.column < columns) {
+            let arrow = if sort.descending { " ▼" } else { " ▲" };
+            header[sort.column].push_str(arrow);
+        }
+
+        let mut rows: Vec<&Vec<String>> = self.rows.iter().collect();
+        if let Some(sort) = sort {
+            rows.sort_by(|a, b| {
+                let ordering = compare(&cell(a, sort.column), &cell(b, sort.column));
+                match sort.descending {
+                    true => ordering.reverse(),
+                    false => ordering,
+                }
+            });
+        }
+
+        let widths: Vec<usize> = (0..columns)
+            .map(|i| {
+                rows.iter()
+                    .map(|row| text_width(&cell(row, i)))
+                    .chain([text_width(&header[i])])
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+        // Numbers line up on the right
+        let numeric: Vec<bool> = (0..columns)
+            .map(|i| {
+                let mut cells = rows
+                    .iter()
+                    .map(|row| cell(row, i))
+                    .filter(|c| !c.is_empty());
+                cells.clone().next().is_some() && cells.all(|c| c.trim().parse::<f64>().is_ok())
+            })
+            .collect();
+
+        // As many columns as fit, with room to show that the rest were left out
+        let shown = (1..=columns)
+            .rev()
+            .find(|&shown| {
+                let elided = if shown < columns {
+                    text_width(ELIDED)
+                } else {
+                    0
+                };
+                let total: usize = widths[..shown].iter().sum::<usize>() + GAP.len() * (shown - 1);
+                total + elided <= width
+            })
+            .unwrap_or(columns.min(1));
+
+        let draw = |cells: &[String], style: ContentStyle| -> Line {
+            let mut text = String::new();
+            for i in 0..shown {
+                if i > 0 {
+                    text.push_str(GAP);
+                }
+                let cell = cells.get(i).map(String::as_str).unwrap_or_default();
+                let padding = " ".repeat(widths[i] - text_width(cell));
+                if numeric[i] {
+                    text.push_str(&padding);
+                    text.push_str(cell);
+                } else {
+                    text.push_str(cell);
+                    // The last column isn't padded, so lines don't end in spaces
+                    if i + 1 < shown {
+                        text.push_str(&padding);
+                    }
+                }
+            }
+            if shown < columns {
+                text.push_str(ELIDED);
+            }
+            vec![Span::new(style, text)]
+        };
+
+        let mut lines = vec![draw(&header, theme.title)];
+        lines.extend(rows.iter().map(|row| draw(row, ContentStyle::new())));
+        lines
+    }
+}
+
+/// Orders numbers by value, and anything else as text.
+fn compare(a: &str, b: &str) -> Ordering {
+    match (a.trim().parse::<f64>(), b.trim().parse::<f64>()) {
+        (Ok(a), Ok(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+        _ => a.cmp(b),
+    }
+}
+
+/// Splits comma-separated `text` into records, unquoting fields in double quotes, where `""` is a
+/// quote and commas and newlines are part of the field.
+fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => quoted = false,
+                c => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() => quoted = true,
+            ',' => record.push(mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                record.push(mem::take(&mut field));
+                records.push(mem::take(&mut record));
+            }
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
@@ -0,0 +1,49 @@
+use std::{
+    borrow::Cow,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::exec::ExecutionResult;
+use crate::outcome::status_code;
+
+/// What the placeholders in the command stand for, for one execution, see
+/// [`Watcher::template`](crate::Watcher::template).
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Vars {
+    /// The execution's number, counting from 1.
+    pub iteration: u64,
+    /// How the previous execution exited, if there was one.
+    pub prev_exit: Option<i32>,
+}
+
+impl Vars {
+    /// The placeholders for execution number `iteration`, which follows `previous`.
+    pub fn new(iteration: u64, previous: Option<&ExecutionResult>) -> Self {
+        Vars {
+            iteration,
+            prev_exit: previous.map(|previous| status_code(previous.status)),
+        }
+    }
+}
+
+/// Substitutes `{i}` with the execution's number, `{ts}` with the current time in seconds since
+/// the Unix epoch, and `{prev_exit}` with the previous execution's exit code, or nothing before
+/// the first one. Anything else in braces is left as it is.
+pub(crate) fn expand<'a>(template: &'a str, vars: &Vars) -> Cow<'a, str> {
+    if !template.contains('{') {
+        return Cow::Borrowed(template);
+    }
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let prev_exit = vars
+        .prev_exit
+        .map(|code| code.to_string())
+        .unwrap_or_default();
+    Cow::Owned(
+        template
+            .replace("{i}", &vars.iteration.to_string())
+            .replace("{ts}", &timestamp.to_string())
+            .replace("{prev_exit}", &prev_exit),
+    )
+}
@@ -0,0 +1,140 @@
+use std::{
+    io::{stdout, Result, Stdout, Write},
+    panic::{self, PanicHookInfo},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+use crossterm::{
+    cursor::{Hide, Show},
+    event::{DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture},
+    execute,
+    terminal::{
+        disable_raw_mode, enable_raw_mode, EnableLineWrap, EnterAlternateScreen,
+        LeaveAlternateScreen,
+    },
+};
+
+use crate::record;
+use crate::render;
+
+/// Whether the terminal is currently set up for the TUI and needs restoring.
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Whether the TUI captures the mouse, which is given back along with the rest of the terminal.
+static MOUSE: AtomicBool = AtomicBool::new(false);
+
+/// Puts the terminal into raw mode on the alternate screen, and restores it when dropped.
+///
+/// Restoring also happens before a panic message is printed, so the message isn't lost on the
+/// alternate screen and the shell isn't left in raw mode with a hidden cursor.
+pub(crate) struct TerminalGuard {
+    /// The panic hook from before, put back when the guard is dropped.
+    previous_hook: Option<Arc<PanicHook>>,
+}
+
+type PanicHook = Box<dyn Fn(&PanicHookInfo<'_>) + Sync + Send + 'static>;
+
+impl TerminalGuard {
+    pub fn enter() -> Result<Self> {
+        enable_raw_mode()?;
+        ACTIVE.store(true, Ordering::SeqCst);
+        // Build the guard first so a failure below still restores raw mode
+        let mut guard = TerminalGuard {
+            previous_hook: None,
+        };
+        execute!(stdout(), Hide, EnterAlternateScreen, EnableLineWrap)?;
+        enable_bracketed_paste();
+        // The alternate screen starts out blank, whatever was drawn on it before
+        render::invalidate();
+
+        let previous_hook = Arc::new(panic::take_hook());
+        guard.previous_hook = Some(previous_hook.clone());
+        panic::set_hook(Box::new(move |info| {
+            restore();
+            previous_hook(info);
+        }));
+        Ok(guard)
+    }
+
+    /// Reports mouse events instead of letting the terminal select text, until the guard is
+    /// dropped.
+    pub fn capture_mouse(&self) -> Result<()> {
+        MOUSE.store(true, Ordering::SeqCst);
+        execute!(stdout(), EnableMouseCapture)
+    }
+
+    /// Hands the normal screen back while `f` runs, e.g. so a pager can use the terminal, then
+    /// returns to the TUI with everything to be redrawn.
+    pub fn suspend<T>(&self, f: impl FnOnce() -> T) -> Result<T> {
+        restore();
+        let result = f();
+        enable_raw_mode()?;
+        ACTIVE.store(true, Ordering::SeqCst);
+        execute!(stdout(), Hide, EnterAlternateScreen, EnableLineWrap)?;
+        enable_bracketed_paste();
+        if MOUSE.load(Ordering::SeqCst) {
+            execute!(stdout(), EnableMouseCapture)?;
+        }
+        render::invalidate();
+        Ok(result)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore();
+        MOUSE.store(false, Ordering::SeqCst);
+        // The hook can't be changed while panicking, and it's restoring anyway
+        if let Some(previous_hook) = self.previous_hook.take().filter(|_| !thread::panicking()) {
+            // Dropping this guard's hook leaves the previous one with nothing else holding it
+            drop(panic::take_hook());
+            match Arc::try_unwrap(previous_hook) {
+                Ok(previous_hook) => panic::set_hook(previous_hook),
+                Err(previous_hook) => panic::set_hook(Box::new(move |info| previous_hook(info))),
+            }
+        }
+    }
+}
+
+/// Leaves the alternate screen, shows the cursor, and disables raw mode, once.
+fn restore() {
+    if ACTIVE.swap(false, Ordering::SeqCst) {
+        // Nothing useful can be done if restoring fails, and this may run during a panic
+        if MOUSE.load(Ordering::SeqCst) {
+            let _ = execute!(stdout(), DisableMouseCapture);
+        }
+        let _ = execute!(stdout(), DisableBracketedPaste, LeaveAlternateScreen, Show);
+        let _ = disable_raw_mode();
+    }
+}
+
+/// Has pasted text arrive all at once, so that it can't trigger key bindings. Consoles that don't
+/// support it just deliver the keys.
+fn enable_bracketed_paste() {
+    let _ = execute!(stdout(), EnableBracketedPaste);
+}
+
+/// Where the TUI is drawn: stdout, and the recording too while one is in progress.
+pub(crate) struct Screen(Stdout);
+
+pub(crate) fn screen() -> Screen {
+    Screen(stdout())
+}
+
+impl Write for Screen {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let written = self.0.write(buf)?;
+        record::capture(&buf[..written]);
+        Ok(written)
+    }
+
+    /// Flushes stdout, and adds everything drawn since the last flush to the recording as a frame.
+    fn flush(&mut self) -> Result<()> {
+        self.0.flush()?;
+        record::flush()
+    }
+}
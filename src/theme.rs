@@ -0,0 +1,262 @@
+use std::str::FromStr;
+
+use crossterm::style::{Attribute, ContentStyle, Stylize};
+
+use crate::highlight::parse_color;
+
+/// The styles the TUI draws its own text in: the header, section titles, diff highlights, and
+/// footer. The command's output keeps its own colors.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Theme {
+    /// "Every 2.0s:" at the start of the header.
+    pub(crate) interval: ContentStyle,
+    /// The command in the header.
+    pub(crate) command: ContentStyle,
+    /// Which past run is shown, when browsing the history.
+    pub(crate) history: ContentStyle,
+    /// The command's failure or timeout, in the header and above the output.
+    pub(crate) status: ContentStyle,
+    /// The hostname and time at the end of the header.
+    pub(crate) host: ContentStyle,
+    /// Section titles like "Output:" and "StdErr:".
+    pub(crate) title: ContentStyle,
+    /// Lines of stderr, when they're interleaved with the output.
+    pub(crate) stderr: ContentStyle,
+    /// The message at the bottom right, e.g. how to exit.
+    pub(crate) footer: ContentStyle,
+    /// How long the command has been running, in the footer.
+    pub(crate) running: ContentStyle,
+    /// "[Paused]" in the footer.
+    pub(crate) paused: ContentStyle,
+    /// Messages that come and go in the footer, e.g. that the output was copied.
+    pub(crate) toast: ContentStyle,
+    /// How long the output has been quiet, and how the search is going, in the footer.
+    pub(crate) notice: ContentStyle,
+    /// The rest of the footer's status: the scroll position, timings, usage, and the countdown.
+    pub(crate) details: ContentStyle,
+    /// The cursor in the prompt at the bottom.
+    pub(crate) cursor: ContentStyle,
+    /// Output cells that changed, layered over the output's own style.
+    pub(crate) diff_changed: ContentStyle,
+    /// Lines only in the current run, in the side-by-side diff and the gutter.
+    pub(crate) diff_added: ContentStyle,
+    /// Lines only in the previous run, in the side-by-side diff and the gutter.
+    pub(crate) diff_removed: ContentStyle,
+    /// The separator between changed lines in the side-by-side diff, and the gutter's marker for
+    /// changed lines.
+    pub(crate) diff_separator: ContentStyle,
+    /// Text matching the search.
+    pub(crate) search_match: ContentStyle,
+    /// Lines crossing an [`AlertRule`](crate::AlertRule)'s threshold.
+    pub(crate) alert: ContentStyle,
+    /// Object keys, strings, and numbers, booleans, and nulls in JSON output, with
+    /// [`Watcher::json`](crate::Watcher::json).
+    pub(crate) json_key: ContentStyle,
+    pub(crate) json_string: ContentStyle,
+    pub(crate) json_literal: ContentStyle,
+}
+
+impl Theme {
+    /// Bright colors for dark terminal backgrounds (the default).
+    pub fn dark() -> Self {
+        Theme {
+            interval: ContentStyle::new().bold(),
+            command: ContentStyle::new().bold().cyan(),
+            history: ContentStyle::new().bold().yellow(),
+            status: ContentStyle::new().bold().red(),
+            host: ContentStyle::new().bold(),
+            title: ContentStyle::new().bold().underlined(),
+            stderr: ContentStyle::new().red(),
+            footer: ContentStyle::new().italic(),
+            running: ContentStyle::new().bold(),
+            paused: ContentStyle::new().reverse(),
+            toast: ContentStyle::new().bold().green(),
+            notice: ContentStyle::new().yellow(),
+            details: ContentStyle::new().dim(),
+            cursor: ContentStyle::new().reverse(),
+            diff_changed: ContentStyle::new().reverse(),
+            diff_added: ContentStyle::new().green(),
+            diff_removed: ContentStyle::new().red(),
+            diff_separator: ContentStyle::new().bold().yellow(),
+            search_match: ContentStyle::new().black().on_yellow(),
+            alert: ContentStyle::new().bold().white().on_red(),
+            json_key: ContentStyle::new().bold().blue(),
+            json_string: ContentStyle::new().green(),
+            json_literal: ContentStyle::new().yellow(),
+        }
+    }
+
+    /// Darker colors that stay readable on light terminal backgrounds.
+    pub fn light() -> Self {
+        Theme {
+            command: ContentStyle::new().bold().dark_blue(),
+            history: ContentStyle::new().bold().dark_magenta(),
+            status: ContentStyle::new().bold().dark_red(),
+            stderr: ContentStyle::new().dark_red(),
+            toast: ContentStyle::new().bold().dark_green(),
+            notice: ContentStyle::new().dark_yellow(),
+            diff_added: ContentStyle::new().dark_green(),
+            diff_removed: ContentStyle::new().dark_red(),
+            diff_separator: ContentStyle::new().bold().dark_magenta(),
+            search_match: ContentStyle::new().white().on_dark_blue(),
+            alert: ContentStyle::new().bold().white().on_dark_red(),
+            json_key: ContentStyle::new().bold().dark_blue(),
+            json_string: ContentStyle::new().dark_green(),
+            json_literal: ContentStyle::new().dark_yellow(),
+            ..Theme::dark()
+        }
+    }
+
+    /// No colors or emphasis at all, except reverse video for what changed, what matches the
+    /// search, alerts, and the prompt's cursor, which would be invisible otherwise.
+    pub fn none() -> Self {
+        let plain = ContentStyle::new();
+        Theme {
+            interval: plain,
+            command: plain,
+            history: plain,
+            status: plain,
+            host: plain,
+            title: plain,
+            stderr: plain,
+            footer: plain,
+            running: plain,
+            paused: plain,
+            toast: plain,
+            notice: plain,
+            details: plain,
+            cursor: ContentStyle::new().reverse(),
+            diff_changed: ContentStyle::new().reverse(),
+            diff_added: plain,
+            diff_removed: plain,
+            diff_separator: ContentStyle::new().reverse(),
+            search_match: ContentStyle::new().reverse(),
+            alert: ContentStyle::new().reverse(),
+            json_key: plain,
+            json_string: plain,
+            json_literal: plain,
+        }
+    }
+
+    /// Sets the style of one element, by its name: `interval`, `command`, `history`, `status`,
+    /// `host`, `title`, `stderr`, `footer`, `running`, `paused`, `toast`, `notice`, `details`,
+    /// `cursor`, `diff-changed`, `diff-added`, `diff-removed`, `diff-separator`, `search-match`,
+    /// `alert`, `json-key`, `json-string`, or `json-literal`.
+    ///
+    /// A style is a space-separated list of colors and attributes, e.g. `"bold cyan"` or
+    /// `"black on-yellow"`. Colors are named like in [`HighlightRule`](crate::HighlightRule), with
+    /// an `on-` prefix for the background. The attributes are `bold`, `dim`, `italic`,
+    /// `underlined`, `reverse`, `blink`, `rapid-blink`, and `crossed-out`, and `none` is no style.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use watch_rs::Theme;
+    ///
+    /// let mut theme: Theme = "light".parse().unwrap();
+    /// theme.set("command", "bold #005f87").unwrap();
+    /// theme.set("footer", "none").unwrap();
+    /// assert!(theme.set("command", "sparkly").is_err());
+    /// assert!(theme.set("sidebar", "bold").is_err());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns a message if there's no element named `element`, or `style` can't be parsed.
+    pub fn set(&mut self, element: &str, style: &str) -> Result<(), String> {
+        let style = parse_style(style)?;
+        let slot = match element {
+            "interval" => &mut self.interval,
+            "command" => &mut self.command,
+            "history" => &mut self.history,
+            "status" => &mut self.status,
+            "host" => &mut self.host,
+            "title" => &mut self.title,
+            "stderr" => &mut self.stderr,
+            "footer" => &mut self.footer,
+            "running" => &mut self.running,
+            "paused" => &mut self.paused,
+            "toast" => &mut self.toast,
+            "notice" => &mut self.notice,
+            "details" => &mut self.details,
+            "cursor" => &mut self.cursor,
+            "diff-changed" => &mut self.diff_changed,
+            "diff-added" => &mut self.diff_added,
+            "diff-removed" => &mut self.diff_removed,
+            "diff-separator" => &mut self.diff_separator,
+            "search-match" => &mut self.search_match,
+            "alert" => &mut self.alert,
+            "json-key" => &mut self.json_key,
+            "json-string" => &mut self.json_string,
+            "json-literal" => &mut self.json_literal,
+            _ => return Err(format!("`{element}` isn't a themeable element")),
+        };
+        *slot = style;
+        Ok(())
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::dark()
+    }
+}
+
+/// Parses the name of a built-in theme: `dark`, `light`, or `none`.
+impl FromStr for Theme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "dark" => Ok(Theme::dark()),
+            "light" => Ok(Theme::light()),
+            "none" => Ok(Theme::none()),
+            _ => Err(format!("`{s}` isn't a theme; try dark, light, or none")),
+        }
+    }
+}
+
+/// Parses a style like `"bold cyan"` or `"black on-yellow"`, see [`Theme::set`].
+fn parse_style(s: &str) -> Result<ContentStyle, String> {
+    let mut style = ContentStyle::new();
+    for word in s.split_whitespace() {
+        let attribute = match word.to_ascii_lowercase().as_str() {
+            "none" => continue,
+            "bold" => Attribute::Bold,
+            "dim" => Attribute::Dim,
+            "italic" => Attribute::Italic,
+            "underlined" | "underline" => Attribute::Underlined,
+            "reverse" => Attribute::Reverse,
+            "blink" | "slow-blink" => Attribute::SlowBlink,
+            "rapid-blink" => Attribute::RapidBlink,
+            "crossed-out" | "strikethrough" => Attribute::CrossedOut,
+            _ => {
+                let (background, color) = match word.strip_prefix("on-") {
+                    Some(color) => (true, color),
+                    None => (false, word),
+                };
+                let color = parse_color(color)
+                    .ok_or_else(|| format!("`{word}` isn't a color or attribute"))?;
+                match background {
+                    true => style.background_color = Some(color),
+                    false => style.foreground_color = Some(color),
+                }
+                continue;
+            }
+        };
+        style.attributes.set(attribute);
+    }
+    Ok(style)
+}
+
+/// `style` layered over `base`: its colors replace the base's, and its attributes are added.
+pub(crate) fn overlay(base: ContentStyle, style: ContentStyle) -> ContentStyle {
+    let mut attributes = base.attributes;
+    attributes.extend(style.attributes);
+    ContentStyle {
+        foreground_color: style.foreground_color.or(base.foreground_color),
+        background_color: style.background_color.or(base.background_color),
+        underline_color: style.underline_color.or(base.underline_color),
+        attributes,
+    }
+}
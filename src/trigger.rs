@@ -0,0 +1,105 @@
+use std::{
+    fmt,
+    io::{Error, Result},
+    path::PathBuf,
+    sync::mpsc::{channel, Receiver},
+    time::{Duration, Instant},
+};
+
+use ::notify::{recommended_watcher, EventKind, RecommendedWatcher, RecursiveMode, Watcher as _};
+
+/// How often file events are checked for while waiting for the next execution.
+pub(crate) const FILE_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// When the first execution happens, see [`Watcher::first_run`](crate::Watcher::first_run).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FirstRun {
+    /// As soon as the watch starts.
+    #[default]
+    Immediately,
+    /// Once a full interval has passed, or when the [`Watcher::cron`](crate::Watcher::cron)
+    /// schedule first matches.
+    AfterInterval,
+    /// Once this long has passed.
+    After(Duration),
+}
+
+/// "immediately", "after one interval", or "after 10s".
+impl fmt::Display for FirstRun {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FirstRun::Immediately => f.write_str("immediately"),
+            FirstRun::AfterInterval => f.write_str("after one interval"),
+            FirstRun::After(delay) => write!(f, "after {}s", delay.as_secs_f64()),
+        }
+    }
+}
+
+/// Requests a new execution when files under the watched paths change, once changes have
+/// settled for the debounce period so that saving several files only runs the command once.
+pub(crate) struct FileTrigger {
+    /// Kept alive for as long as events are wanted.
+    _watcher: RecommendedWatcher,
+    events: Receiver<()>,
+    debounce: Duration,
+    /// When the latest change not yet acted on was seen.
+    pending: Option<Instant>,
+}
+
+impl FileTrigger {
+    /// Starts watching every path in `paths`, recursively for directories.
+    pub fn new(paths: &[PathBuf], debounce: Duration) -> Result<Self> {
+        let (sender, events) = channel();
+        let mut watcher = recommended_watcher(move |event: ::notify::Result<::notify::Event>| {
+            // Reading a file shouldn't trigger a run, only changing it
+            if let Ok(event) = event {
+                if !matches!(event.kind, EventKind::Access(_)) {
+                    let _ = sender.send(());
+                }
+            }
+        })
+        .map_err(to_io_error)?;
+        for path in paths {
+            watcher
+                .watch(path, RecursiveMode::Recursive)
+                .map_err(to_io_error)?;
+        }
+        Ok(FileTrigger {
+            _watcher: watcher,
+            events,
+            debounce,
+            pending: None,
+        })
+    }
+
+    /// Whether files changed and have since been quiet for the debounce period. Returns true at
+    /// most once per burst of changes.
+    pub fn fired(&mut self) -> bool {
+        while let Ok(()) = self.events.try_recv() {
+            self.pending = Some(Instant::now());
+        }
+        match self.pending {
+            Some(changed_at) if changed_at.elapsed() >= self.debounce => {
+                self.pending = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Debug for FileTrigger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FileTrigger")
+            .field("debounce", &self.debounce)
+            .field("pending", &self.pending)
+            .finish()
+    }
+}
+
+fn to_io_error(err: ::notify::Error) -> Error {
+    match err.kind {
+        ::notify::ErrorKind::Io(err) => err,
+        _ => Error::other(err.to_string()),
+    }
+}
@@ -0,0 +1,71 @@
+/// Which part of an output longer than the [`Watcher::max_output`](crate::Watcher::max_output) is
+/// kept.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Truncation {
+    /// Keep the start, like `head`.
+    #[default]
+    Head,
+    /// Keep the end, like `tail`.
+    Tail,
+}
+
+/// How much of each of the command's pipes is kept, and which part.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct OutputCap {
+    pub bytes: usize,
+    pub truncation: Truncation,
+}
+
+/// What's kept of one of the command's pipes, and how many bytes of it were dropped.
+#[derive(Default)]
+pub(crate) struct Capture {
+    pub bytes: Vec<u8>,
+    pub dropped: usize,
+}
+
+impl Capture {
+    /// Adds `read` to what's kept, within `cap`. Returns how many of its bytes were added, and
+    /// how many were dropped from the start of what was kept to make room for them.
+    pub fn push(&mut self, read: &[u8], cap: Option<OutputCap>) -> (usize, usize) {
+        let Some(cap) = cap else {
+            self.bytes.extend_from_slice(read);
+            return (read.len(), 0);
+        };
+        match cap.truncation {
+            Truncation::Head => {
+                let kept = read.len().min(cap.bytes.saturating_sub(self.bytes.len()));
+                self.bytes.extend_from_slice(&read[..kept]);
+                self.dropped += read.len() - kept;
+                (kept, 0)
+            }
+            Truncation::Tail => {
+                self.bytes.extend_from_slice(read);
+                // Only making room once there's twice too much keeps the rest from being moved on
+                // every read
+                let dropped = match self.bytes.len() > cap.bytes.saturating_mul(2) {
+                    true => self.finish(Some(cap)),
+                    false => 0,
+                };
+                (read.len(), dropped)
+            }
+        }
+    }
+
+    /// Drops whatever is still over `cap` once the pipe has been read to the end, along with the
+    /// rest of the line it cuts into, returning how many bytes that was.
+    pub fn finish(&mut self, cap: Option<OutputCap>) -> usize {
+        let mut excess = match cap {
+            Some(cap) if cap.truncation == Truncation::Tail => {
+                self.bytes.len().saturating_sub(cap.bytes)
+            }
+            _ => 0,
+        };
+        if excess > 0 && self.bytes[excess - 1] != b'\n' {
+            let rest = self.bytes[excess..].iter().position(|&b| b == b'\n');
+            excess += rest.map_or(0, |rest| rest + 1);
+        }
+        self.bytes.drain(..excess);
+        self.dropped += excess;
+        excess
+    }
+}
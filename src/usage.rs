@@ -0,0 +1,119 @@
+use std::{
+    collections::HashSet,
+    fmt,
+    time::{Duration, Instant},
+};
+
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+
+/// How often the usage is sampled. CPU usage is averaged over the time between samples, so much
+/// shorter would make it jumpy.
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The CPU and memory a command and the processes it started use.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct Usage {
+    /// Percent of one core, so above 100 when using several.
+    pub cpu: f32,
+    /// Resident memory, in bytes.
+    pub memory: u64,
+}
+
+impl Usage {
+    fn max(self, other: Usage) -> Usage {
+        Usage {
+            cpu: self.cpu.max(other.cpu),
+            memory: self.memory.max(other.memory),
+        }
+    }
+}
+
+/// "CPU 12% RSS 3.4M".
+impl fmt::Display for Usage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CPU {:.0}% RSS {}", self.cpu, format_bytes(self.memory))
+    }
+}
+
+/// Samples the usage of a running command, keeping the highest seen.
+pub(crate) struct UsageSampler {
+    system: System,
+    last_sample: Option<Instant>,
+    current: Usage,
+    peak: Usage,
+}
+
+impl UsageSampler {
+    pub fn new() -> Self {
+        UsageSampler {
+            system: System::new(),
+            last_sample: None,
+            current: Usage::default(),
+            peak: Usage::default(),
+        }
+    }
+
+    /// The usage of the processes `pids` and their descendants, sampled again if the last sample
+    /// is old enough.
+    pub fn sample(&mut self, pids: &[u32]) -> Usage {
+        if self
+            .last_sample
+            .is_some_and(|last| last.elapsed() < SAMPLE_INTERVAL)
+        {
+            return self.current;
+        }
+        self.last_sample = Some(Instant::now());
+        self.system.refresh_processes_specifics(
+            ProcessesToUpdate::All,
+            true,
+            ProcessRefreshKind::nothing().with_cpu().with_memory(),
+        );
+
+        // The command is often a shell, running the processes that do the work
+        let processes = self.system.processes();
+        let mut tree: HashSet<Pid> = pids.iter().copied().map(Pid::from_u32).collect();
+        loop {
+            let before = tree.len();
+            for (child, process) in processes {
+                if process
+                    .parent()
+                    .is_some_and(|parent| tree.contains(&parent))
+                {
+                    tree.insert(*child);
+                }
+            }
+            if tree.len() == before {
+                break;
+            }
+        }
+        self.current = tree.iter().filter_map(|pid| processes.get(pid)).fold(
+            Usage::default(),
+            |usage, process| Usage {
+                cpu: usage.cpu + process.cpu_usage(),
+                memory: usage.memory + process.memory(),
+            },
+        );
+        self.peak = self.peak.max(self.current);
+        self.current
+    }
+
+    /// The highest usage sampled.
+    pub fn peak(&self) -> Usage {
+        self.peak
+    }
+}
+
+/// `bytes` in binary units, with a decimal below 10, e.g. "512K" or "1.5G".
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["K", "M", "G", "T"];
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    match value < 10.0 {
+        true => format!("{:.1}{}", value, UNITS[unit]),
+        false => format!("{:.0}{}", value, UNITS[unit]),
+    }
+}
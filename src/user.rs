@@ -0,0 +1,200 @@
+use std::{
+    ffi::{CStr, CString},
+    fmt,
+    io::{Error, ErrorKind, Result},
+    mem::MaybeUninit,
+    os::unix::process::CommandExt,
+    process::Command,
+    ptr,
+    str::FromStr,
+};
+
+/// A user to run the command as, see [`Watcher::user`](crate::Watcher::user).
+///
+/// Parsed from a user name or a numeric id, either of which has to be in the user database.
+///
+/// # Examples
+///
+/// ```
+/// use watch_rs::User;
+///
+/// assert!("root".parse::<User>().is_ok());
+/// assert!("0".parse::<User>().is_ok());
+/// assert!("no-such-user".parse::<User>().is_err());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct User {
+    name: String,
+    uid: u32,
+    /// The user's primary group, which the command runs in unless a [`Group`] is given.
+    gid: u32,
+}
+
+/// A group to run the command in, see [`Watcher::group`](crate::Watcher::group).
+///
+/// Parsed from a group name, or any numeric id.
+///
+/// # Examples
+///
+/// ```
+/// use watch_rs::Group;
+///
+/// assert!("0".parse::<Group>().is_ok());
+/// assert!("no-such-group".parse::<Group>().is_err());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Group {
+    name: String,
+    gid: u32,
+}
+
+/// Large enough for the entries of the user and group databases of most systems, and grown for
+/// the others.
+const BUFFER_SIZE: usize = 1024;
+
+/// Calls one of the reentrant `getpw*_r` or `getgr*_r` lookups with a buffer big enough for the
+/// entry, returning what `read` takes from it, if there is one. The entry points into the buffer,
+/// so it can only be read while it's alive.
+fn lookup<T, R>(
+    mut call: impl FnMut(*mut T, *mut libc::c_char, usize, *mut *mut T) -> libc::c_int,
+    read: impl FnOnce(&T) -> R,
+) -> Result<Option<R>> {
+    let mut buffer: Vec<libc::c_char> = vec![0; BUFFER_SIZE];
+    loop {
+        let mut entry = MaybeUninit::<T>::uninit();
+        let mut found: *mut T = ptr::null_mut();
+        let code = call(
+            entry.as_mut_ptr(),
+            buffer.as_mut_ptr(),
+            buffer.len(),
+            &mut found,
+        );
+        match code {
+            0 if found.is_null() => return Ok(None),
+            // SAFETY: the lookup succeeded, so it filled in the entry
+            0 => return Ok(Some(read(unsafe { entry.assume_init_ref() }))),
+            libc::ERANGE => buffer.resize(buffer.len() * 2, 0),
+            code => return Err(Error::from_raw_os_error(code)),
+        }
+    }
+}
+
+impl FromStr for User {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let read = |entry: &libc::passwd| User {
+            // SAFETY: the name is a C string in the entry's buffer
+            name: unsafe { CStr::from_ptr(entry.pw_name) }
+                .to_string_lossy()
+                .into_owned(),
+            uid: entry.pw_uid,
+            gid: entry.pw_gid,
+        };
+        let user = match s.parse::<u32>() {
+            // SAFETY: the buffers passed in all outlive the call
+            Ok(uid) => lookup(
+                |entry, buffer, size, found| unsafe {
+                    libc::getpwuid_r(uid, entry, buffer, size, found)
+                },
+                read,
+            ),
+            Err(_) => {
+                let name = CString::new(s).map_err(|_| format!("`{s}` isn't a user name"))?;
+                lookup(
+                    |entry, buffer, size, found| unsafe {
+                        libc::getpwnam_r(name.as_ptr(), entry, buffer, size, found)
+                    },
+                    read,
+                )
+            }
+        };
+        user.map_err(|err| format!("couldn't look up `{s}`: {err}"))?
+            .ok_or_else(|| format!("there's no user `{s}`"))
+    }
+}
+
+impl FromStr for Group {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        if let Ok(gid) = s.parse::<u32>() {
+            return Ok(Group {
+                name: s.to_string(),
+                gid,
+            });
+        }
+        let name = CString::new(s).map_err(|_| format!("`{s}` isn't a group name"))?;
+        // SAFETY: the buffers passed in all outlive the call
+        let gid = lookup(
+            |entry, buffer, size, found| unsafe {
+                libc::getgrnam_r(name.as_ptr(), entry, buffer, size, found)
+            },
+            |entry: &libc::group| entry.gr_gid,
+        );
+        let gid = gid
+            .map_err(|err| format!("couldn't look up `{s}`: {err}"))?
+            .ok_or_else(|| format!("there's no group `{s}`"))?;
+        Ok(Group {
+            name: s.to_string(),
+            gid,
+        })
+    }
+}
+
+impl fmt::Display for User {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+impl fmt::Display for Group {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+/// The user and group ids the command runs with, if it isn't the same as watchr's.
+fn ids(user: Option<&User>, group: Option<&Group>) -> Option<(u32, u32)> {
+    // SAFETY: these can't fail
+    let (euid, egid) = unsafe { (libc::geteuid(), libc::getegid()) };
+    let uid = user.map_or(euid, |user| user.uid);
+    let gid = group
+        .map(|group| group.gid)
+        .or(user.map(|user| user.gid))
+        .unwrap_or(egid);
+    (user.is_some() || group.is_some()).then_some((uid, gid))
+}
+
+/// Fails with a message explaining why if watchr can't switch to `user` and `group`, which takes
+/// root unless they're the ones it already runs as.
+pub(crate) fn check(user: Option<&User>, group: Option<&Group>) -> Result<()> {
+    let Some((uid, gid)) = ids(user, group) else {
+        return Ok(());
+    };
+    // SAFETY: these can't fail
+    let (euid, egid) = unsafe { (libc::geteuid(), libc::getegid()) };
+    if euid == 0 || (uid == euid && gid == egid) {
+        return Ok(());
+    }
+    let target = match (user, group) {
+        (Some(user), Some(group)) => format!("user `{user}` and group `{group}`"),
+        (Some(user), None) => format!("user `{user}`"),
+        (None, _) => format!(
+            "group `{}`",
+            group.map(Group::to_string).unwrap_or_default()
+        ),
+    };
+    Err(Error::new(
+        ErrorKind::PermissionDenied,
+        format!("running the command as {target} needs root, but watchr is running as uid {euid}"),
+    ))
+}
+
+/// Has `command` switch to `user` and `group` when it's spawned, between forking and running the
+/// program. Run from root, it's left in just that group, without root's supplementary groups.
+pub(crate) fn apply(command: &mut Command, user: Option<&User>, group: Option<&Group>) {
+    if let Some((uid, gid)) = ids(user, group) {
+        command.gid(gid).uid(uid);
+    }
+}
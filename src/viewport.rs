@@ -0,0 +1,630 @@
+use crossterm::style::{ContentStyle, StyledContent, Stylize};
+
+use crate::diff::SplitRow;
+use crate::table::{Sort, Table};
+use crate::theme::Theme;
+use crate::width::{char_width, text_width};
+
+/// A piece of text sharing a single style.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Span {
+    pub style: ContentStyle,
+    pub text: String,
+}
+
+impl Span {
+    pub fn new(style: ContentStyle, text: impl Into<String>) -> Self {
+        Span {
+            style,
+            text: text.into(),
+        }
+    }
+
+    /// Borrows the span as something crossterm can print.
+    pub fn styled(&self) -> StyledContent<&str> {
+        StyledContent::new(self.style, &self.text)
+    }
+}
+
+impl<D: std::fmt::Display> From<StyledContent<D>> for Span {
+    fn from(content: StyledContent<D>) -> Self {
+        Span::new(*content.style(), content.content().to_string())
+    }
+}
+
+/// A single line of output, made up of differently styled pieces.
+pub(crate) type Line = Vec<Span>;
+
+/// What's shown in the gutter next to a line of output, see [`Viewport::set_line_numbers`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Gutter {
+    pub number: usize,
+    /// `+` for an added line, `~` for a changed one, or `-` for the last line before lines were
+    /// removed, when highlighting differences.
+    pub marker: Option<char>,
+}
+
+/// The lines to show in a [`Viewport`], each with its gutter. Lines that aren't part of the
+/// command's output, like headings, have no gutter.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Body {
+    pub lines: Vec<Line>,
+    pub gutters: Vec<Option<Gutter>>,
+    /// Index of the line starting the stderr section, which runs to the end, if there is one.
+    pub stderr: Option<usize>,
+    /// The output as a table, see [`Watcher::table`](crate::Watcher::table), and the index of the
+    /// line it starts at. Its lines are drawn again to fit the width and sort.
+    pub table: Option<(usize, Table)>,
+}
+
+impl Body {
+    /// Adds a line without a gutter.
+    pub fn push(&mut self, line: Line) {
+        self.lines.push(line);
+        self.gutters.push(None);
+    }
+}
+
+/// Appends `c` to `line`, extending the last span when it has the same style.
+pub(crate) fn push_char(line: &mut Line, style: ContentStyle, c: char) {
+    match line.last_mut() {
+        Some(span) if span.style == style => span.text.push(c),
+        _ => line.push(Span::new(style, c)),
+    }
+}
+
+/// The ways the user can move the viewport.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Scroll {
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    /// Sideways, when lines are clipped instead of wrapped.
+    Left,
+    Right,
+}
+
+/// How many columns [`Scroll::Left`] and [`Scroll::Right`] move by.
+const HORIZONTAL_STEP: usize = 8;
+
+/// A line of output kept at the top of the viewport, see [`Viewport::pin_top`].
+///
+/// Lines are followed across runs by their text: to the nearest line that reads the same, or if
+/// none does, to the line at the same index, as when a row of a table changes in place.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Pin {
+    index: usize,
+    text: String,
+}
+
+/// Buffers the full output of an execution and tracks which window of it is visible.
+pub(crate) struct Viewport {
+    lines: Vec<Line>,
+    gutters: Vec<Option<Gutter>>,
+    /// Index of the stderr section's heading within `lines`.
+    stderr: Option<usize>,
+    /// Whether the stderr section is collapsed to its heading.
+    collapse_stderr: bool,
+    /// The stderr section's lines, while it's collapsed.
+    hidden: Option<Body>,
+    table: Option<(usize, Table)>,
+    /// The column the table is sorted by, kept across runs.
+    sort: Option<Sort>,
+    /// Whether the gutters are shown.
+    line_numbers: bool,
+    /// Shown instead of `lines` while the side-by-side diff is on.
+    split: Option<Vec<SplitRow>>,
+    /// Text highlighted wherever it appears, from the search prompt.
+    search: Option<String>,
+    /// Index into the lines containing a match of the match last jumped to.
+    current_match: Option<usize>,
+    /// A line to scroll to on the next call to [`Viewport::rows`], once wrapping is known.
+    jump_to_line: Option<usize>,
+    /// Whether long lines wrap onto the next row, or are clipped at the width.
+    wrap: bool,
+    /// Index of the first visible column, when clipping.
+    column: usize,
+    /// Index of the first visible row, after wrapping.
+    offset: usize,
+    /// The lines kept at the top, in the order they appear in the output.
+    pins: Vec<Pin>,
+    /// Index of the line the first scrolling row belongs to, as of the last call to
+    /// [`Viewport::rows`].
+    top_line: Option<usize>,
+    /// Height used by the last call to [`Viewport::rows`].
+    height: usize,
+    total_rows: usize,
+    theme: Theme,
+}
+
+impl Viewport {
+    pub fn new() -> Self {
+        Viewport {
+            lines: Vec::new(),
+            gutters: Vec::new(),
+            stderr: None,
+            collapse_stderr: false,
+            hidden: None,
+            table: None,
+            sort: None,
+            line_numbers: false,
+            split: None,
+            search: None,
+            current_match: None,
+            jump_to_line: None,
+            wrap: true,
+            column: 0,
+            offset: 0,
+            pins: Vec::new(),
+            top_line: None,
+            height: 0,
+            total_rows: 0,
+            theme: Theme::default(),
+        }
+    }
+
+    /// The styles for search matches, the side-by-side diff, and the gutter's markers.
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    /// Replaces the buffered output, keeping the current scroll position where possible.
+    pub fn set_lines(&mut self, mut body: Body) {
+        self.hidden = None;
+        if let (true, Some(heading)) = (self.collapse_stderr, body.stderr) {
+            let hidden = Body {
+                lines: body.lines.split_off(heading + 1),
+                gutters: body.gutters.split_off(heading + 1),
+                stderr: None,
+                table: None,
+            };
+            let note = format!("({} lines hidden, 'e' to show)", hidden.lines.len());
+            body.push(vec![Span::new(ContentStyle::new().dim(), note)]);
+            self.hidden = Some(hidden);
+        }
+        self.lines = body.lines;
+        self.gutters = body.gutters;
+        self.stderr = body.stderr;
+        self.table = body.table;
+        self.follow_pins();
+    }
+
+    /// Pins the first line of output that's scrolled into view and not already pinned, so it stays
+    /// at the top while scrolling and across runs.
+    pub fn pin_top(&mut self) {
+        if self.split.is_some() {
+            return;
+        }
+        let start = self.top_line.unwrap_or(0);
+        let Some(index) =
+            (start..self.lines.len()).find(|&i| self.gutters[i].is_some() && !self.is_pinned(i))
+        else {
+            return;
+        };
+        self.pins.push(Pin {
+            index,
+            text: line_text(&self.lines[index]),
+        });
+        self.pins.sort_by_key(|pin| pin.index);
+    }
+
+    /// Unpins every line.
+    pub fn unpin_all(&mut self) {
+        self.pins.clear();
+    }
+
+    fn is_pinned(&self, index: usize) -> bool {
+        self.pins.iter().any(|pin| pin.index == index)
+    }
+
+    /// Moves the pins to where their lines are in new output, dropping those that are gone.
+    fn follow_pins(&mut self) {
+        let is_output = |i: usize| self.gutters.get(i).is_some_and(Option::is_some);
+        let mut followed: Vec<Pin> = Vec::new();
+        for pin in &self.pins {
+            let same_text = (0..self.lines.len())
+                .filter(|&i| is_output(i) && line_text(&self.lines[i]) == pin.text)
+                .min_by_key(|&i| i.abs_diff(pin.index));
+            let index = match same_text {
+                Some(i) => i,
+                None if is_output(pin.index) => pin.index,
+                None => continue,
+            };
+            if followed.iter().all(|pin| pin.index != index) {
+                followed.push(Pin {
+                    index,
+                    text: line_text(&self.lines[index]),
+                });
+            }
+        }
+        followed.sort_by_key(|pin| pin.index);
+        self.pins = followed;
+    }
+
+    /// Collapses the stderr section to its heading, or expands it back.
+    pub fn set_collapse_stderr(&mut self, collapse: bool) {
+        self.collapse_stderr = collapse;
+        let body = self.take_lines();
+        self.set_lines(body);
+    }
+
+    pub fn is_collapsing_stderr(&self) -> bool {
+        self.collapse_stderr
+    }
+
+    /// Shows a gutter with line numbers and change markers to the left of the output.
+    pub fn set_line_numbers(&mut self, line_numbers: bool) {
+        self.line_numbers = line_numbers;
+    }
+
+    /// Shows `split` in place of the lines, or goes back to the lines when `None`.
+    pub fn set_split(&mut self, split: Option<Vec<SplitRow>>) {
+        self.split = split;
+    }
+
+    /// Highlights every occurrence of `search`, or clears the highlighting when `None`.
+    /// Matching is case-insensitive unless `search` contains an uppercase letter.
+    pub fn set_search(&mut self, search: Option<String>) {
+        self.search = search.filter(|search| !search.is_empty());
+        self.current_match = None;
+    }
+
+    /// Scrolls to the next line with a match, or the previous one when `forward` is false,
+    /// wrapping around at either end.
+    pub fn jump_to_match(&mut self, forward: bool) {
+        let matches = self.matching_lines();
+        if matches.is_empty() {
+            return;
+        }
+        let index = match (self.current_match, forward) {
+            (None, true) => 0,
+            (None, false) => matches.len() - 1,
+            (Some(current), true) => (current + 1) % matches.len(),
+            (Some(current), false) => (current + matches.len() - 1) % matches.len(),
+        };
+        self.current_match = Some(index);
+        self.jump_to_line = Some(matches[index]);
+    }
+
+    /// Describes the search for the footer, e.g. "/error: match 2/5" or "/error: no matches".
+    pub fn search_status(&self) -> Option<String> {
+        let search = self.search.as_ref()?;
+        let matches = self.matching_lines().len();
+        Some(match (matches, self.current_match) {
+            (0, _) => format!("/{}: no matches", search),
+            (n, Some(current)) => format!("/{}: match {}/{}", search, current + 1, n),
+            (n, None) => format!("/{}: {} matching lines", search, n),
+        })
+    }
+
+    /// Indices of the lines containing a match of the search.
+    fn matching_lines(&self) -> Vec<usize> {
+        let Some(search) = &self.search else {
+            return Vec::new();
+        };
+        self.lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| find_matches(line, search).iter().any(|&m| m))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Wraps long lines onto the next row, or clips them at the width when false so tabular
+    /// output stays aligned. Clipped lines can be scrolled sideways.
+    pub fn set_wrap(&mut self, wrap: bool) {
+        self.wrap = wrap;
+        self.column = 0;
+    }
+
+    pub fn is_wrapping(&self) -> bool {
+        self.wrap
+    }
+
+    pub fn is_split(&self) -> bool {
+        self.split.is_some()
+    }
+
+    /// Sorts the table by `column`, counting from 1, or in the reverse order if it already is.
+    /// Column 0 puts the rows back in the output's order.
+    pub fn sort_by(&mut self, column: usize) {
+        let Some((_, table)) = &self.table else {
+            return;
+        };
+        if column > table.columns() {
+            return;
+        }
+        self.sort = column.checked_sub(1).map(|column| Sort {
+            column,
+            descending: self
+                .sort
+                .is_some_and(|sort| sort.column == column && !sort.descending),
+        });
+    }
+
+    /// Removes the lines, leaving the viewport empty.
+    pub fn take_lines(&mut self) -> Body {
+        let mut body = Body {
+            lines: std::mem::take(&mut self.lines),
+            gutters: std::mem::take(&mut self.gutters),
+            stderr: self.stderr.take(),
+            table: self.table.take(),
+        };
+        if let (Some(hidden), Some(heading)) = (self.hidden.take(), body.stderr) {
+            body.lines.truncate(heading + 1);
+            body.gutters.truncate(heading + 1);
+            body.lines.extend(hidden.lines);
+            body.gutters.extend(hidden.gutters);
+        }
+        body
+    }
+
+    pub fn scroll(&mut self, scroll: Scroll) {
+        let page = self.height.max(1);
+        self.offset = match scroll {
+            Scroll::Up => self.offset.saturating_sub(1),
+            Scroll::Down => self.offset.saturating_add(1),
+            Scroll::PageUp => self.offset.saturating_sub(page),
+            Scroll::PageDown => self.offset.saturating_add(page),
+            Scroll::Home => 0,
+            Scroll::End => usize::MAX,
+            Scroll::Left | Scroll::Right => self.offset,
+        };
+        if !self.wrap {
+            self.column = match scroll {
+                Scroll::Left => self.column.saturating_sub(HORIZONTAL_STEP),
+                Scroll::Right => self.column.saturating_add(HORIZONTAL_STEP),
+                _ => self.column,
+            };
+        }
+    }
+
+    /// Wraps (or clips) the buffered lines to `width` and returns the rows that fit in `height`.
+    /// Side-by-side rows are truncated instead of wrapped, so both columns stay aligned.
+    pub fn rows(&mut self, width: u16, height: u16) -> Vec<Line> {
+        let gutter_width = match self.line_numbers {
+            true => gutter_width(&self.gutters),
+            false => 0,
+        };
+        let full_width = width as usize;
+        let width = full_width.saturating_sub(gutter_width).max(1);
+        if let Some((start, table)) = &self.table {
+            let lines = table.render(width, self.sort, &self.theme);
+            self.lines.splice(*start..*start + table.len(), lines);
+        }
+        if !self.wrap {
+            let longest = self.lines.iter().map(line_width).max().unwrap_or(0);
+            self.column = self.column.min(longest.saturating_sub(width));
+        }
+        // Pinned lines are drawn above the rest, which scroll beneath them
+        let mut pinned_rows = Vec::new();
+        // The line each of the scrolling rows belongs to
+        let mut row_lines = Vec::new();
+        let rows: Vec<Line> = match &self.split {
+            Some(split) => split
+                .iter()
+                .map(|row| row.render(full_width, &self.theme))
+                .collect(),
+            None => {
+                let mut rows = Vec::new();
+                for i in 0..self.lines.len() {
+                    let line_rows = self.line_rows(i, width, gutter_width);
+                    if self.is_pinned(i) {
+                        pinned_rows.extend(line_rows);
+                        continue;
+                    }
+                    if self.jump_to_line == Some(i) {
+                        self.offset = rows.len();
+                    }
+                    row_lines.extend(std::iter::repeat_n(i, line_rows.len()));
+                    rows.extend(line_rows);
+                }
+                self.jump_to_line = None;
+                rows
+            }
+        };
+
+        // Leave at least a row to scroll
+        pinned_rows.truncate((height as usize).saturating_sub(1));
+        self.height = height as usize - pinned_rows.len();
+        self.total_rows = rows.len();
+        self.offset = self.offset.min(self.total_rows.saturating_sub(self.height));
+        self.top_line = row_lines.get(self.offset).copied();
+        pinned_rows
+            .into_iter()
+            .chain(rows.into_iter().skip(self.offset).take(self.height))
+            .collect()
+    }
+
+    /// The rows line `i` takes up at `width`, highlighted, wrapped or clipped, and with its gutter
+    /// if it's `gutter_width` wide.
+    fn line_rows(&self, i: usize, width: usize, gutter_width: usize) -> Vec<Line> {
+        let line = match &self.search {
+            Some(search) => highlight(&self.lines[i], search, self.theme.search_match),
+            None => self.lines[i].clone(),
+        };
+        let line_rows = match self.wrap {
+            true => wrap(&line, width),
+            false => vec![clip(&line, self.column, width)],
+        };
+        if gutter_width == 0 {
+            return line_rows;
+        }
+        // Only the first row of a wrapped line is numbered
+        let gutter = self.gutters.get(i).copied().flatten();
+        line_rows
+            .into_iter()
+            .enumerate()
+            .map(|(j, row)| {
+                let mut gutter =
+                    render_gutter(gutter.filter(|_| j == 0), gutter_width, &self.theme);
+                gutter.extend(row);
+                gutter
+            })
+            .collect()
+    }
+
+    /// Describes the visible window, e.g. "Lines 11-40/120" or "Lines 1-30/30, column 17", or
+    /// `None` when everything fits.
+    pub fn position(&self) -> Option<String> {
+        let mut position = Vec::new();
+        if self.total_rows > self.height {
+            let last = (self.offset + self.height).min(self.total_rows);
+            position.push(format!(
+                "Lines {}-{}/{}",
+                self.offset + 1,
+                last,
+                self.total_rows
+            ));
+        }
+        if self.column > 0 {
+            position.push(format!("column {}", self.column + 1));
+        }
+        if !self.pins.is_empty() && self.split.is_none() {
+            position.push(format!("{} pinned", self.pins.len()));
+        }
+        (!position.is_empty()).then(|| position.join(", "))
+    }
+}
+
+/// Splits a line into rows of at most `width` columns, preserving styles. A wide character that
+/// doesn't fit at the end of a row starts the next one.
+fn wrap(line: &Line, width: usize) -> Vec<Line> {
+    let width = width.max(1);
+    let mut rows: Vec<Line> = vec![Vec::new()];
+    let mut row_width = 0;
+    for span in line {
+        for c in span.text.chars() {
+            let c_width = char_width(c);
+            if row_width + c_width > width && row_width > 0 {
+                rows.push(Vec::new());
+                row_width = 0;
+            }
+            push_char(rows.last_mut().unwrap(), span.style, c);
+            row_width += c_width;
+        }
+    }
+    rows
+}
+
+/// The part of `line` from column `start` that fits in `width`, preserving styles. A wide
+/// character cut in half by either edge is shown as a space.
+fn clip(line: &Line, start: usize, width: usize) -> Line {
+    let mut clipped = Line::new();
+    let end = start + width;
+    let mut column = 0;
+    let mut shown = false;
+    let chars = line
+        .iter()
+        .flat_map(|span| span.text.chars().map(move |c| (span.style, c)));
+    for (style, c) in chars {
+        let c_width = char_width(c);
+        // Combining marks go with the character before them
+        if c_width == 0 {
+            if shown {
+                push_char(&mut clipped, style, c);
+            }
+            continue;
+        }
+        if column >= end {
+            break;
+        }
+        let from = column;
+        column += c_width;
+        shown = from >= start && column <= end;
+        if shown {
+            push_char(&mut clipped, style, c);
+        } else if column > start {
+            push_char(&mut clipped, style, ' ');
+        }
+    }
+    clipped
+}
+
+/// The columns taken by the gutter: the widest line number, the marker, and a space.
+fn gutter_width(gutters: &[Option<Gutter>]) -> usize {
+    let widest = gutters
+        .iter()
+        .flatten()
+        .map(|gutter| gutter.number)
+        .max()
+        .unwrap_or(0);
+    widest.to_string().len() + 2
+}
+
+/// A gutter padded to `width`, or blank when `None`.
+fn render_gutter(gutter: Option<Gutter>, width: usize, theme: &Theme) -> Line {
+    let Some(gutter) = gutter else {
+        return vec![Span::new(ContentStyle::new(), " ".repeat(width))];
+    };
+    let marker = gutter.marker.unwrap_or(' ');
+    let style = match marker {
+        '+' => theme.diff_added,
+        '-' => theme.diff_removed,
+        _ => theme.diff_separator,
+    };
+    vec![
+        format!("{:>1$}", gutter.number, width - 2).dim().into(),
+        StyledContent::new(style, marker).bold().into(),
+        Span::new(ContentStyle::new(), " "),
+    ]
+}
+
+/// The text of `line`, without its styles.
+pub(crate) fn line_text(line: &Line) -> String {
+    line.iter().map(|span| span.text.as_str()).collect()
+}
+
+/// The columns `line` takes up.
+pub(crate) fn line_width(line: &Line) -> usize {
+    line.iter().map(|span| text_width(&span.text)).sum()
+}
+
+/// Which chars of `line` are part of a match of `search`, smart-case like the search prompt.
+fn find_matches(line: &Line, search: &str) -> Vec<bool> {
+    let ignore_case = !search.chars().any(char::is_uppercase);
+    let fold = |c: char| match ignore_case {
+        true => c.to_lowercase().next().unwrap_or(c),
+        false => c,
+    };
+    let text: Vec<char> = line
+        .iter()
+        .flat_map(|span| span.text.chars())
+        .map(fold)
+        .collect();
+    let search: Vec<char> = search.chars().map(fold).collect();
+
+    let mut matched = vec![false; text.len()];
+    if search.is_empty() || search.len() > text.len() {
+        return matched;
+    }
+    for start in 0..=text.len() - search.len() {
+        if text[start..start + search.len()] == search[..] {
+            matched[start..start + search.len()].fill(true);
+        }
+    }
+    matched
+}
+
+/// Restyles the chars of `line` that match `search` so they stand out, keeping everything else.
+fn highlight(line: &Line, search: &str, match_style: ContentStyle) -> Line {
+    let matched = find_matches(line, search);
+    if !matched.iter().any(|&m| m) {
+        return line.clone();
+    }
+    let mut highlighted = Line::new();
+    let chars = line
+        .iter()
+        .flat_map(|span| span.text.chars().map(move |c| (span.style, c)));
+    for ((style, c), matched) in chars.zip(matched) {
+        let style = match matched {
+            true => match_style,
+            false => style,
+        };
+        push_char(&mut highlighted, style, c);
+    }
+    highlighted
+}
@@ -0,0 +1,2492 @@
+use std::{
+    borrow::Cow,
+    fmt,
+    io::{stdout, IsTerminal, Result, Write},
+    ops::ControlFlow,
+    path::PathBuf,
+    process::Command,
+    sync::mpsc::{self, Receiver},
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Local};
+use crossterm::{
+    cursor::*,
+    event::{poll, read, Event, KeyEventKind},
+    execute, queue,
+    style::*,
+};
+
+use gethostname::gethostname;
+use regex::Regex;
+
+use crate::alert::{self, AlertRule, AlertState};
+use crate::ansi;
+use crate::clipboard::Clipboard;
+use crate::cron::Cron;
+use crate::decode::Encoding;
+use crate::diff::{self, DiffHighlighter, Differences, SplitRow};
+use crate::error::WatchError;
+use crate::events::{self, ChangeEvent};
+use crate::exec::{self, ExecutionResult, Filter, Pipe, Status, Step};
+use crate::export::{self, export};
+use crate::headless::WatchStream;
+use crate::highlight::{self, HighlightRule};
+use crate::history::History;
+use crate::hooks::{self, Hooks};
+use crate::input::{self, Action, Keymap};
+use crate::jq::{self, JqFilter};
+use crate::json::OutputMode;
+use crate::log::{save_snapshot, snapshot_name, LogSink};
+use crate::notify::{self, Notify};
+use crate::outcome::{ExitReason, Outcome};
+use crate::overlay::Overlay;
+use crate::pager;
+use crate::panes::{Layout, Pane};
+use crate::prompt::{Edit, LineEditor, Purpose};
+use crate::record;
+use crate::remote::{ContainerRuntime, Remote};
+use crate::render::{Frame, Renderer, Target};
+use crate::runner::{self, CommandRunner, FnRunner};
+use crate::shell::Shell;
+use crate::signal::{self, CANCEL_POLL_INTERVAL};
+use crate::stats::{DurationStats, HealthStats};
+use crate::table::{Table, TableFormat};
+use crate::template::{self, Vars};
+use crate::terminal::{screen, TerminalGuard};
+use crate::theme::{overlay, Theme};
+use crate::trigger::{FileTrigger, FirstRun, FILE_POLL_INTERVAL};
+use crate::truncation::{OutputCap, Truncation};
+use crate::usage::UsageSampler;
+#[cfg(unix)]
+use crate::user::{self, Group, User};
+use crate::viewport::{line_text, Body, Gutter, Line, Span, Viewport};
+use crate::width::{fit, text_width};
+use crate::MIN_INTERVAL;
+
+/// Builder used to configure and run a watch.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use watch_rs::Watcher;
+///
+/// let outcome = Watcher::new("ls")
+///     .arg("-l")
+///     .interval(Duration::from_secs(2))
+///     .run()
+///     .expect("watch failed");
+/// ```
+#[derive(Debug)]
+pub struct Watcher {
+    pub(crate) command: String,
+    pub(crate) args: Vec<String>,
+    pub(crate) interval: Duration,
+    pub(crate) cron: Option<Cron>,
+    pub(crate) first_run: FirstRun,
+    pub(crate) chgexit: bool,
+    pub(crate) count: Option<u64>,
+    pub(crate) precise: bool,
+    pub(crate) backoff: Option<Duration>,
+    pub(crate) wait_first_success: Option<Duration>,
+    pub(crate) run_for: Option<Duration>,
+    pub(crate) until: Option<Regex>,
+    pub(crate) while_matches: Option<Regex>,
+    pub(crate) grep: Option<Regex>,
+    pub(crate) grep_v: Option<Regex>,
+    pub(crate) differences: Option<Differences>,
+    pub(crate) color: bool,
+    pub(crate) show_control_chars: bool,
+    pub(crate) tab_width: usize,
+    pub(crate) table: Option<TableFormat>,
+    pub(crate) json: bool,
+    pub(crate) jq: Option<JqFilter>,
+    pub(crate) errexit: bool,
+    pub(crate) beep: bool,
+    pub(crate) quiet_empty: bool,
+    pub(crate) exec: bool,
+    pub(crate) pipe: Option<String>,
+    pub(crate) shell: Shell,
+    pub(crate) env: Vec<(String, String)>,
+    pub(crate) current_dir: Option<PathBuf>,
+    pub(crate) remote: Option<Remote>,
+    pub(crate) ssh_options: Vec<String>,
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) runner: Option<Box<dyn CommandRunner>>,
+    pub(crate) title: bool,
+    pub(crate) encoding: Encoding,
+    pub(crate) max_output: Option<u64>,
+    pub(crate) truncation: Truncation,
+    pub(crate) stream: bool,
+    pub(crate) interleave: bool,
+    pub(crate) stats: bool,
+    pub(crate) interactive: bool,
+    pub(crate) wrap: bool,
+    pub(crate) line_numbers: bool,
+    pub(crate) mouse: bool,
+    pub(crate) hooks: Hooks,
+    pub(crate) log_file: Option<PathBuf>,
+    pub(crate) log_max_size: Option<u64>,
+    pub(crate) export: Option<PathBuf>,
+    #[cfg(unix)]
+    pub(crate) user: Option<User>,
+    #[cfg(unix)]
+    pub(crate) group: Option<Group>,
+    #[cfg(feature = "metrics")]
+    pub(crate) metrics_port: Option<u16>,
+    pub(crate) record: Option<PathBuf>,
+    pub(crate) output: OutputMode,
+    pub(crate) history: usize,
+    pub(crate) preloaded: Vec<ExecutionResult>,
+    pub(crate) notify: Option<Notify>,
+    pub(crate) on_change_command: Option<String>,
+    pub(crate) pager: Option<String>,
+    pub(crate) template: bool,
+    pub(crate) panes: Vec<Pane>,
+    pub(crate) and: Vec<String>,
+    pub(crate) foreach: Vec<String>,
+    pub(crate) layout: Layout,
+    pub(crate) watch_paths: Vec<PathBuf>,
+    pub(crate) debounce: Duration,
+    pub(crate) on_interval: bool,
+    pub(crate) highlights: Vec<HighlightRule>,
+    pub(crate) alerts: Vec<AlertRule>,
+    pub(crate) keymap: Keymap,
+    pub(crate) theme: Theme,
+    /// Where the TUI is drawn instead of the terminal.
+    pub(crate) draw_to: Option<Renderer<Target>>,
+}
+
+impl Watcher {
+    /// Creates a watcher for `command`, run every 2 seconds by default.
+    pub fn new(command: impl Into<String>) -> Self {
+        Watcher {
+            command: command.into(),
+            args: Vec::new(),
+            interval: Duration::from_secs(2),
+            cron: None,
+            first_run: FirstRun::default(),
+            chgexit: false,
+            count: None,
+            precise: false,
+            backoff: None,
+            wait_first_success: None,
+            run_for: None,
+            until: None,
+            while_matches: None,
+            grep: None,
+            grep_v: None,
+            differences: None,
+            color: true,
+            show_control_chars: false,
+            tab_width: ansi::TAB_WIDTH,
+            table: None,
+            json: false,
+            jq: None,
+            errexit: false,
+            beep: false,
+            quiet_empty: false,
+            exec: false,
+            pipe: None,
+            shell: Shell::platform_default(),
+            env: Vec::new(),
+            current_dir: None,
+            remote: None,
+            ssh_options: Vec::new(),
+            timeout: None,
+            title: true,
+            encoding: Encoding::default(),
+            max_output: None,
+            truncation: Truncation::default(),
+            stream: false,
+            interleave: false,
+            stats: false,
+            interactive: false,
+            wrap: true,
+            line_numbers: false,
+            mouse: false,
+            hooks: Hooks::default(),
+            runner: None,
+            log_file: None,
+            log_max_size: None,
+            export: None,
+            #[cfg(unix)]
+            user: None,
+            #[cfg(unix)]
+            group: None,
+            #[cfg(feature = "metrics")]
+            metrics_port: None,
+            record: None,
+            output: OutputMode::default(),
+            history: 50,
+            preloaded: Vec::new(),
+            notify: None,
+            on_change_command: None,
+            pager: None,
+            template: false,
+            panes: Vec::new(),
+            and: Vec::new(),
+            foreach: Vec::new(),
+            layout: Layout::default(),
+            watch_paths: Vec::new(),
+            debounce: Duration::from_millis(200),
+            on_interval: true,
+            highlights: Vec::new(),
+            alerts: Vec::new(),
+            keymap: Keymap::default(),
+            theme: Theme::default(),
+            draw_to: None,
+        }
+    }
+
+    /// Creates a watcher that calls `source` on every execution and shows the text it returns,
+    /// instead of running a command, so that a program can show its own data the way a command's
+    /// output is: with the header, diffing, scrolling, history, and exit conditions. `label` is
+    /// shown in the header in place of the command, and an error is shown like a failing command's
+    /// stderr.
+    ///
+    /// It's a [`Watcher::runner`], so it's called by [`Watcher::watch_iter`], JSON output, and the
+    /// TUI, which ignores [`Watcher::stream`] and [`Watcher::interactive`] for it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use watch_rs::Watcher;
+    ///
+    /// let mut polls = 0;
+    /// let results: Vec<_> = Watcher::from_fn("queue depth", move || {
+    ///     polls += 1;
+    ///     match polls {
+    ///         1 => Ok(format!("{} jobs", polls * 10)),
+    ///         _ => Err("the queue is unreachable"),
+    ///     }
+    /// })
+    /// .count(Some(2))
+    /// .watch_iter()
+    /// .collect::<Result<_, _>>()
+    /// .unwrap();
+    /// assert_eq!(results[0].stdout, "10 jobs");
+    /// assert!(!results[1].status.success());
+    /// assert_eq!(results[1].stderr, "the queue is unreachable");
+    /// ```
+    pub fn from_fn<F, E>(label: impl Into<String>, source: F) -> Self
+    where
+        F: FnMut() -> std::result::Result<String, E> + Send + 'static,
+        E: fmt::Display,
+    {
+        Watcher::new(label).runner(FnRunner(source))
+    }
+
+    /// Appends a single argument to pass to the command.
+    pub fn arg(mut self, arg: impl Into<String>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// Appends multiple arguments to pass to the command.
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// Sets the interval between command executions. Anything below [`MIN_INTERVAL`] is clamped up to it.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval.max(Duration::from_secs_f64(MIN_INTERVAL));
+        self
+    }
+
+    /// Run the command whenever `cron` matches, e.g. every weekday hour with `0 9-17 * * mon-fri`,
+    /// instead of on the interval. The first run still happens right away, and the header counts
+    /// down to the next one. [`Watcher::pane`]s keep to their intervals.
+    pub fn cron(mut self, cron: Option<Cron>) -> Self {
+        self.cron = cron;
+        self
+    }
+
+    /// Sets when the first execution happens: right away (the default), after a full interval,
+    /// or after a delay, e.g. to give a service that was just deployed time to start. Input is
+    /// handled while waiting, and 'r' starts it early. [`Watcher::pane`]s start right away.
+    pub fn first_run(mut self, first_run: FirstRun) -> Self {
+        self.first_run = first_run;
+        self
+    }
+
+    /// Exit as soon as the command's output differs from the previous execution.
+    pub fn chgexit(mut self, chgexit: bool) -> Self {
+        self.chgexit = chgexit;
+        self
+    }
+
+    /// Exit after running the command `count` times.
+    pub fn count(mut self, count: Option<u64>) -> Self {
+        self.count = count;
+        self
+    }
+
+    /// Schedule executions on a fixed cadence from the start of the watch (t0, t0+n, t0+2n, ...)
+    /// instead of waiting a full interval after each one finishes, so slow commands don't drift.
+    /// Slots missed while the command was still running are skipped.
+    pub fn precise(mut self, precise: bool) -> Self {
+        self.precise = precise;
+        self
+    }
+
+    /// While the command keeps failing, double the interval after each failure, up to `max`. The
+    /// interval goes back to normal after the first success.
+    pub fn backoff(mut self, max: Option<Duration>) -> Self {
+        self.backoff = max;
+        self
+    }
+
+    /// Until the command first succeeds, retry it every `retry` instead of on the interval, e.g. to
+    /// watch a service that's still starting up.
+    pub fn wait_first_success(mut self, retry: Option<Duration>) -> Self {
+        self.wait_first_success = retry;
+        self
+    }
+
+    /// Stop watching once `budget` of wall-clock time has passed since the watch started.
+    pub fn run_for(mut self, budget: Option<Duration>) -> Self {
+        self.run_for = budget;
+        self
+    }
+
+    /// Also runs the command whenever files under `path` change, recursively for directories.
+    /// Can be called more than once to watch several paths.
+    ///
+    /// A burst of changes only triggers one execution, once the files have been quiet for
+    /// [`Watcher::debounce`]. Commands that write under a watched path will trigger themselves.
+    pub fn watch_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.watch_paths.push(path.into());
+        self
+    }
+
+    /// Sets how long files must be left alone after a change before the command runs.
+    /// Defaults to 200ms.
+    pub fn debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    /// Run the command on the interval (the default). Turning this off while watching paths runs
+    /// the command only when files change; without watched paths it has no effect.
+    pub fn on_interval(mut self, on_interval: bool) -> Self {
+        self.on_interval = on_interval;
+        self
+    }
+
+    /// Exit as soon as the command's stdout matches `pattern`, e.g. `Running` for a pod status.
+    pub fn until(mut self, pattern: Option<Regex>) -> Self {
+        self.until = pattern;
+        self
+    }
+
+    /// Keep watching only while the command's stdout matches `pattern`, exiting once it doesn't.
+    pub fn while_matches(mut self, pattern: Option<Regex>) -> Self {
+        self.while_matches = pattern;
+        self
+    }
+
+    /// Show only the lines of stdout that match `pattern`. The filtered output is what gets
+    /// compared between executions, diffed, and checked by [`Watcher::until`].
+    pub fn grep(mut self, pattern: Option<Regex>) -> Self {
+        self.grep = pattern;
+        self
+    }
+
+    /// Hide the lines of stdout that match `pattern`, like `grep -v`. See [`Watcher::grep`].
+    pub fn grep_v(mut self, pattern: Option<Regex>) -> Self {
+        self.grep_v = pattern;
+        self
+    }
+
+    /// Highlight the characters that changed between executions.
+    pub fn differences(mut self, differences: Option<Differences>) -> Self {
+        self.differences = differences;
+        self
+    }
+
+    /// Interpret ANSI color and style sequences in the command's output (the default), or strip them.
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Show the control characters and escape sequences in the output that aren't interpreted, in
+    /// caret notation like `^G` or `^[]0;title^G`, instead of dropping them. Either way they can't
+    /// corrupt the screen.
+    pub fn show_control_chars(mut self, show: bool) -> Self {
+        self.show_control_chars = show;
+        self
+    }
+
+    /// Expand tabs in the output to spaces, up to the next multiple of `width` columns (8 by
+    /// default), so that tables line up however the terminal would have shown them. Anything
+    /// below 1 is clamped up to it.
+    pub fn tabs(mut self, width: usize) -> Self {
+        self.tab_width = width.max(1);
+        self
+    }
+
+    /// Show the output as a table, split into columns by `format` and aligned, with the first row
+    /// as its header. The columns that don't fit the terminal are left out, and pressing a
+    /// column's number sorts the rows by it. Highlights other than the search's don't apply to it.
+    pub fn table(mut self, format: Option<TableFormat>) -> Self {
+        self.table = format;
+        self
+    }
+
+    /// Pretty-print output that's JSON, or a JSON value per line, and color its keys, strings,
+    /// and other values. Output that isn't JSON is left as it is. It's reformatted before
+    /// changes are detected, so only changes to the values count.
+    pub fn json(mut self, json: bool) -> Self {
+        self.json = json;
+        self
+    }
+
+    /// Show only the parts of the JSON output `filter` selects, like jq would, pretty-printed as
+    /// with [`Watcher::json`]. If the filter doesn't apply to the output, why is shown instead.
+    pub fn jq(mut self, filter: Option<JqFilter>) -> Self {
+        self.jq = filter;
+        self
+    }
+
+    /// Freeze the screen when the command exits with a non-zero status, then exit with an error once
+    /// a key is pressed. By default failures are shown in the header and watching continues.
+    pub fn errexit(mut self, errexit: bool) -> Self {
+        self.errexit = errexit;
+        self
+    }
+
+    /// Ring the terminal bell whenever the command exits with a non-zero status.
+    pub fn beep(mut self, beep: bool) -> Self {
+        self.beep = beep;
+        self
+    }
+
+    /// When the command prints nothing, keep the last output it printed on screen, noting since
+    /// when there's been none, instead of blanking it. Useful for commands that are usually quiet,
+    /// like a `grep` waiting for a match. Without the TUI, runs that print nothing are left out.
+    pub fn quiet_empty(mut self, quiet_empty: bool) -> Self {
+        self.quiet_empty = quiet_empty;
+        self
+    }
+
+    /// Spawn the command directly with its arguments instead of passing it to a shell,
+    /// which avoids quoting issues and shell startup overhead.
+    pub fn exec(mut self, exec: bool) -> Self {
+        self.exec = exec;
+        self
+    }
+
+    /// Pipe the command's stdout through `filter`, a shell command line like `sort | head -20`,
+    /// and show what it prints instead. The two run side by side, connected by a pipe, so this
+    /// works with [`Watcher::exec`] too; the filter always runs on this machine. If it fails, that's
+    /// noted under the output without counting as the command failing.
+    pub fn pipe(mut self, filter: Option<String>) -> Self {
+        self.pipe = filter;
+        self
+    }
+
+    /// Sets an environment variable for the command, on top of those inherited from this process.
+    pub fn env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.push((key.into(), value.into()));
+        self
+    }
+
+    /// Sets multiple environment variables for the command. Later values win for repeated keys.
+    pub fn envs<I, K, V>(mut self, vars: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.env.extend(
+            vars.into_iter()
+                .map(|(key, value)| (key.into(), value.into())),
+        );
+        self
+    }
+
+    /// Runs the command in `dir` instead of the current working directory. The directory is shown
+    /// in the header.
+    pub fn current_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.current_dir = Some(dir.into());
+        self
+    }
+
+    /// Runs the command on `destination` over SSH, e.g. `user@host`, through the remote user's
+    /// shell. The host is shown in the header, and [`Watcher::env`] and
+    /// [`Watcher::current_dir`] apply on the remote side. In exec mode the arguments are quoted
+    /// for a POSIX shell.
+    ///
+    /// Connections never prompt, and are shared across executions on Unix. When one fails the
+    /// run is [`Status::Unreachable`] and retried on the next interval.
+    pub fn ssh(mut self, destination: impl Into<String>) -> Self {
+        self.remote = Some(Remote::Ssh {
+            destination: destination.into(),
+        });
+        self
+    }
+
+    /// Passes `option` to `ssh` as `-o <option>`, e.g. `Port=2222`, overriding the defaults.
+    pub fn ssh_option(mut self, option: impl Into<String>) -> Self {
+        self.ssh_options.push(option.into());
+        self
+    }
+
+    /// Runs the command in the running container `name` with `docker exec` or `podman exec`,
+    /// through its `sh`, replacing any [`Watcher::ssh`] host. The container is shown in the
+    /// header, and [`Watcher::env`] and [`Watcher::current_dir`] apply inside it.
+    ///
+    /// When the container isn't running the run is [`Status::Unreachable`] and retried on the
+    /// next interval.
+    pub fn container(mut self, name: impl Into<String>, runtime: ContainerRuntime) -> Self {
+        self.remote = Some(Remote::Container {
+            name: name.into(),
+            runtime,
+        });
+        self
+    }
+
+    /// Sets the shell used to run the command. Defaults to [`Shell::platform_default`].
+    /// Ignored in [`Watcher::exec`] mode.
+    pub fn shell(mut self, shell: Shell) -> Self {
+        self.shell = shell;
+        self
+    }
+
+    /// Kill the command, and any processes it started, if an execution takes longer than `timeout`.
+    /// The output captured up to that point is still shown.
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Runs each execution's command with `runner` instead of spawning it, e.g. to script its
+    /// output in tests. Everything else, from diffing to exit conditions, works as usual.
+    ///
+    /// The runner is used by [`Watcher::watch_iter`], JSON output, and the TUI, which ignores
+    /// [`Watcher::stream`] and [`Watcher::interactive`] then, there being no process to show as it
+    /// runs or to type into. [`Watcher::pane`]s and the async engine still spawn processes.
+    /// [`Watcher::timeout`] is left to the runner.
+    pub fn runner(mut self, runner: impl CommandRunner + 'static) -> Self {
+        self.runner = Some(Box::new(runner));
+        self
+    }
+
+    /// Show the header and footer around the output (the default). Without them the output gets
+    /// the whole terminal.
+    pub fn title(mut self, title: bool) -> Self {
+        self.title = title;
+        self
+    }
+
+    /// Colors every match of `rule`'s regex in the output. Can be called more than once; later
+    /// rules win where matches overlap.
+    pub fn highlight(mut self, rule: HighlightRule) -> Self {
+        self.highlights.push(rule);
+        self
+    }
+
+    /// Highlights the lines of output crossing `rule`'s threshold, and beeps and shows a
+    /// desktop notification when a run starts crossing it. Can be called more than once.
+    pub fn alert(mut self, rule: AlertRule) -> Self {
+        self.alerts.push(rule);
+        self
+    }
+
+    /// Sets the encoding used to decode the command's output. Defaults to UTF-8; invalid bytes
+    /// never cause an error, they're shown as U+FFFD instead.
+    pub fn encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Keeps at most `max_size` bytes of each of stdout and stderr per command, dropping the rest
+    /// as it's read so that a command printing far more doesn't use up memory. Where bytes were
+    /// dropped, the output notes how many, and what's shown, kept in the history, compared, and
+    /// logged is what was kept. No limit by default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use watch_rs::{Truncation, Watcher};
+    ///
+    /// let result = Watcher::new("seq 1000")
+    ///     .max_output(Some(10))
+    ///     .truncation(Truncation::Tail)
+    ///     .watch_iter()
+    ///     .next()
+    ///     .unwrap()
+    ///     .unwrap();
+    /// assert_eq!(result.stdout, "[output truncated (3884 bytes dropped)]\n999\n1000");
+    /// ```
+    pub fn max_output(mut self, max_size: Option<u64>) -> Self {
+        self.max_output = max_size;
+        self
+    }
+
+    /// Sets which part of an output longer than the [`Watcher::max_output`] is kept. Defaults to
+    /// [`Truncation::Head`], the start.
+    pub fn truncation(mut self, truncation: Truncation) -> Self {
+        self.truncation = truncation;
+        self
+    }
+
+    /// Render the command's output as it arrives instead of once it exits, with a spinner showing
+    /// how long it has been running and the CPU and memory it's using, and the most it used once
+    /// it exits. Useful for slow commands.
+    pub fn stream(mut self, stream: bool) -> Self {
+        self.stream = stream;
+        self
+    }
+
+    /// Show stderr mixed into the output, line by line in the order it was printed, instead of in
+    /// its own section after the output. The lines are kept in
+    /// [`ExecutionResult::interleaved`].
+    pub fn interleave(mut self, interleave: bool) -> Self {
+        self.interleave = interleave;
+        self
+    }
+
+    /// Forward keystrokes to the command's stdin while it runs, for commands that occasionally
+    /// prompt (e.g. `y/n`). Implies [`Watcher::stream`], so prompts show up as they're printed.
+    ///
+    /// Only the quit and scroll keys are kept by the watch while the command is running. Typed
+    /// characters aren't echoed unless the command echoes them itself.
+    pub fn interactive(mut self, interactive: bool) -> Self {
+        self.interactive = interactive;
+        self
+    }
+
+    /// Wrap long lines onto the next row (the default), or clip them at the terminal width so
+    /// tabular output like `ps aux` stays readable. Toggled with `w`; clipped lines scroll
+    /// sideways with the left and right arrow keys.
+    pub fn wrap(mut self, wrap: bool) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    /// Number the lines of output in a gutter on the left. With [`Watcher::differences`], the
+    /// gutter also marks lines that were added (`+`), changed (`~`), or followed by removed lines
+    /// (`-`).
+    pub fn line_numbers(mut self, line_numbers: bool) -> Self {
+        self.line_numbers = line_numbers;
+        self
+    }
+
+    /// Capture the mouse: the wheel scrolls the output, clicking the header pauses or resumes,
+    /// and clicking a [`Watcher::pane`] focuses it. Text can't be selected with the mouse
+    /// meanwhile, unless the terminal has a modifier for that (often Shift).
+    pub fn mouse(mut self, mouse: bool) -> Self {
+        self.mouse = mouse;
+        self
+    }
+
+    /// Show a dashboard above the output with how many runs succeeded and failed, when the last
+    /// failure was, and a sparkline of how long the recent runs took.
+    pub fn stats(mut self, stats: bool) -> Self {
+        self.stats = stats;
+        self
+    }
+
+    /// The keys the TUI responds to, see [`Keymap`].
+    pub fn keymap(mut self, keymap: Keymap) -> Self {
+        self.keymap = keymap;
+        self
+    }
+
+    /// The styles of the header, section titles, diff highlights, and footer, see [`Theme`].
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Registers a hook invoked with the result of every execution.
+    /// Returning [`ControlFlow::Break`] from it stops watching. Hooks are `Send`, so that the
+    /// watcher can be moved to another thread or task.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::ops::ControlFlow;
+    /// use watch_rs::Watcher;
+    ///
+    /// Watcher::new("uptime")
+    ///     .on_output(|result| {
+    ///         eprintln!("took {:?}", result.duration);
+    ///         ControlFlow::Continue(())
+    ///     })
+    ///     .on_error(|_| ControlFlow::Break(()))
+    ///     .run()
+    ///     .unwrap();
+    /// ```
+    pub fn on_output(
+        mut self,
+        hook: impl FnMut(&ExecutionResult) -> ControlFlow<()> + Send + 'static,
+    ) -> Self {
+        self.hooks.on_output.push(Box::new(hook));
+        self
+    }
+
+    /// Registers a hook invoked whenever the command exits with a non-zero status or times out.
+    pub fn on_error(
+        mut self,
+        hook: impl FnMut(&ExecutionResult) -> ControlFlow<()> + Send + 'static,
+    ) -> Self {
+        self.hooks.on_error.push(Box::new(hook));
+        self
+    }
+
+    /// Registers a hook invoked whenever the output differs from the previous execution.
+    pub fn on_change(
+        mut self,
+        hook: impl FnMut(&ExecutionResult) -> ControlFlow<()> + Send + 'static,
+    ) -> Self {
+        self.hooks.on_change.push(Box::new(hook));
+        self
+    }
+
+    /// Returns a channel that gets the lines of stdout that changed whenever the output changes,
+    /// as [`ChangeEvent`]s in the order of the lines, turning the polling into events for other
+    /// programs to act on. Can be called more than once, with each channel getting every change.
+    ///
+    /// With a `debounce`, a new output is only sent once later runs have kept it for that long,
+    /// compared to the output last sent, so output that changes and changes back within it sends
+    /// nothing. An output still waiting out the debounce when the watch ends is sent then, once the
+    /// watch is dropped, since it's the one the watch ended on.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use watch_rs::{ChangeEvent, RunOutput, Watcher};
+    ///
+    /// let mut outputs = ["web1 up\nweb2 up", "web1 up\nweb2 down\nweb3 up"].into_iter();
+    /// let mut watcher = Watcher::new("status")
+    ///     .interval(Duration::from_millis(100))
+    ///     .count(Some(2))
+    ///     .runner(move |_: &_| Ok(RunOutput::success(outputs.next().unwrap())));
+    /// let events = watcher.subscribe(None);
+    /// for result in watcher.watch_iter() {
+    ///     result.unwrap();
+    /// }
+    ///
+    /// let events: Vec<ChangeEvent> = events.try_iter().collect();
+    /// assert_eq!(
+    ///     events,
+    ///     [
+    ///         ChangeEvent::Modified {
+    ///             line: 2,
+    ///             old: "web2 up".to_string(),
+    ///             new: "web2 down".to_string(),
+    ///         },
+    ///         ChangeEvent::Added {
+    ///             line: 3,
+    ///             text: "web3 up".to_string(),
+    ///         },
+    ///     ]
+    /// );
+    /// ```
+    pub fn subscribe(&mut self, debounce: Option<Duration>) -> Receiver<ChangeEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.hooks
+            .on_output
+            .push(events::change_events(sender, debounce));
+        receiver
+    }
+
+    /// Shows a desktop notification when the output changes, the command fails, or both, so a watch
+    /// in a background terminal can still get attention.
+    pub fn notify(mut self, notify: Option<Notify>) -> Self {
+        self.notify = notify;
+        self
+    }
+
+    /// Runs `command` through the [`Watcher::shell`] whenever the output changes, without waiting
+    /// for it. The previous and new stdout and the exit code are passed in the `WATCH_OLD_OUTPUT`,
+    /// `WATCH_NEW_OUTPUT`, and `WATCH_EXIT_CODE` environment variables.
+    pub fn on_change_command(mut self, command: Option<String>) -> Self {
+        self.on_change_command = command;
+        self
+    }
+
+    /// The command the output on screen is piped into with '|', run through the
+    /// [`Watcher::shell`] with the terminal to itself. Defaults to `$PAGER`, or `less` (`more` on
+    /// Windows). The watch waits until it exits.
+    pub fn pager(mut self, pager: Option<String>) -> Self {
+        self.pager = pager;
+        self
+    }
+
+    /// Fill in placeholders in the command and its arguments before each execution: `{i}` with
+    /// its number, counting from 1, `{ts}` with the time in seconds since the Unix epoch, and
+    /// `{prev_exit}` with the previous execution's exit code, or nothing before the first one.
+    /// Anything else in braces is left alone.
+    pub fn template(mut self, template: bool) -> Self {
+        self.template = template;
+        self
+    }
+
+    /// Appends every execution's timestamp, exit code, duration, and output to the file at `path`.
+    pub fn log_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.log_file = Some(path.into());
+        self
+    }
+
+    /// Rotates the log file to `<path>.1` once it grows past `max_size` bytes.
+    pub fn log_max_size(mut self, max_size: Option<u64>) -> Self {
+        self.log_max_size = max_size;
+        self
+    }
+
+    /// Serves metrics about the executions at `/metrics` on `port`, on every interface, in
+    /// Prometheus' text format: how many runs there were and how many failed or changed the
+    /// output, the last run's exit code and start time, and a histogram of their durations.
+    ///
+    /// Only available with the `metrics` feature.
+    #[cfg(feature = "metrics")]
+    pub fn metrics_port(mut self, port: Option<u16>) -> Self {
+        self.metrics_port = port;
+        self
+    }
+
+    /// Writes every run in the [`Watcher::history`] to the directory at `dir` when the watch ends,
+    /// each to a file named after when it started, along with an `index.json` listing them. In the
+    /// TUI they're also written there when 'x' is pressed, which without this goes to a new
+    /// directory in the working directory instead.
+    pub fn export(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.export = Some(dir.into());
+        self
+    }
+
+    /// Runs the command as `user`, in their primary group unless [`Watcher::group`] is given, e.g.
+    /// so a watch started as root doesn't run it with root's privileges. Switching to another user
+    /// takes root, and the watch fails to start without it.
+    ///
+    /// Only available on Unix.
+    #[cfg(unix)]
+    pub fn user(mut self, user: Option<User>) -> Self {
+        self.user = user;
+        self
+    }
+
+    /// Runs the command in `group`, which like [`Watcher::user`] takes root.
+    ///
+    /// Only available on Unix.
+    #[cfg(unix)]
+    pub fn group(mut self, group: Option<Group>) -> Self {
+        self.group = group;
+        self
+    }
+
+    /// Records the session to the file at `path`, in asciinema's v2 format, so it can be replayed
+    /// later with `asciinema play` or `watchr --replay`. Every frame drawn is kept with its timing.
+    pub fn record(mut self, path: impl Into<PathBuf>) -> Self {
+        self.record = Some(path.into());
+        self
+    }
+
+    /// Watches `pane` alongside the watcher's own command, each in its own part of the screen.
+    ///
+    /// In pane mode every command runs on its own interval, Tab moves the focus between panes, and
+    /// scrolling and '+'/'-' apply to the focused one. History, diffs, streaming, and exit
+    /// conditions other than [`Watcher::run_for`] only apply to single-command watches.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use watch_rs::{Layout, Pane, Watcher};
+    ///
+    /// Watcher::new("df -h")
+    ///     .pane(Pane::new("free -m").interval(Duration::from_secs(1)))
+    ///     .layout(Layout::Vertical)
+    ///     .run()
+    ///     .unwrap();
+    /// ```
+    pub fn pane(mut self, pane: Pane) -> Self {
+        self.panes.push(pane);
+        self
+    }
+
+    /// Runs `command` through the [`Watcher::shell`] after the watched command on every
+    /// execution, and after any earlier ones. Each command's output is shown under a heading
+    /// with the command, one after the other, and the execution fails if any of them does.
+    /// [`Watcher::timeout`] applies to each command separately.
+    pub fn and(mut self, command: impl Into<String>) -> Self {
+        self.and.push(command.into());
+        self
+    }
+
+    /// Runs the command once for each of `items` on every execution, all at the same time, with
+    /// `{}` in the command and its arguments replaced by the item. Each one's output is shown
+    /// under a heading with its item and how it ended, in the order of `items`, and the execution
+    /// fails if any of them does. [`Watcher::timeout`] applies to each one separately, and the
+    /// [`Watcher::and`] commands run once all of them have finished.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use watch_rs::Watcher;
+    ///
+    /// let plan = Watcher::new("ping")
+    ///     .arg("{}")
+    ///     .exec(true)
+    ///     .foreach(["web1", "web2"])
+    ///     .plan();
+    /// assert_eq!(plan.commands[1].args, ["web2"]);
+    /// ```
+    pub fn foreach(mut self, items: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.foreach.extend(items.into_iter().map(Into::into));
+        self
+    }
+
+    /// Sets how panes are arranged when there are several. Defaults to [`Layout::Horizontal`].
+    pub fn layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Sets how many past executions are kept for browsing with '[' and ']'. Defaults to 50.
+    pub fn history(mut self, history: usize) -> Self {
+        self.history = history;
+        self
+    }
+
+    /// Draws the TUI on a `width` by `height` screen shown by `out` instead of the terminal, e.g.
+    /// a PTY or a socket. Keys are still read from the terminal, and the last output is still
+    /// printed to stdout on exit.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::TcpListener;
+    /// use watch_rs::Watcher;
+    ///
+    /// let (socket, _) = TcpListener::bind("127.0.0.1:7878")?.accept()?;
+    /// Watcher::new("uptime").draw_to(socket, 80, 24).run()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn draw_to(mut self, out: impl Write + Send + 'static, width: u16, height: u16) -> Self {
+        self.draw_to = Some(Renderer::new(Box::new(out), width, height));
+        self
+    }
+
+    /// Starts the history with `runs`, oldest first, e.g. ones kept from an earlier watch of the
+    /// same command. The TUI shows the last of them until the command first finishes, and the
+    /// first run is compared against it for change detection and [`Watcher::differences`].
+    pub fn preload(mut self, runs: impl IntoIterator<Item = ExecutionResult>) -> Self {
+        self.preloaded.extend(runs);
+        self
+    }
+
+    /// Sets how [`Watcher::run`] presents the output. Defaults to the interactive TUI; with
+    /// [`OutputMode::Json`] it instead writes one JSON object per execution to stdout.
+    pub fn output(mut self, output: OutputMode) -> Self {
+        self.output = output;
+        self
+    }
+
+    /// Runs the command on the interval without touching the terminal, yielding each execution's
+    /// result. Hooks still fire, and the iterator ends when a hook breaks or an exit condition
+    /// ([`Watcher::chgexit`], [`Watcher::errexit`], [`Watcher::until`], [`Watcher::while_matches`],
+    /// [`Watcher::count`]) is met, after yielding the result that met it, or once the next execution
+    /// would start after the [`Watcher::run_for`] budget runs out.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use watch_rs::Watcher;
+    ///
+    /// let runs: Vec<_> = Watcher::new("echo hello")
+    ///     .interval(Duration::from_millis(100))
+    ///     .watch_iter()
+    ///     .take(2)
+    ///     .collect::<Result<_, _>>()
+    ///     .unwrap();
+    /// assert_eq!(runs[1].stdout, "hello");
+    /// assert!(!runs[1].changed);
+    /// ```
+    pub fn watch_iter(self) -> WatchStream {
+        WatchStream::new(self)
+    }
+
+    /// Uses `crossterm` to watch the command and print its output until the user exits by pressing
+    /// 'q' or 'Ctrl+C', or an exit condition is met.
+    ///
+    /// While watching, space pauses/resumes execution, 'r' or Enter runs the command immediately,
+    /// '+'/'-' adjust the interval, '['/']' step through previous runs, 'D' toggles a side-by-side
+    /// diff against the previous run, '/' searches the output with 'n'/'N' jumping between matches,
+    /// and the arrow keys, PgUp/PgDn, and Home/End scroll the output.
+    ///
+    /// When stdout isn't a terminal, e.g. when it's piped to another program, there's no TUI and
+    /// each execution's output is appended to it instead, as with [`OutputMode::Plain`].
+    ///
+    /// Returns why the watch stopped along with the last execution, whose output is also printed
+    /// to the normal screen on exit. [`Outcome::exit_code`] maps it to a process exit code.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`WatchError`]: [`WatchError::Command`] when the command fails with
+    /// [`Watcher::errexit`], [`WatchError::Spawn`] if it can't be started, and
+    /// [`WatchError::Terminal`] or [`WatchError::Io`] if the terminal can't be used or the log file
+    /// can't be written.
+    pub fn run(self) -> std::result::Result<Outcome, WatchError> {
+        let outcome = self.run_until_stopped()?;
+        match (outcome.reason, outcome.last) {
+            (ExitReason::Failed, Some(last)) => Err(WatchError::Command {
+                status: last.status,
+                stderr: last.stderr,
+            }),
+            (reason, last) => Ok(Outcome { reason, last }),
+        }
+    }
+
+    /// What the TUI is drawn with: the terminal, unless it's drawn to something else.
+    pub(crate) fn renderer(&mut self) -> Renderer<Target> {
+        self.draw_to
+            .take()
+            .unwrap_or_else(|| Renderer::terminal(Box::new(screen())))
+    }
+
+    fn run_until_stopped(mut self) -> std::result::Result<Outcome, WatchError> {
+        #[cfg(unix)]
+        user::check(self.user.as_ref(), self.group.as_ref())?;
+        let _signals = signal::install();
+        match self.output {
+            OutputMode::Json => return self.run_json(),
+            OutputMode::Tui if !stdout().is_terminal() && self.draw_to.is_none() => {
+                return self.run_plain()
+            }
+            OutputMode::Plain => return self.run_plain(),
+            OutputMode::Tui => {}
+        }
+        let _recording = match &self.record {
+            Some(path) => Some(record::start(path, self.full_command().trim_end())?),
+            None => None,
+        };
+        if !self.panes.is_empty() {
+            return self.run_panes();
+        }
+        let full_watch_command = self.full_command();
+        self.install_builtin_hooks();
+        self.serve_metrics()?;
+        let mut command_runner = self.runner.take();
+        // There's no process to show as it runs or to type into
+        if command_runner.is_some() {
+            self.stream = false;
+            self.interactive = false;
+        }
+
+        let mut interval = self.interval;
+        let hostname = self.hostname();
+        let mut header_command = full_watch_command.trim_end().to_string();
+        for command in &self.and {
+            header_command.push_str("; ");
+            header_command.push_str(command);
+        }
+        if !self.foreach.is_empty() {
+            header_command.push_str(&format!(" (for each of {})", self.foreach.join(", ")));
+        }
+        if let Some(dir) = &self.current_dir {
+            header_command.push_str(&format!(" (in {})", dir.display()));
+        }
+
+        // Recent executions, used for change detection and browsing with '[' and ']'
+        let mut history = History::new(self.history);
+        // The latest run's lines, set aside while an older run is being viewed
+        let mut live_lines = Body::default();
+        let mut highlighter = self
+            .differences
+            .map(|mode| DiffHighlighter::new(mode, self.theme.diff_changed));
+        // Whether the last run repeated the one before it, so its lines have no diff highlights left
+        let mut settled = false;
+        let mut viewport = Viewport::new();
+        viewport.set_theme(self.theme.clone());
+        viewport.set_wrap(self.wrap);
+        viewport.set_line_numbers(self.line_numbers);
+        for result in std::mem::take(&mut self.preloaded) {
+            history.push(result);
+        }
+        if let Some(result) = history.latest() {
+            viewport.set_lines(self.result_lines(result, self.title, highlighter.as_mut()));
+        }
+        let mut reason = ExitReason::Quit;
+        let mut paused = false;
+        let mut log = self.open_log();
+        let mut runs: u64 = 0;
+        // Consecutive failed runs, which the interval backs off by
+        let mut failures: u32 = 0;
+        let mut succeeded = false;
+        let mut stats = DurationStats::default();
+        let mut health = self.stats.then(HealthStats::default);
+        let mut alerts = AlertState::default();
+        // When the command stopped printing anything, while its last output is kept on screen
+        let mut quiet_since: Option<DateTime<Local>> = None;
+        // When the countdown to the next run was last redrawn
+        let mut countdown_drawn = Instant::now();
+        let deadline = self.run_for.map(|run_for| Instant::now() + run_for);
+
+        // When the current execution was due, which the precise cadence is measured from
+        let mut scheduled = Instant::now();
+        let mut files = self.file_trigger()?;
+        // The prompt at the bottom of the screen, while it's open
+        let mut prompt: Option<(Purpose, LineEditor)> = None;
+        // Whether the help is shown over the output
+        let mut help = false;
+        let mut clipboard = Clipboard::new();
+        let mut toast: Option<(String, Instant)> = None;
+        let on_interval = self.runs_on_interval();
+        let quit_msg = quit_message(&self.keymap);
+
+        let terminal = TerminalGuard::enter().map_err(WatchError::Terminal)?;
+        if self.mouse {
+            terminal.capture_mouse().map_err(WatchError::Terminal)?;
+        }
+        let mut renderer = self.renderer();
+        let mut first_run_at = self.first_run_at();
+        'watchLoop: loop {
+            let mut frame = Frame {
+                command: &header_command,
+                interval,
+                status: None,
+                footer: &quit_msg,
+                theme: &self.theme,
+                paused,
+                title: self.title,
+                hostname: &hostname,
+                last_run: Local::now(),
+                running: None,
+                history: None,
+                timing: None,
+                on_interval,
+                watching_files: files.is_some(),
+                prompt: prompt.as_ref().map(prompt_state),
+                toast: toast.clone(),
+                usage: None,
+                quiet_since,
+                cron: self.cron.as_ref().map(Cron::to_string),
+                next_run: None,
+                health: health.clone(),
+                overlay: help.then(|| self.help(interval)),
+            };
+            show_selected(&history, &stats, &mut frame);
+            if let Some(first_run_at) = first_run_at.take() {
+                if let Some(stop) =
+                    self.wait_to_start(first_run_at, &mut renderer, &mut frame, &mut viewport)?
+                {
+                    reason = stop;
+                    break 'watchLoop;
+                }
+            }
+            let vars = Vars::new(runs + 1, history.latest());
+            let steps = self.build_steps(&full_watch_command, &vars);
+            let output = if self.stream || self.interactive {
+                let output = self.run_streaming(steps, &mut renderer, &mut frame, &mut viewport)?;
+                help = frame.overlay.is_some();
+                output
+            } else if let Some(command_runner) = command_runner.as_deref_mut() {
+                Some(runner::run(command_runner, steps)?)
+            } else {
+                self.wait_for_exit(steps)?
+            };
+            let Some(output) = output else {
+                reason = quit_reason();
+                break 'watchLoop;
+            };
+
+            let result = self.decode(output, history.latest());
+            if let Some(log) = log.as_mut() {
+                log.record(&result)?;
+            }
+
+            // Give hooks and exit conditions a chance to stop before rendering
+            runs += 1;
+            let stop = match self.hooks.fire(&result) {
+                ControlFlow::Break(()) => Some(ExitReason::Hook),
+                ControlFlow::Continue(()) => self.exit_reason(&result, runs),
+            };
+            let status = result.status;
+            failures = match status.success() {
+                true => 0,
+                false => failures.saturating_add(1),
+            };
+            succeeded |= status.success();
+            // A run that repeats the previous one draws the same lines, unless it clears the
+            // previous run's diff highlights. Streaming has replaced the lines meanwhile, though.
+            let unchanged = !result.changed
+                && history
+                    .latest()
+                    .is_some_and(|previous| previous.status == status);
+            let same_lines = unchanged
+                && (highlighter.is_none() || settled)
+                && !(self.stream || self.interactive);
+            settled = unchanged;
+            let printed = |result: &ExecutionResult| !self.is_quiet(result);
+            let quiet = self.is_quiet(&result)
+                && (quiet_since.is_some() || history.latest().is_some_and(printed));
+            quiet_since = quiet.then(|| quiet_since.unwrap_or(result.started_at.into()));
+            frame.quiet_since = quiet_since;
+            let lines = (!same_lines && !quiet)
+                .then(|| self.result_lines(&result, self.title, highlighter.as_mut()));
+            stats.record(result.duration);
+            if let Some(health) = health.as_mut() {
+                health.record(&result);
+                frame.health = Some(health.clone());
+            }
+            let alerted = !alerts.update(&self.alerts, &result.stdout).is_empty();
+            history.push(result);
+            if let Some(stop) = stop {
+                reason = stop;
+                break 'watchLoop;
+            }
+
+            // Keep showing an older run if that's what the user is looking at
+            match (lines, history.is_browsing()) {
+                (Some(lines), true) => live_lines = lines,
+                (Some(lines), false) => viewport.set_lines(lines),
+                (None, _) => {}
+            }
+            if !history.is_browsing() {
+                frame.status = Some(status);
+            }
+            if viewport.is_split() {
+                viewport.set_split(Some(self.split_rows(&history)));
+            }
+            show_selected(&history, &stats, &mut frame);
+            frame.running = None;
+            if self.errexits_on(status) {
+                frame.footer = ERREXIT_MSG;
+            }
+            draw(&mut renderer, &frame, &mut viewport)?;
+
+            if alerted || (self.beep && !status.success()) {
+                execute!(renderer.get_mut(), Print('\x07')).map_err(WatchError::Terminal)?;
+            }
+
+            // Freeze on failure until a key is pressed, then exit
+            if self.errexits_on(status) {
+                reason = ExitReason::Failed;
+                loop {
+                    if signal::cancelled() {
+                        reason = ExitReason::Interrupted;
+                        break;
+                    }
+                    if poll(CANCEL_POLL_INTERVAL).map_err(WatchError::Terminal)?
+                        && matches!(read().map_err(WatchError::Terminal)?, Event::Key(_))
+                    {
+                        break;
+                    }
+                }
+                break 'watchLoop;
+            }
+
+            // Handle input until the next execution is due. The interval can change meanwhile.
+            let (precise, finished_at) = (self.precise, Instant::now());
+            let (backoff, retry) = (self.backoff, self.wait_first_success);
+            let cron_due = self.next_cron_run();
+            let due = move |interval| {
+                if let Some(due) = cron_due {
+                    return due;
+                }
+                let interval = match (retry, succeeded) {
+                    (Some(retry), false) => retry,
+                    _ => backoff.map_or(interval, |max| backoff_interval(interval, failures, max)),
+                };
+                match precise {
+                    true => next_slot(scheduled, interval, finished_at),
+                    false => finished_at + interval,
+                }
+            };
+            while paused || !on_interval || Instant::now() < due(interval) {
+                let mut timeout = if paused || !on_interval {
+                    // Nothing to wake up for; just wait for input
+                    Duration::from_secs(60)
+                } else {
+                    due(interval).saturating_duration_since(Instant::now())
+                };
+                if let Some(files) = files.as_mut() {
+                    if files.fired() && !paused {
+                        scheduled = Instant::now();
+                        continue 'watchLoop;
+                    }
+                    timeout = timeout.min(FILE_POLL_INTERVAL);
+                }
+                if let Some(deadline) = deadline {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    if remaining.is_zero() {
+                        reason = ExitReason::Duration;
+                        break 'watchLoop;
+                    }
+                    timeout = timeout.min(remaining);
+                }
+                // Keep the countdown to the next run going, unless nothing's due
+                frame.next_run = (!paused && on_interval).then(|| (finished_at, due(interval)));
+                if frame.next_run.is_some() {
+                    let since_drawn = countdown_drawn.elapsed();
+                    if since_drawn >= COUNTDOWN_TICK {
+                        countdown_drawn = Instant::now();
+                        draw(&mut renderer, &frame, &mut viewport)?;
+                    }
+                    timeout = timeout.min(COUNTDOWN_TICK.saturating_sub(since_drawn));
+                }
+                if let Some((_, expires)) = &toast {
+                    let remaining = expires.saturating_duration_since(Instant::now());
+                    if remaining.is_zero() {
+                        toast = None;
+                        frame.toast = None;
+                        draw(&mut renderer, &frame, &mut viewport)?;
+                    } else {
+                        timeout = timeout.min(remaining);
+                    }
+                }
+                if signal::cancelled() {
+                    reason = ExitReason::Interrupted;
+                    break 'watchLoop;
+                }
+                if !poll(timeout.min(CANCEL_POLL_INTERVAL)).map_err(WatchError::Terminal)? {
+                    continue;
+                }
+                let event = read().map_err(WatchError::Terminal)?;
+                // Any key closes the help, without doing what it otherwise would
+                if help && is_key_press(&event) {
+                    help = false;
+                    frame.overlay = None;
+                    draw(&mut renderer, &frame, &mut viewport)?;
+                    continue;
+                }
+                // While a prompt is open, keys edit it instead of triggering actions
+                let edit = match (prompt.as_mut(), &event) {
+                    (Some((_, editor)), Event::Key(key)) => Some(editor.handle(*key)),
+                    (Some((_, editor)), Event::Paste(text)) => Some(editor.paste(text)),
+                    _ => None,
+                };
+                if let (Some((purpose, editor)), Some(edit)) = (prompt.as_mut(), edit) {
+                    match (*purpose, edit) {
+                        (Purpose::Search, Edit::Changed) => {
+                            viewport.set_search(Some(editor.text().to_string()))
+                        }
+                        (Purpose::Search, Edit::Submit) => {
+                            viewport.jump_to_match(true);
+                            prompt = None;
+                        }
+                        (Purpose::Search, Edit::Cancel) => {
+                            viewport.set_search(None);
+                            prompt = None;
+                        }
+                        (Purpose::SaveAs, Edit::Submit) => {
+                            let path = PathBuf::from(editor.text());
+                            let message = match history.selected().or(history.latest()) {
+                                Some(result) => {
+                                    match save_snapshot(
+                                        &path,
+                                        full_watch_command.trim_end(),
+                                        result,
+                                    ) {
+                                        Ok(()) => format!("Saved to {}", path.display()),
+                                        Err(err) => format!("Couldn't save: {}", err),
+                                    }
+                                }
+                                None => "Nothing to save yet".to_string(),
+                            };
+                            toast = Some((message, Instant::now() + TOAST_DURATION));
+                            frame.toast = toast.clone();
+                            prompt = None;
+                        }
+                        (Purpose::SaveAs, Edit::Cancel) => prompt = None,
+                        _ => {}
+                    }
+                    frame.prompt = prompt.as_ref().map(prompt_state);
+                    draw(&mut renderer, &frame, &mut viewport)?;
+                    continue;
+                }
+                let action = match self.keymap.action_for(event) {
+                    // Clicking the header pauses or resumes, like the key
+                    Some(Action::Click { row, .. }) if self.title && row == 0 => {
+                        Some(Action::TogglePause)
+                    }
+                    action => action,
+                };
+                match action {
+                    Some(Action::Quit) => break 'watchLoop,
+                    Some(Action::Help) => {
+                        help = true;
+                        frame.overlay = Some(self.help(interval));
+                        draw(&mut renderer, &frame, &mut viewport)?;
+                    }
+                    Some(Action::Search) => {
+                        prompt = Some((Purpose::Search, LineEditor::default()));
+                        frame.prompt = prompt.as_ref().map(prompt_state);
+                        draw(&mut renderer, &frame, &mut viewport)?;
+                    }
+                    Some(Action::Save) => {
+                        if let Some(result) = history.selected().or(history.latest()) {
+                            let name = snapshot_name(result);
+                            prompt = Some((Purpose::SaveAs, LineEditor::new(name)));
+                            frame.prompt = prompt.as_ref().map(prompt_state);
+                            draw(&mut renderer, &frame, &mut viewport)?;
+                        }
+                    }
+                    Some(Action::NextMatch) => {
+                        viewport.jump_to_match(true);
+                        draw(&mut renderer, &frame, &mut viewport)?;
+                    }
+                    Some(Action::PreviousMatch) => {
+                        viewport.jump_to_match(false);
+                        draw(&mut renderer, &frame, &mut viewport)?;
+                    }
+                    Some(Action::Refresh) => {
+                        paused = false;
+                        // Start a new cadence from this run
+                        scheduled = Instant::now();
+                        continue 'watchLoop;
+                    }
+                    Some(Action::TogglePause) => {
+                        paused = !paused;
+                        frame.paused = paused;
+                        draw(&mut renderer, &frame, &mut viewport)?;
+                    }
+                    Some(Action::IncreaseInterval) => {
+                        interval = step_interval(interval, true);
+                        frame.interval = interval;
+                        draw(&mut renderer, &frame, &mut viewport)?;
+                    }
+                    Some(Action::DecreaseInterval) => {
+                        interval = step_interval(interval, false);
+                        frame.interval = interval;
+                        draw(&mut renderer, &frame, &mut viewport)?;
+                    }
+                    Some(Action::Scroll(scroll)) => {
+                        viewport.scroll(scroll);
+                        draw(&mut renderer, &frame, &mut viewport)?;
+                    }
+                    Some(Action::HistoryBack) => {
+                        let was_live = !history.is_browsing();
+                        if history.back() {
+                            if was_live {
+                                live_lines = viewport.take_lines();
+                            }
+                            self.show_history(&history, &mut frame, &mut viewport, &mut live_lines);
+                            show_selected(&history, &stats, &mut frame);
+                            draw(&mut renderer, &frame, &mut viewport)?;
+                        }
+                    }
+                    Some(Action::HistoryForward) if history.forward() => {
+                        self.show_history(&history, &mut frame, &mut viewport, &mut live_lines);
+                        show_selected(&history, &stats, &mut frame);
+                        draw(&mut renderer, &frame, &mut viewport)?;
+                    }
+                    Some(Action::ToggleWrap) => {
+                        viewport.set_wrap(!viewport.is_wrapping());
+                        draw(&mut renderer, &frame, &mut viewport)?;
+                    }
+                    Some(Action::ToggleStderr) => {
+                        viewport.set_collapse_stderr(!viewport.is_collapsing_stderr());
+                        draw(&mut renderer, &frame, &mut viewport)?;
+                    }
+                    Some(Action::Pin) => {
+                        viewport.pin_top();
+                        draw(&mut renderer, &frame, &mut viewport)?;
+                    }
+                    Some(Action::UnpinAll) => {
+                        viewport.unpin_all();
+                        draw(&mut renderer, &frame, &mut viewport)?;
+                    }
+                    Some(Action::Sort(column)) => {
+                        viewport.sort_by(column);
+                        draw(&mut renderer, &frame, &mut viewport)?;
+                    }
+                    Some(Action::ToggleSplit) => {
+                        let split = !viewport.is_split();
+                        viewport.set_split(split.then(|| self.split_rows(&history)));
+                        draw(&mut renderer, &frame, &mut viewport)?;
+                    }
+                    Some(Action::Yank) => {
+                        if let Some(result) = history.selected().or(history.latest()) {
+                            let message = clipboard.copy(&result.stdout)?;
+                            toast = Some((message.to_string(), Instant::now() + TOAST_DURATION));
+                            frame.toast = toast.clone();
+                            draw(&mut renderer, &frame, &mut viewport)?;
+                        }
+                    }
+                    Some(Action::Export) => {
+                        let dir = self.export.clone().unwrap_or_else(export::default_dir);
+                        let message = match export(&dir, full_watch_command.trim_end(), &history) {
+                            Ok(runs) => format!("Exported {} runs to {}", runs, dir.display()),
+                            Err(err) => format!("Couldn't export: {}", err),
+                        };
+                        toast = Some((message, Instant::now() + TOAST_DURATION));
+                        frame.toast = toast.clone();
+                        draw(&mut renderer, &frame, &mut viewport)?;
+                    }
+                    Some(Action::Pager) => {
+                        if let Some(result) = history.selected().or(history.latest()) {
+                            let pager = self.pager.clone().unwrap_or_else(pager::default_pager);
+                            let paged = terminal
+                                .suspend(|| {
+                                    signal::passing_interrupts(|| {
+                                        pager::page(&self.shell, &pager, &result.stdout)
+                                    })
+                                })
+                                .map_err(WatchError::Terminal)?;
+                            if let Err(err) = paged {
+                                let message = format!("Couldn't run the pager: {}", err);
+                                toast = Some((message, Instant::now() + TOAST_DURATION));
+                                frame.toast = toast.clone();
+                            }
+                            draw(&mut renderer, &frame, &mut viewport)?;
+                        }
+                    }
+                    Some(Action::Redraw) => {
+                        show_selected(&history, &stats, &mut frame);
+                        draw(&mut renderer, &frame, &mut viewport)?;
+                    }
+                    _ => {}
+                }
+            }
+            scheduled = due(interval);
+        }
+        drop(terminal);
+
+        // Print the output one more time, so it remains visible after exit
+        if let Some(result) = history.latest() {
+            print_final_output(
+                &full_watch_command,
+                &result.stdout,
+                &result.stderr,
+                self.parse_options(),
+            )
+            .map_err(WatchError::Terminal)?;
+        }
+        if let Some(dir) = &self.export {
+            export(dir, full_watch_command.trim_end(), &history)?;
+        }
+        Ok(Outcome {
+            reason,
+            last: history.into_latest(),
+        })
+    }
+}
+
+impl Watcher {
+    /// The command line as shown in the header, e.g. "ls -l".
+    pub(crate) fn full_command(&self) -> String {
+        let mut full_watch_command: String = self.command.to_owned();
+        full_watch_command.push(' ');
+        full_watch_command.push_str(self.args.join(" ").as_str());
+        full_watch_command
+    }
+
+    /// Which of the conditions that end the watch successfully `result`, from execution number
+    /// `runs`, meets, if any.
+    pub(crate) fn exit_reason(&self, result: &ExecutionResult, runs: u64) -> Option<ExitReason> {
+        if self.chgexit && result.changed {
+            Some(ExitReason::Changed)
+        } else if self
+            .until
+            .as_ref()
+            .is_some_and(|until| until.is_match(&result.stdout))
+        {
+            Some(ExitReason::Until)
+        } else if self
+            .while_matches
+            .as_ref()
+            .is_some_and(|pattern| !pattern.is_match(&result.stdout))
+        {
+            Some(ExitReason::While)
+        } else if self.count.is_some_and(|count| runs >= count) {
+            Some(ExitReason::Count)
+        } else {
+            None
+        }
+    }
+
+    /// Registers the hooks behind [`Watcher::notify`], [`Watcher::alert`], and
+    /// [`Watcher::on_change_command`], once the command is final.
+    pub(crate) fn install_builtin_hooks(&mut self) {
+        if let Some(on_change_command) = self.on_change_command.take() {
+            let shell = self.shell.clone();
+            self.hooks
+                .on_output
+                .push(hooks::command_on_change(shell, on_change_command));
+        }
+
+        if !self.alerts.is_empty() {
+            let (command, rules) = (self.full_command(), self.alerts.clone());
+            let mut state = AlertState::default();
+            self.hooks.on_output.push(Box::new(move |result| {
+                for (rule, line) in state.update(&rules, &result.stdout) {
+                    notify::send_alert(&command, rule, line);
+                }
+                ControlFlow::Continue(())
+            }));
+        }
+
+        let Some(notify) = self.notify.take() else {
+            return;
+        };
+        let command = self.full_command();
+        if notify.on_change() {
+            let command = command.clone();
+            self.hooks.on_change.push(Box::new(move |result| {
+                notify::send(&command, result);
+                ControlFlow::Continue(())
+            }));
+        }
+        if notify.on_error() {
+            self.hooks.on_error.push(Box::new(move |result| {
+                notify::send(&command, result);
+                ControlFlow::Continue(())
+            }));
+        }
+    }
+
+    /// Starts watching [`Watcher::watch_path`]s for changes, if any were given.
+    pub(crate) fn file_trigger(&self) -> Result<Option<FileTrigger>> {
+        if self.watch_paths.is_empty() {
+            return Ok(None);
+        }
+        FileTrigger::new(&self.watch_paths, self.debounce).map(Some)
+    }
+
+    /// When the next run is due by [`Watcher::cron`], which takes the place of the interval, if
+    /// there's a schedule and it matches again.
+    pub(crate) fn next_cron_run(&self) -> Option<Instant> {
+        self.cron.as_ref().and_then(Cron::next_instant)
+    }
+
+    /// When the first run is due, or `None` if it's right away.
+    pub(crate) fn first_run_at(&self) -> Option<Instant> {
+        match self.first_run {
+            FirstRun::Immediately => None,
+            FirstRun::AfterInterval => self
+                .next_cron_run()
+                .or_else(|| Some(Instant::now() + self.interval)),
+            FirstRun::After(delay) => Some(Instant::now() + delay),
+        }
+    }
+
+    /// Whether the interval triggers executions, which can only be turned off when watching paths.
+    pub(crate) fn runs_on_interval(&self) -> bool {
+        self.on_interval || self.watch_paths.is_empty()
+    }
+
+    /// Starts the listener for [`Watcher::metrics_port`], if one was configured, which records
+    /// every execution from then on. It's only started once.
+    pub(crate) fn serve_metrics(&mut self) -> Result<()> {
+        #[cfg(feature = "metrics")]
+        if let Some(port) = self.metrics_port.take() {
+            self.hooks.on_output.push(crate::metrics::serve(port)?);
+        }
+        Ok(())
+    }
+
+    /// Creates the log sink for [`Watcher::log_file`], if one was configured.
+    pub(crate) fn open_log(&self) -> Option<LogSink> {
+        self.log_file
+            .as_ref()
+            .map(|path| LogSink::new(path.clone(), self.log_max_size))
+    }
+
+    /// Runs the command once, without touching the terminal.
+    pub(crate) fn execute(
+        &self,
+        full_watch_command: &str,
+        vars: &Vars,
+    ) -> std::result::Result<exec::Output, WatchError> {
+        exec::run(self.build_steps(full_watch_command, vars), self.timeout)
+    }
+
+    /// Decodes the captured output, comparing it against `previous` to detect changes.
+    pub(crate) fn decode(
+        &self,
+        output: exec::Output,
+        previous: Option<&ExecutionResult>,
+    ) -> ExecutionResult {
+        let output = self.classify(output);
+        let stdout = self.filter(&self.encoding.decode(&output.stdout));
+        let stdout = self.format_json(stdout.trim());
+        let stderr = self.encoding.decode(&output.stderr).trim().to_owned();
+        let changed = previous.is_some_and(|prev| prev.stdout != stdout || prev.stderr != stderr);
+        let interleaved = match self.interleave {
+            true => self.interleave_lines(&output),
+            false => Vec::new(),
+        };
+        ExecutionResult {
+            stdout,
+            stderr,
+            interleaved,
+            status: output.status,
+            started_at: output.started_at,
+            duration: output.duration,
+            changed,
+        }
+    }
+
+    /// Whether `result` printed nothing, and [`Watcher::quiet_empty`] leaves it off the screen.
+    pub(crate) fn is_quiet(&self, result: &ExecutionResult) -> bool {
+        self.quiet_empty && result.stdout.is_empty() && result.stderr.is_empty()
+    }
+
+    /// Whether JSON output is pretty-printed, with [`Watcher::json`] or [`Watcher::jq`].
+    fn formats_json(&self) -> bool {
+        self.json || self.jq.is_some()
+    }
+
+    /// Pretty-prints `stdout` through the [`Watcher::jq`] filter, if it's JSON that's meant to be.
+    fn format_json(&self, stdout: &str) -> String {
+        if !self.formats_json() {
+            return stdout.to_owned();
+        }
+        let Some(values) = jq::parse(stdout) else {
+            return stdout.to_owned();
+        };
+        let values = match &self.jq {
+            Some(filter) => {
+                let selected: std::result::Result<Vec<Vec<_>>, _> = values
+                    .into_iter()
+                    .map(|value| filter.apply(value))
+                    .collect();
+                match selected {
+                    Ok(selected) => selected.concat(),
+                    Err(err) => return format!("jq: error: {err} (in `{filter}`)"),
+                }
+            }
+            None => values,
+        };
+        jq::pretty(&values)
+    }
+
+    /// Keeps the lines of `stdout` selected by [`Watcher::grep`] and [`Watcher::grep_v`].
+    fn filter(&self, stdout: &str) -> String {
+        if self.grep.is_none() && self.grep_v.is_none() {
+            return stdout.to_owned();
+        }
+        stdout
+            .lines()
+            .filter(|line| self.keeps(line))
+            .map(|line| format!("{line}\n"))
+            .collect()
+    }
+
+    /// Whether a line of stdout is selected by [`Watcher::grep`] and [`Watcher::grep_v`]. Lines
+    /// are matched without their color codes.
+    fn keeps(&self, line: &str) -> bool {
+        if self.grep.is_none() && self.grep_v.is_none() {
+            return true;
+        }
+        let text = ansi::strip(line);
+        self.grep.as_ref().is_none_or(|grep| grep.is_match(&text))
+            && !self
+                .grep_v
+                .as_ref()
+                .is_some_and(|grep_v| grep_v.is_match(&text))
+    }
+
+    /// Splits the output back into lines in the order they were printed, going by the order its
+    /// chunks arrived in: a line counts as printed once its newline arrives. Lines of stdout are
+    /// filtered like [`ExecutionResult::stdout`], and blank lines at either end are dropped.
+    fn interleave_lines(&self, output: &exec::Output) -> Vec<(Pipe, String)> {
+        let stdout = self.encoding.decode(&output.stdout);
+        let stderr = self.encoding.decode(&output.stderr);
+        let mut stdout_lines = stdout.split('\n');
+        let mut stderr_lines = stderr.split('\n');
+
+        let mut order = Vec::new();
+        let (mut stdout_read, mut stderr_read) = (0, 0);
+        for &(pipe, len) in &output.chunks {
+            let (bytes, read) = match pipe {
+                Pipe::Stdout => (&output.stdout, &mut stdout_read),
+                Pipe::Stderr => (&output.stderr, &mut stderr_read),
+            };
+            let chunk = bytes.get(*read..*read + len).unwrap_or_default();
+            *read += len;
+            order.extend(chunk.iter().filter(|&&b| b == b'\n').map(|_| pipe));
+        }
+        // Whatever wasn't ended by a newline, or wasn't recorded, goes last
+        let mut lines: Vec<(Pipe, &str)> = Vec::new();
+        for pipe in order {
+            let line = match pipe {
+                Pipe::Stdout => stdout_lines.next(),
+                Pipe::Stderr => stderr_lines.next(),
+            };
+            lines.extend(line.map(|line| (pipe, line)));
+        }
+        lines.extend(stdout_lines.map(|line| (Pipe::Stdout, line)));
+        lines.extend(stderr_lines.map(|line| (Pipe::Stderr, line)));
+
+        let mut lines: Vec<(Pipe, String)> = lines
+            .into_iter()
+            .map(|(pipe, line)| (pipe, line.strip_suffix('\r').unwrap_or(line)))
+            .filter(|&(pipe, line)| pipe == Pipe::Stderr || self.keeps(line))
+            .map(|(pipe, line)| (pipe, line.to_owned()))
+            .collect();
+        let blank = |(_, line): &(Pipe, String)| line.trim().is_empty();
+        while lines.last().is_some_and(blank) {
+            lines.pop();
+        }
+        let leading = lines.iter().take_while(|line| blank(line)).count();
+        lines.drain(..leading);
+        lines
+    }
+
+    /// Runs the command to completion while watching for the user quitting, which kills it and
+    /// returns `None`. Other keys are ignored until it exits.
+    fn wait_for_exit(
+        &self,
+        steps: Vec<Step>,
+    ) -> std::result::Result<Option<exec::Output>, WatchError> {
+        let mut running = exec::Running::spawn(steps, self.timeout)?;
+        loop {
+            if let Some(status) = running.try_finish()? {
+                return Ok(Some(running.finish(status)));
+            }
+            if signal::cancelled() {
+                return Ok(None);
+            }
+            if poll(WAIT_POLL_INTERVAL).map_err(WatchError::Terminal)?
+                && self
+                    .keymap
+                    .action_for(read().map_err(WatchError::Terminal)?)
+                    == Some(Action::Quit)
+            {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// Counts down to the first run, due at `first_run_at`, handling input meanwhile. Returns why
+    /// the watch stopped if it's quit before then.
+    fn wait_to_start(
+        &self,
+        first_run_at: Instant,
+        renderer: &mut Renderer<Target>,
+        frame: &mut Frame,
+        viewport: &mut Viewport,
+    ) -> std::result::Result<Option<ExitReason>, WatchError> {
+        frame.next_run = Some((Instant::now(), first_run_at));
+        while Instant::now() < first_run_at {
+            draw(renderer, frame, viewport)?;
+            if signal::cancelled() {
+                return Ok(Some(ExitReason::Interrupted));
+            }
+            let remaining = first_run_at.saturating_duration_since(Instant::now());
+            let timeout = remaining.min(COUNTDOWN_TICK).min(CANCEL_POLL_INTERVAL);
+            if !poll(timeout).map_err(WatchError::Terminal)? {
+                continue;
+            }
+            match self
+                .keymap
+                .action_for(read().map_err(WatchError::Terminal)?)
+            {
+                Some(Action::Quit) => return Ok(Some(ExitReason::Quit)),
+                Some(Action::Refresh) => break,
+                _ => {}
+            }
+        }
+        frame.next_run = None;
+        Ok(None)
+    }
+
+    /// Runs the command while rendering its output as it arrives, with an elapsed-time spinner.
+    /// Input is still handled meanwhile, and in interactive mode forwarded to the command; returns
+    /// `None` if the user quit, killing the command.
+    fn run_streaming(
+        &self,
+        steps: Vec<Step>,
+        renderer: &mut Renderer<Target>,
+        frame: &mut Frame,
+        viewport: &mut Viewport,
+    ) -> std::result::Result<Option<exec::Output>, WatchError> {
+        let mut running = match self.interactive {
+            true => exec::Running::spawn_interactive(steps, self.timeout)?,
+            false => exec::Running::spawn(steps, self.timeout)?,
+        };
+        let mut usage = UsageSampler::new();
+        loop {
+            if let Some(status) = running.try_finish()? {
+                frame.usage = Some(format!("peak {}", usage.peak()));
+                return Ok(Some(running.finish(status)));
+            }
+            frame.usage = Some(usage.sample(&running.pids()).to_string());
+
+            // Leave an older run on screen if one is being viewed, or the last output until there's
+            // more with `quiet_empty`
+            let (std_output, std_error) = running.output_so_far();
+            let quiet = self.quiet_empty && std_output.is_empty() && std_error.is_empty();
+            if frame.history.is_none() && !quiet {
+                viewport.set_lines(self.body_lines(
+                    None,
+                    self.encoding.decode(&std_output).trim(),
+                    self.encoding.decode(&std_error).trim(),
+                    self.title,
+                    None,
+                ));
+            }
+            frame.running = Some(running.elapsed());
+            draw(renderer, frame, viewport)?;
+            if signal::cancelled() {
+                return Ok(None);
+            }
+
+            if poll(STREAM_REFRESH_INTERVAL).map_err(WatchError::Terminal)? {
+                let event = read().map_err(WatchError::Terminal)?;
+                if frame.overlay.is_some() && is_key_press(&event) {
+                    frame.overlay = None;
+                    continue;
+                }
+                match self.keymap.action_for(event.clone()) {
+                    Some(Action::Quit) => return Ok(None),
+                    Some(Action::Help) if !self.interactive => {
+                        frame.overlay = Some(self.help(frame.interval))
+                    }
+                    Some(Action::Scroll(scroll)) => viewport.scroll(scroll),
+                    Some(Action::ToggleWrap) if !self.interactive => {
+                        viewport.set_wrap(!viewport.is_wrapping())
+                    }
+                    Some(Action::ToggleStderr) if !self.interactive => {
+                        viewport.set_collapse_stderr(!viewport.is_collapsing_stderr())
+                    }
+                    // Anything else is meant for the command, in interactive mode
+                    _ if self.interactive => {
+                        if let Some(bytes) = input::key_bytes(&event) {
+                            running.write_stdin(&bytes)?;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// What the help shows: what each key does, and the options the watch runs with, with the
+    /// `interval` it's at now.
+    fn help(&self, interval: Duration) -> Overlay {
+        // There's a single pane to focus
+        let keys = self
+            .keymap
+            .help(&[Action::FocusNext, Action::FocusPrevious]);
+        let mut options = vec![
+            (
+                "interval",
+                match (&self.cron, self.runs_on_interval()) {
+                    (Some(cron), _) => format!("cron {}", cron),
+                    (None, true) => format!("{}s", interval.as_secs_f64()),
+                    (None, false) => "none, runs on change".to_string(),
+                },
+            ),
+            (
+                "shell",
+                match self.exec {
+                    true => "none, runs the command itself".to_string(),
+                    false => self.shell.to_string(),
+                },
+            ),
+        ];
+        if !self.watch_paths.is_empty() {
+            let paths: Vec<String> = self
+                .watch_paths
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect();
+            options.push(("watching", paths.join(", ")));
+        }
+        if let Some(timeout) = self.timeout {
+            options.push(("timeout", format!("{}s", timeout.as_secs_f64())));
+        }
+        // A line for each way the watch ends by itself
+        let mut exits = self.exit_conditions().into_iter();
+        options.push((
+            "exits",
+            exits.next().unwrap_or("only when quit".to_string()),
+        ));
+        options.extend(exits.map(|exit| ("", format!("or {}", exit))));
+
+        let width = keys
+            .iter()
+            .map(|(keys, _)| text_width(keys))
+            .chain(options.iter().map(|(name, _)| name.len()))
+            .max()
+            .unwrap_or(0);
+        let mut lines = vec!["Keys".to_string()];
+        lines.extend(
+            keys.iter()
+                .map(|(keys, description)| format!("{}  {}", fit(keys, width), description)),
+        );
+        lines.push(String::new());
+        lines.push("Options".to_string());
+        lines.extend(
+            options
+                .iter()
+                .map(|(name, value)| format!("{}  {}", fit(name, width), value)),
+        );
+        Overlay {
+            title: "Help".to_string(),
+            lines,
+        }
+    }
+
+    /// Shows the run selected in `history`, after stepping through it. Returning to the latest run
+    /// restores its lines, diff highlighting included, from `live_lines`.
+    fn show_history(
+        &self,
+        history: &History,
+        frame: &mut Frame,
+        viewport: &mut Viewport,
+        live_lines: &mut Body,
+    ) {
+        match history.selected() {
+            Some(result) => viewport.set_lines(self.result_lines(result, self.title, None)),
+            None => viewport.set_lines(std::mem::take(live_lines)),
+        }
+        if viewport.is_split() {
+            viewport.set_split(Some(self.split_rows(history)));
+        }
+        frame.status = history.selected().or(history.latest()).map(|r| r.status);
+    }
+
+    /// Lines up the stdout of the run on screen against the run before it, under headings with the
+    /// time of each.
+    fn split_rows(&self, history: &History) -> Vec<SplitRow> {
+        let Some((previous, shown)) = history.shown_with_previous() else {
+            return Vec::new();
+        };
+        let heading = |label: &str, result: Option<&ExecutionResult>| -> Line {
+            let heading = match result {
+                Some(result) => {
+                    let started_at: DateTime<Local> = result.started_at.into();
+                    format!("{} ({})", label, started_at.format("%H:%M:%S"))
+                }
+                None => format!("{} (none yet)", label),
+            };
+            vec![Span::new(self.theme.title, heading)]
+        };
+
+        let mut rows = vec![SplitRow::heading(
+            heading("Previous", previous),
+            heading("Current", Some(shown)),
+        )];
+        rows.extend(diff::side_by_side(
+            previous
+                .map(|result| ansi::parse(&result.stdout, self.parse_options()))
+                .unwrap_or_default(),
+            ansi::parse(&shown.stdout, self.parse_options()),
+        ));
+        rows
+    }
+
+    /// Builds the processes to spawn for one execution: the command, followed by each
+    /// [`Watcher::and`] command.
+    pub(crate) fn build_steps(&self, full_watch_command: &str, vars: &Vars) -> Vec<Step> {
+        let filter = || {
+            self.pipe.as_ref().map(|pipe| {
+                let command_line = self.expand(pipe, vars).into_owned();
+                let mut command = self.shell.command(&command_line);
+                self.apply_environment(&mut command);
+                self.switch_user(&mut command);
+                Filter {
+                    command,
+                    command_line,
+                }
+            })
+        };
+        let cap = self.max_output.map(|bytes| OutputCap {
+            bytes: usize::try_from(bytes).unwrap_or(usize::MAX),
+            truncation: self.truncation,
+        });
+        let mut steps = match self.foreach.is_empty() {
+            true => {
+                let mut command = self.build_command(full_watch_command, vars, None);
+                self.switch_user(&mut command);
+                let heading =
+                    (!self.and.is_empty()).then(|| full_watch_command.trim_end().to_string());
+                vec![Step::new(command, heading).filtered(filter()).capped(cap)]
+            }
+            false => self
+                .foreach
+                .iter()
+                .map(|item| {
+                    let mut command = self.build_command(full_watch_command, vars, Some(item));
+                    self.switch_user(&mut command);
+                    Step::new(command, Some(item.clone()))
+                        .filtered(filter())
+                        .capped(cap)
+                        .parallel()
+                })
+                .collect(),
+        };
+        for command_line in &self.and {
+            let mut command = self.shell_command(&self.expand(command_line, vars));
+            self.switch_user(&mut command);
+            steps.push(Step::new(command, Some(command_line.clone())).capped(cap));
+        }
+        steps
+    }
+
+    /// Has `command` run as the [`Watcher::user`] and [`Watcher::group`], if any.
+    pub(crate) fn switch_user(&self, command: &mut Command) {
+        #[cfg(unix)]
+        user::apply(command, self.user.as_ref(), self.group.as_ref());
+        #[cfg(not(unix))]
+        let _ = command;
+    }
+
+    /// Builds the process to spawn: the command itself in exec mode, otherwise a shell running it.
+    /// With a [`Watcher::foreach`] `item`, it goes wherever the command has `{}`.
+    fn build_command(&self, full_watch_command: &str, vars: &Vars, item: Option<&str>) -> Command {
+        let expand = |text| match (self.expand(text, vars), item) {
+            (text, Some(item)) if text.contains("{}") => Cow::Owned(text.replace("{}", item)),
+            (text, _) => text,
+        };
+        if !self.exec {
+            return self.shell_command(&expand(full_watch_command));
+        }
+        let program = expand(&self.command);
+        let args = self.args.iter().map(|arg| expand(arg));
+        if let Some(remote) = &self.remote {
+            let words: Vec<String> = std::iter::once(program)
+                .chain(args)
+                .map(Cow::into_owned)
+                .collect();
+            return self.remote_exec(remote, &words);
+        }
+        let mut command = Command::new(&*program);
+        command.args(args.map(Cow::into_owned));
+        self.apply_environment(&mut command);
+        command
+    }
+
+    /// Builds the process running `command_line` through the shell, on the remote target if
+    /// there is one.
+    pub(crate) fn shell_command(&self, command_line: &str) -> Command {
+        if let Some(remote) = &self.remote {
+            return self.remote_command(remote, command_line);
+        }
+        let mut command = self.shell.command(command_line);
+        self.apply_environment(&mut command);
+        command
+    }
+
+    /// The host shown in the header: the remote one, or this machine.
+    pub(crate) fn hostname(&self) -> String {
+        match &self.remote {
+            Some(remote) => remote.host(),
+            None => gethostname().to_string_lossy().into_owned(),
+        }
+    }
+
+    /// Whether `status` ends the watch with [`Watcher::errexit`]: the command failed, rather than
+    /// not being reached, which is retried.
+    pub(crate) fn errexits_on(&self, status: Status) -> bool {
+        self.errexit && !status.success() && !matches!(status, Status::Unreachable(_))
+    }
+
+    /// Fills in the placeholders in `text` with [`Watcher::template`].
+    fn expand<'a>(&self, text: &'a str, vars: &Vars) -> Cow<'a, str> {
+        match self.template {
+            true => template::expand(text, vars),
+            false => Cow::Borrowed(text),
+        }
+    }
+
+    /// Applies [`Watcher::env`] and [`Watcher::current_dir`] to a process about to be spawned.
+    pub(crate) fn apply_environment(&self, command: &mut Command) {
+        command.envs(self.env.iter().map(|(key, value)| (key, value)));
+        if let Some(dir) = &self.current_dir {
+            command.current_dir(dir);
+        }
+    }
+
+    /// How the output is parsed into styled lines.
+    pub(crate) fn parse_options(&self) -> ansi::Options {
+        ansi::Options {
+            color: self.color,
+            show_controls: self.show_control_chars,
+            tab_width: self.tab_width,
+        }
+    }
+
+    /// Builds the lines shown in the body of the screen: the command's stdout, followed by its stderr if any.
+    pub(crate) fn body_lines(
+        &self,
+        status: Option<Status>,
+        std_output: &str,
+        std_error: &str,
+        title: bool,
+        highlighter: Option<&mut DiffHighlighter>,
+    ) -> Body {
+        let mut lines = Body::default();
+        if let Some(Status::TimedOut(timeout)) = status {
+            let banner = format!(
+                " Command timed out after {}s and was killed ",
+                timeout.as_secs_f64()
+            );
+            lines.push(vec![StyledContent::new(self.theme.status, banner)
+                .reverse()
+                .into()]);
+        }
+        if title {
+            lines.push(vec![Span::new(self.theme.title, "Output:")]);
+        }
+        let table = self
+            .table
+            .map(|format| {
+                Table::parse(std_output, format, |cell| {
+                    let lines = ansi::parse(cell, self.parse_options());
+                    let text: Vec<String> = lines.iter().map(line_text).collect();
+                    text.join(" ")
+                })
+            })
+            .filter(|table| table.columns() > 0);
+        let output_lines = match &table {
+            Some(table) => table.render(usize::MAX, None, &self.theme),
+            None => {
+                let parsed =
+                    match self.formats_json() && self.color && jq::parse(std_output).is_some() {
+                        true => jq::highlight(std_output, &self.theme),
+                        false => ansi::parse(std_output, self.parse_options()),
+                    };
+                alert::apply(
+                    highlight::apply(parsed, &self.highlights),
+                    &self.alerts,
+                    self.theme.alert,
+                )
+            }
+        };
+        lines.table = table.map(|table| (lines.lines.len(), table));
+        // Tables are drawn again to fit, which diff highlights wouldn't survive
+        let highlighter = highlighter.filter(|_| lines.table.is_none());
+        let (output_lines, markers) = match highlighter {
+            Some(highlighter) => highlighter.highlight(output_lines),
+            None => {
+                let markers = vec![None; output_lines.len()];
+                (output_lines, markers)
+            }
+        };
+        for (i, (line, marker)) in output_lines.into_iter().zip(markers).enumerate() {
+            lines.lines.push(line);
+            lines.gutters.push(Some(Gutter {
+                number: i + 1,
+                marker,
+            }));
+        }
+        if !std_error.is_empty() {
+            lines.stderr = Some(lines.lines.len());
+            lines.push(vec![Span::new(self.theme.title, "StdErr:")]);
+            for line in highlight::apply(
+                ansi::parse(std_error, self.parse_options()),
+                &self.highlights,
+            ) {
+                lines.push(line);
+            }
+        }
+        lines
+    }
+
+    /// The lines to show for `result`, like [`Watcher::body_lines`], with its stderr mixed into
+    /// the output in its own style when it was interleaved.
+    pub(crate) fn result_lines(
+        &self,
+        result: &ExecutionResult,
+        title: bool,
+        highlighter: Option<&mut DiffHighlighter>,
+    ) -> Body {
+        let status = Some(result.status);
+        if result.interleaved.is_empty() {
+            return self.body_lines(status, &result.stdout, &result.stderr, title, highlighter);
+        }
+        let text: Vec<&str> = result
+            .interleaved
+            .iter()
+            .map(|(_, line)| line.as_str())
+            .collect();
+        let mut body = self.body_lines(status, &text.join("\n"), "", title, highlighter);
+        // The output's lines are the ones with a gutter
+        let output_lines = body
+            .lines
+            .iter_mut()
+            .zip(&body.gutters)
+            .filter(|(_, gutter)| gutter.is_some())
+            .map(|(line, _)| line);
+        for (line, (pipe, _)) in output_lines.zip(&result.interleaved) {
+            if *pipe == Pipe::Stderr {
+                for span in line {
+                    span.style = overlay(span.style, self.theme.stderr);
+                }
+            }
+        }
+        body
+    }
+}
+
+/// How often input is checked for while the command runs, outside streaming mode.
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How often the output of a running command is re-rendered in streaming mode.
+const STREAM_REFRESH_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How often the countdown to the next run is redrawn while waiting for it.
+const COUNTDOWN_TICK: Duration = Duration::from_millis(250);
+
+/// How long a message like "Copied to the clipboard" stays in the status bar.
+const TOAST_DURATION: Duration = Duration::from_secs(2);
+
+const ERREXIT_MSG: &str = "Command exited with a non-zero status, press a key to exit";
+
+/// Nudges the interval up or down: by 1s normally, or by 0.1s once it's at or below one second.
+pub(crate) fn step_interval(interval: Duration, up: bool) -> Duration {
+    let one_second = Duration::from_secs(1);
+    let step = if interval > one_second || (up && interval == one_second) {
+        one_second
+    } else {
+        Duration::from_millis(100)
+    };
+    if up {
+        interval + step
+    } else {
+        interval
+            .saturating_sub(step)
+            .max(Duration::from_secs_f64(MIN_INTERVAL))
+    }
+}
+
+/// The interval after `failures` consecutive failed runs: doubled for each one, up to `max`, but
+/// never below the interval itself.
+pub(crate) fn backoff_interval(interval: Duration, failures: u32, max: Duration) -> Duration {
+    let factor = 2u32.saturating_pow(failures);
+    interval.saturating_mul(factor).min(max).max(interval)
+}
+
+/// The first slot of the cadence started at `scheduled` that is still ahead at `now`, skipping any
+/// missed while the command was running.
+pub(crate) fn next_slot(scheduled: Instant, interval: Duration, now: Instant) -> Instant {
+    let next = scheduled + interval;
+    if next > now {
+        return next;
+    }
+    let missed = (now - next).as_nanos() / interval.as_nanos().max(1) + 1;
+    next + interval.saturating_mul(u32::try_from(missed).unwrap_or(u32::MAX))
+}
+
+/// Describes how the command exited, e.g. "exit code 1".
+pub(crate) fn describe_status(status: Status) -> String {
+    match status {
+        Status::Exited(status) => match status.code() {
+            Some(code) => format!("exit code {}", code),
+            None => "termination by signal".to_string(),
+        },
+        Status::TimedOut(timeout) => format!("timeout after {}s", timeout.as_secs_f64()),
+        Status::Unreachable(status) => match status.code() {
+            Some(code) => format!("unreachable, exit code {}", code),
+            None => "unreachable".to_string(),
+        },
+    }
+}
+
+/// The footer telling the user how to exit, e.g. "Press 'q' or 'ctrl+c' to exit".
+fn quit_message(keymap: &Keymap) -> String {
+    let keys: Vec<String> = keymap
+        .keys_for(Action::Quit)
+        .iter()
+        .map(|key| format!("'{}'", key))
+        .collect();
+    match keys.is_empty() {
+        true => String::new(),
+        false => format!("Press {} to exit", keys.join(" or ")),
+    }
+}
+
+/// Whether `event` is a key being pressed, rather than released or repeated on Windows.
+fn is_key_press(event: &Event) -> bool {
+    matches!(event, Event::Key(key) if key.kind == KeyEventKind::Press)
+}
+
+/// Why the watch stopped when the command was cut short: by the user, or by a signal.
+pub(crate) fn quit_reason() -> ExitReason {
+    match signal::cancelled() {
+        true => ExitReason::Interrupted,
+        false => ExitReason::Quit,
+    }
+}
+
+/// What [`Frame::prompt`] shows for an open prompt.
+fn prompt_state((purpose, editor): &(Purpose, LineEditor)) -> (&'static str, String, usize) {
+    (purpose.label(), editor.text().to_string(), editor.cursor())
+}
+
+/// Points the header at the run being viewed, when browsing the history.
+fn show_selected(history: &History, stats: &DurationStats, frame: &mut Frame) {
+    frame.history = history.label();
+    frame.timing = history
+        .selected()
+        .or(history.latest())
+        .map(|result| stats.describe(result.duration));
+    if let Some(result) = history.selected() {
+        frame.status = Some(result.status);
+        frame.last_run = result.started_at.into();
+    }
+}
+
+/// Draws the header, the visible part of the output, and the footer over the whole screen.
+fn draw(
+    renderer: &mut Renderer<Target>,
+    frame: &Frame,
+    viewport: &mut Viewport,
+) -> std::result::Result<(), WatchError> {
+    renderer
+        .area()
+        .and_then(|area| renderer.draw_frame(frame, viewport, area))
+        .map_err(WatchError::Terminal)
+}
+
+/// Prints the output to the normal screen, after the terminal has been restored. It's parsed with
+/// `options` like the TUI's, so what would corrupt the screen is dropped or shown escaped there too.
+pub(crate) fn print_final_output(
+    full_watch_command: &str,
+    std_output: &str,
+    std_error: &str,
+    options: ansi::Options,
+) -> Result<()> {
+    queue!(
+        stdout(),
+        Print("> "),
+        Print(full_watch_command),
+        MoveToNextLine(2),
+        PrintStyledContent("Output:".bold().underlined()),
+        MoveToNextLine(1),
+    )?;
+    print_lines(std_output, options)?;
+    if !std_error.is_empty() {
+        queue!(
+            stdout(),
+            PrintStyledContent("StdErr:".bold().underlined()),
+            MoveToNextLine(1),
+        )?;
+        print_lines(std_error, options)?;
+    }
+    stdout().flush()
+}
+
+/// Prints `text` parsed with `options`, in the styles it's parsed into, and moves to the next line.
+fn print_lines(text: &str, options: ansi::Options) -> Result<()> {
+    for (i, line) in ansi::parse(text, options).iter().enumerate() {
+        if i > 0 {
+            queue!(stdout(), Print('\n'))?;
+        }
+        for span in line {
+            queue!(
+                stdout(),
+                PrintStyledContent(StyledContent::new(span.style, span.text.as_str()))
+            )?;
+        }
+    }
+    queue!(stdout(), MoveToNextLine(1))
+}
@@ -0,0 +1,41 @@
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// The columns `text` takes up in a terminal: two for wide characters like CJK and most emoji,
+/// none for combining marks.
+pub(crate) fn text_width(text: &str) -> usize {
+    text.width()
+}
+
+/// The columns `c` takes up in a terminal, with control characters taking none.
+pub(crate) fn char_width(c: char) -> usize {
+    c.width().unwrap_or(0)
+}
+
+/// The longest start of `text` that fits in `width` columns. Combining marks stay with the
+/// character before them, and a wide character that only half fits is left out.
+pub(crate) fn truncate(text: &str, width: usize) -> &str {
+    let mut used = 0;
+    for (i, c) in text.char_indices() {
+        used += char_width(c);
+        if used > width {
+            return &text[..i];
+        }
+    }
+    text
+}
+
+/// `text`, ending in `…` if it had to be cut to fit in `width` columns.
+pub(crate) fn ellipsize(text: &str, width: usize) -> String {
+    if text_width(text) <= width {
+        return text.to_string();
+    }
+    let mut cut = truncate(text, width.saturating_sub(1)).to_string();
+    cut.push('…');
+    cut
+}
+
+/// `text` cut or padded with spaces to exactly `width` columns.
+pub(crate) fn fit(text: &str, width: usize) -> String {
+    let cut = truncate(text, width);
+    format!("{}{}", cut, " ".repeat(width - text_width(cut)))
+}
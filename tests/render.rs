@@ -0,0 +1,158 @@
+//! Captures frames drawn by a [`Renderer`] into a buffer.
+
+use std::{process::ExitStatus, time::SystemTime};
+
+use regex::Regex;
+use watch_rs::render::{self, Area, Frame, Renderer};
+use watch_rs::{ExecutionResult, Status, Theme};
+
+/// The escape sequence clearing the whole screen.
+const CLEAR_ALL: &str = "\x1b[2J";
+
+fn result(stdout: &str) -> ExecutionResult {
+    ExecutionResult {
+        stdout: stdout.to_string(),
+        stderr: String::new(),
+        interleaved: Vec::new(),
+        status: Status::Exited(ExitStatus::default()),
+        started_at: SystemTime::UNIX_EPOCH,
+        duration: Default::default(),
+        changed: false,
+    }
+}
+
+/// Draws `result` on a 60x6 screen, returning what was written for it.
+fn draw(renderer: &mut Renderer<Vec<u8>>, result: &ExecutionResult) -> String {
+    renderer.get_mut().clear();
+    let frame = Frame::new("uptime")
+        .hostname("build-box")
+        .footer("q to exit");
+    renderer
+        .draw(result, &frame, Area::new(0, 0, 60, 6))
+        .unwrap();
+    String::from_utf8_lossy(renderer.get_ref()).into_owned()
+}
+
+#[test]
+fn draws_the_header_output_and_footer() {
+    let mut renderer = Renderer::new(Vec::new(), 60, 6);
+    let screen = draw(&mut renderer, &result("up 3 days\nload 0.5"));
+
+    assert!(screen.starts_with(CLEAR_ALL));
+    for text in [
+        "Every 2.0s: ",
+        "uptime",
+        "build-box",
+        "up 3 days",
+        "load 0.5",
+        "q to exit",
+    ] {
+        assert!(screen.contains(text), "{text:?} missing from {screen:?}");
+    }
+}
+
+#[test]
+fn only_rewrites_rows_that_changed() {
+    let mut renderer = Renderer::new(Vec::new(), 60, 6);
+    draw(&mut renderer, &result("up 3 days\nload 0.5"));
+    let screen = draw(&mut renderer, &result("up 3 days\nload 0.7"));
+
+    assert!(!screen.contains(CLEAR_ALL));
+    assert!(screen.contains("load 0.7"));
+    assert!(!screen.contains("up 3 days"));
+    assert!(!screen.contains("uptime"));
+}
+
+#[test]
+fn renderers_remember_what_they_drew_apart() {
+    let (mut load, mut disk) = (
+        Renderer::new(Vec::new(), 60, 6),
+        Renderer::new(Vec::new(), 60, 6),
+    );
+    draw(&mut load, &result("load 0.5"));
+    draw(&mut disk, &result("disk 91%"));
+    // Neither the other renderer nor the terminal being drawn over changes what this one shows
+    render::invalidate();
+    let screen = draw(&mut load, &result("load 0.5"));
+
+    assert!(!screen.contains(CLEAR_ALL));
+    assert!(!screen.contains("load 0.5"));
+}
+
+#[test]
+fn redraws_everything_after_a_resize() {
+    let mut renderer = Renderer::new(Vec::new(), 60, 6);
+    draw(&mut renderer, &result("up 3 days"));
+    renderer.resize(70, 6);
+    let screen = draw(&mut renderer, &result("up 3 days"));
+
+    assert!(screen.starts_with(CLEAR_ALL));
+    assert!(screen.contains("up 3 days"));
+}
+
+#[test]
+fn control_characters_dont_reach_the_screen() {
+    let mut renderer = Renderer::new(Vec::new(), 60, 6);
+    let screen = draw(&mut renderer, &result("ding\x07 \x1b]0;title\x07done\tok"));
+
+    assert!(screen.contains("ding done       ok"));
+    assert!(!screen.contains('\x07'));
+    assert!(!screen.contains("title"));
+}
+
+#[test]
+fn wide_characters_take_two_columns() {
+    let mut renderer = Renderer::new(Vec::new(), 60, 6);
+    let screen = draw(
+        &mut renderer,
+        &result(&format!("日\tok\n{}", "日".repeat(31))),
+    );
+
+    // 30 fill the 60 columns, and the last one wraps
+    assert!(screen.contains(&"日".repeat(30)));
+    assert!(!screen.contains(&"日".repeat(31)));
+    assert!(screen.contains("日      ok"));
+}
+
+#[test]
+fn the_header_and_footer_are_cut_to_fit_narrow_screens() {
+    let mut renderer = Renderer::new(Vec::new(), 20, 6);
+    let frame = Frame::new("uptime")
+        .hostname("build-box")
+        .footer("q to exit, h for help")
+        .paused(true);
+    renderer
+        .draw(&result("up 3 days"), &frame, Area::new(0, 0, 20, 6))
+        .unwrap();
+    let screen = String::from_utf8_lossy(renderer.get_ref());
+
+    // Each row is drawn after moving the cursor to it
+    let rows = Regex::new(r"\x1b\[\d+;\d+H").unwrap();
+    let escapes = Regex::new(r"\x1b\[[0-9;?]*[A-Za-z]").unwrap();
+    for row in rows.split(&screen).skip(1) {
+        let text = escapes.replace_all(row, "");
+        assert!(
+            text.chars().count() <= 20,
+            "{text:?} is wider than the screen"
+        );
+    }
+    assert!(screen.contains("Every"));
+}
+
+#[test]
+fn the_none_theme_draws_the_footer_without_styles() {
+    let mut renderer = Renderer::new(Vec::new(), 60, 6);
+    let theme = Theme::none();
+    let frame = Frame::new("uptime")
+        .footer("q to exit")
+        .paused(true)
+        .theme(&theme);
+    renderer
+        .draw(&result("up 3 days"), &frame, Area::new(0, 0, 60, 6))
+        .unwrap();
+    let screen = String::from_utf8_lossy(renderer.get_ref());
+
+    assert!(screen.contains("[Paused]"));
+    let styles = Regex::new(r"\x1b\[[0-9;]*m").unwrap();
+    assert!(!styles.is_match(&screen), "{screen:?}");
+}
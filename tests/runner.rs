@@ -0,0 +1,488 @@
+//! Runs the watch engine against scripted output, through a fake [`CommandRunner`], so that
+//! diffing, exit conditions, and the history can be checked without spawning the command.
+
+use std::{
+    collections::VecDeque,
+    env, fs,
+    io::{Error, Result},
+    process::ExitStatus,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use regex::Regex;
+use serde_json::Value;
+use watch_rs::render::{Area, Frame, Renderer};
+use watch_rs::{
+    ChangeEvent, CommandRunner, Differences, ExecutionResult, ExitReason, PlannedCommand,
+    RunOutput, Status, Truncation, WatchStream, Watcher,
+};
+
+/// Plays back a fixed list of outputs, one per run, remembering each command it was given.
+struct Script {
+    outputs: VecDeque<RunOutput>,
+    seen: Arc<Mutex<Vec<PlannedCommand>>>,
+}
+
+impl Script {
+    fn new(outputs: impl IntoIterator<Item = RunOutput>) -> Self {
+        Script {
+            outputs: outputs.into_iter().collect(),
+            seen: Arc::default(),
+        }
+    }
+}
+
+impl CommandRunner for Script {
+    fn run(&mut self, command: &PlannedCommand) -> Result<RunOutput> {
+        self.seen.lock().unwrap().push(command.clone());
+        self.outputs
+            .pop_front()
+            .ok_or_else(|| Error::other("the script ran out"))
+    }
+}
+
+#[cfg(unix)]
+fn exit_status(code: i32) -> ExitStatus {
+    std::os::unix::process::ExitStatusExt::from_raw(code << 8)
+}
+
+#[cfg(windows)]
+fn exit_status(code: i32) -> ExitStatus {
+    std::os::windows::process::ExitStatusExt::from_raw(code as u32)
+}
+
+fn failure(code: i32, stderr: &str) -> RunOutput {
+    RunOutput {
+        status: Status::Exited(exit_status(code)),
+        stdout: Vec::new(),
+        stderr: stderr.into(),
+    }
+}
+
+fn watcher() -> Watcher {
+    Watcher::new("status").interval(Duration::from_millis(100))
+}
+
+/// Runs `watcher` with `script` to the end, returning every result and why it ended.
+fn run(watcher: Watcher, script: Script) -> (Vec<ExecutionResult>, Option<ExitReason>) {
+    let mut stream: WatchStream = watcher.runner(script).watch_iter();
+    let results = stream
+        .by_ref()
+        .collect::<std::result::Result<_, _>>()
+        .unwrap();
+    (results, stream.exit_reason())
+}
+
+#[test]
+fn marks_runs_whose_output_changed() {
+    let script = Script::new(["a", "a", "b", "b"].map(RunOutput::success));
+    let (results, reason) = run(watcher().count(Some(4)), script);
+
+    let changed: Vec<bool> = results.iter().map(|result| result.changed).collect();
+    assert_eq!(changed, [false, false, true, false]);
+    assert_eq!(reason, Some(ExitReason::Count));
+}
+
+#[test]
+fn chgexit_stops_at_the_first_change() {
+    let script = Script::new(["a", "a", "b", "c"].map(RunOutput::success));
+    let (results, reason) = run(watcher().chgexit(true), script);
+
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[2].stdout, "b");
+    assert_eq!(reason, Some(ExitReason::Changed));
+}
+
+#[test]
+fn until_stops_once_the_output_matches() {
+    let script = Script::new(["Pending", "Pending", "Running"].map(RunOutput::success));
+    let until = Regex::new("^Running$").unwrap();
+    let (results, reason) = run(watcher().until(Some(until)), script);
+
+    assert_eq!(results.len(), 3);
+    assert_eq!(reason, Some(ExitReason::Until));
+}
+
+#[test]
+fn while_matches_stops_once_the_output_doesnt() {
+    let script = Script::new(["busy", "busy", "idle"].map(RunOutput::success));
+    let busy = Regex::new("busy").unwrap();
+    let (results, reason) = run(watcher().while_matches(Some(busy)), script);
+
+    assert_eq!(results.len(), 3);
+    assert_eq!(reason, Some(ExitReason::While));
+}
+
+#[test]
+fn errexit_stops_at_the_first_failure() {
+    let script = Script::new([
+        RunOutput::success("ok"),
+        failure(3, "broken"),
+        RunOutput::success("ok"),
+    ]);
+    let (results, reason) = run(watcher().errexit(true), script);
+
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[1].status.code(), Some(3));
+    assert_eq!(results[1].stderr, "broken");
+    assert_eq!(reason, Some(ExitReason::Failed));
+}
+
+#[test]
+fn failures_dont_stop_the_watch_by_default() {
+    let script = Script::new([failure(1, "down"), RunOutput::success("up")]);
+    let (results, reason) = run(watcher().count(Some(2)), script);
+
+    assert!(!results[0].status.success());
+    assert!(results[1].status.success());
+    assert!(results[1].changed);
+    assert_eq!(reason, Some(ExitReason::Count));
+}
+
+#[test]
+fn grep_filters_before_changes_are_detected() {
+    let script = Script::new(
+        [
+            "load 1\nuptime 10",
+            "load 1\nuptime 11",
+            "load 2\nuptime 12",
+        ]
+        .map(RunOutput::success),
+    );
+    let load = Regex::new("^load").unwrap();
+    let (results, _) = run(watcher().count(Some(3)).grep(Some(load)), script);
+
+    let changed: Vec<bool> = results.iter().map(|result| result.changed).collect();
+    assert_eq!(changed, [false, false, true]);
+    assert_eq!(results[2].stdout, "load 2");
+}
+
+#[test]
+fn max_output_keeps_part_of_each_pipe_and_notes_what_was_dropped() {
+    let script = Script::new(
+        ["up 1\nload 0.10", "up 1\nload 0.25", "up 2\nload 0.25"].map(RunOutput::success),
+    );
+    let (results, _) = run(watcher().count(Some(3)).max_output(Some(5)), script);
+
+    assert_eq!(
+        results[0].stdout,
+        "up 1\n[output truncated (9 bytes dropped)]"
+    );
+    let changed: Vec<bool> = results.iter().map(|result| result.changed).collect();
+    assert_eq!(changed, [false, false, true]);
+
+    let script = Script::new([RunOutput::success("1\n2\n3\n4"), failure(1, "oops\nno")]);
+    let watcher = watcher()
+        .count(Some(2))
+        .max_output(Some(3))
+        .truncation(Truncation::Tail);
+    let (results, _) = run(watcher, script);
+
+    assert_eq!(
+        results[0].stdout,
+        "[output truncated (4 bytes dropped)]\n3\n4"
+    );
+    assert_eq!(
+        results[1].stderr,
+        "[output truncated (5 bytes dropped)]\nno"
+    );
+}
+
+#[test]
+fn the_history_keeps_the_latest_runs_up_to_its_capacity() {
+    let dir = env::temp_dir().join(format!("watch-rs-history-{}", std::process::id()));
+    let preloaded = RunOutput::success("0");
+    let (earlier, _) = run(watcher().count(Some(1)), Script::new([preloaded]));
+    let script = Script::new(["1", "2", "3", "4"].map(RunOutput::success));
+    let watcher = watcher().count(Some(4)).history(3).preload(earlier);
+    run(watcher.export(&dir), script);
+
+    let index: Value =
+        serde_json::from_str(&fs::read_to_string(dir.join("index.json")).unwrap()).unwrap();
+    let kept: Vec<String> = index["runs"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|run| {
+            let file = fs::read_to_string(dir.join(run["file"].as_str().unwrap())).unwrap();
+            file.lines().last().unwrap().to_string()
+        })
+        .collect();
+    fs::remove_dir_all(&dir).unwrap();
+    // The preloaded run and the first one are gone, oldest first
+    assert_eq!(kept, ["2", "3", "4"]);
+}
+
+#[test]
+fn differences_highlight_what_changed_since_the_run_drawn_before() {
+    let script =
+        Script::new(["load 0.5", "load 0.7", "load 0.7", "load 0.9"].map(RunOutput::success));
+    let (results, _) = run(watcher().count(Some(4)), script);
+    // The changed characters are in reverse video
+    let reversed = Regex::new("\x1b\\[7m([^\x1b]*)").unwrap();
+
+    for (mode, expected) in [
+        (Differences::Transient, [None, Some("7"), None, Some("9")]),
+        (
+            Differences::Permanent,
+            [None, Some("7"), Some("7"), Some("9")],
+        ),
+    ] {
+        let mut renderer = Renderer::new(Vec::new(), 40, 6).differences(Some(mode));
+        let highlighted: Vec<Option<String>> = results
+            .iter()
+            .map(|result| {
+                renderer.invalidate();
+                renderer.get_mut().clear();
+                let frame = Frame::new("status");
+                renderer
+                    .draw(result, &frame, Area::new(0, 0, 40, 6))
+                    .unwrap();
+                let screen = String::from_utf8_lossy(renderer.get_ref()).into_owned();
+                reversed.captures(&screen).map(|found| found[1].to_string())
+            })
+            .collect();
+        assert_eq!(
+            highlighted,
+            expected.map(|text| text.map(String::from)),
+            "{mode:?}"
+        );
+    }
+}
+
+#[test]
+fn templates_see_the_previous_run() {
+    let script = Script::new([failure(2, ""), RunOutput::success("")]);
+    let seen = Arc::clone(&script.seen);
+    let watcher = Watcher::new("check")
+        .arg("--run={i}")
+        .arg("--last={prev_exit}")
+        .exec(true)
+        .template(true)
+        .interval(Duration::from_millis(100))
+        .count(Some(2));
+    run(watcher, script);
+
+    let args: Vec<Vec<String>> = seen
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|command| command.args.clone())
+        .collect();
+    assert_eq!(args, [["--run=1", "--last="], ["--run=2", "--last=2"]]);
+}
+
+#[test]
+fn and_commands_are_run_in_turn_under_headings() {
+    let script = Script::new(["one", "two"].map(RunOutput::success));
+    let seen = Arc::clone(&script.seen);
+    let (results, _) = run(watcher().and("other").count(Some(1)), script);
+
+    assert_eq!(
+        results[0].stdout,
+        "==> status <==\none\n\n==> other <==\ntwo"
+    );
+    let headings: Vec<Option<String>> = seen
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|command| command.heading.clone())
+        .collect();
+    assert_eq!(headings, [Some("status".into()), Some("other".into())]);
+}
+
+#[test]
+fn foreach_runs_once_per_item_under_headings_with_their_status() {
+    let script = Script::new([RunOutput::success("up"), failure(2, "unreachable")]);
+    let seen = Arc::clone(&script.seen);
+    let watcher = Watcher::new("ping")
+        .arg("{}")
+        .exec(true)
+        .foreach(["web1", "web2"]);
+    let (results, _) = run(watcher.count(Some(1)), script);
+
+    assert_eq!(
+        results[0].stdout,
+        "==> web1 (ok) <==\nup\n\n==> web2 (exit code 2) <=="
+    );
+    assert!(!results[0].status.success());
+    let seen = seen.lock().unwrap();
+    let args: Vec<&[String]> = seen.iter().map(|command| &command.args[..]).collect();
+    assert_eq!(args, [["web1"], ["web2"]]);
+    assert!(seen.iter().all(|command| command.parallel));
+}
+
+#[test]
+fn from_fn_shows_what_the_function_returns_and_fails_on_its_errors() {
+    let mut polls = VecDeque::from([Ok("1 job"), Err("unreachable"), Ok("1 job"), Ok("2 jobs")]);
+    let watcher = Watcher::from_fn("jobs", move || polls.pop_front().unwrap().map(String::from))
+        .interval(Duration::from_millis(100))
+        .count(Some(4));
+    let results: Vec<ExecutionResult> = watcher
+        .watch_iter()
+        .collect::<std::result::Result<_, _>>()
+        .unwrap();
+
+    let failed: Vec<bool> = results
+        .iter()
+        .map(|result| !result.status.success())
+        .collect();
+    assert_eq!(failed, [false, true, false, false]);
+    assert_eq!(results[1].stderr, "unreachable");
+    assert!(results[3].changed);
+}
+
+#[test]
+fn the_interval_is_counted_from_when_the_run_finished() {
+    let slow = || {
+        std::thread::sleep(Duration::from_millis(150));
+        Ok::<_, String>("1 job".to_string())
+    };
+    let results: Vec<ExecutionResult> = Watcher::from_fn("jobs", slow)
+        .interval(Duration::from_millis(100))
+        .count(Some(2))
+        .watch_iter()
+        .collect::<std::result::Result<_, _>>()
+        .unwrap();
+
+    let gap = results[1]
+        .started_at
+        .duration_since(results[0].started_at)
+        .unwrap();
+    assert!(gap >= Duration::from_millis(250), "{:?}", gap);
+}
+
+#[test]
+fn subscribers_only_hear_of_changes_that_outlast_the_debounce() {
+    let script =
+        Script::new(["a\nb", "a\nflap", "a\nb", "a", "a", "a", "a"].map(RunOutput::success));
+    let mut watcher = watcher().count(Some(7)).runner(script);
+    let events = watcher.subscribe(Some(Duration::from_millis(150)));
+    for result in watcher.watch_iter() {
+        result.unwrap();
+    }
+
+    let events: Vec<ChangeEvent> = events.try_iter().collect();
+    assert_eq!(
+        events,
+        [ChangeEvent::Removed {
+            line: 2,
+            text: "b".to_string()
+        }]
+    );
+}
+
+#[test]
+fn changes_are_numbered_by_the_output_they_are_at() {
+    let script = Script::new(["a\nb\nc", "a\nc\nd", "x\nc\nd\ne"].map(RunOutput::success));
+    let mut watcher = watcher().count(Some(3)).runner(script);
+    let events = watcher.subscribe(None);
+    for result in watcher.watch_iter() {
+        result.unwrap();
+    }
+
+    let events: Vec<ChangeEvent> = events.try_iter().collect();
+    assert_eq!(
+        events,
+        [
+            // From the older output
+            ChangeEvent::Removed {
+                line: 2,
+                text: "b".to_string()
+            },
+            // From the newer one
+            ChangeEvent::Added {
+                line: 3,
+                text: "d".to_string()
+            },
+            ChangeEvent::Modified {
+                line: 1,
+                old: "a".to_string(),
+                new: "x".to_string()
+            },
+            ChangeEvent::Added {
+                line: 4,
+                text: "e".to_string()
+            },
+        ]
+    );
+}
+
+#[test]
+fn output_that_keeps_flapping_back_within_the_debounce_sends_nothing() {
+    let script = Script::new(["up", "down", "up", "down", "up"].map(RunOutput::success));
+    let mut watcher = watcher().count(Some(5)).runner(script);
+    let events = watcher.subscribe(Some(Duration::from_millis(150)));
+    for result in watcher.watch_iter() {
+        result.unwrap();
+    }
+
+    assert_eq!(events.try_iter().count(), 0);
+}
+
+#[test]
+fn a_change_still_waiting_out_the_debounce_is_sent_when_the_watch_ends() {
+    let script = Script::new(["up", "down"].map(RunOutput::success));
+    let mut watcher = watcher().count(Some(2)).runner(script);
+    let events = watcher.subscribe(Some(Duration::from_secs(60)));
+    let mut stream = watcher.watch_iter();
+    for result in stream.by_ref() {
+        result.unwrap();
+    }
+    assert_eq!(events.try_iter().count(), 0);
+
+    drop(stream);
+    let events: Vec<ChangeEvent> = events.try_iter().collect();
+    assert_eq!(
+        events,
+        [ChangeEvent::Modified {
+            line: 1,
+            old: "up".to_string(),
+            new: "down".to_string()
+        }]
+    );
+}
+
+#[test]
+fn runner_errors_end_the_stream() {
+    let mut stream = watcher().runner(Script::new([])).watch_iter();
+
+    assert!(stream.next().unwrap().is_err());
+    assert!(stream.next().is_none());
+}
+
+#[test]
+fn jq_filters_before_changes_are_detected() {
+    let script = Script::new(
+        [
+            r#"{"status": "ok", "checked_at": 1}"#,
+            r#"{"status": "ok", "checked_at": 2}"#,
+            r#"{"status": "down", "checked_at": 3}"#,
+        ]
+        .map(RunOutput::success),
+    );
+    let filter = ".status".parse().unwrap();
+    let (results, _) = run(watcher().count(Some(3)).jq(Some(filter)), script);
+
+    let changed: Vec<bool> = results.iter().map(|result| result.changed).collect();
+    assert_eq!(changed, [false, false, true]);
+    assert_eq!(results[2].stdout, r#""down""#);
+}
+
+#[cfg(unix)]
+#[test]
+fn pipe_filters_stdout_and_reports_its_own_failure() {
+    let script = Script::new([RunOutput::success("b\nc\na\n")]);
+    let seen = Arc::clone(&script.seen);
+    let pipe = "sort; exit 3";
+    let (results, _) = run(watcher().count(Some(1)).pipe(Some(pipe.into())), script);
+
+    assert_eq!(results[0].stdout, "a\nb\nc");
+    assert!(results[0].status.success());
+    assert_eq!(
+        results[0].stderr,
+        "pipe `sort; exit 3` failed with exit code 3"
+    );
+    assert_eq!(seen.lock().unwrap()[0].pipe.as_deref(), Some(pipe));
+}
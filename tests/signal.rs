@@ -0,0 +1,29 @@
+//! Stops a watch with a signal, in a process of its own so the signal doesn't reach other tests.
+#![cfg(unix)]
+
+use std::{process::Command, time::Duration};
+
+use watch_rs::{ExitReason, OutputMode, Watcher};
+
+fn watcher(source: impl FnMut() -> Result<String, String> + Send + 'static) -> Watcher {
+    Watcher::from_fn("jobs", source)
+        .interval(Duration::from_millis(100))
+        .output(OutputMode::Plain)
+}
+
+#[test]
+fn a_signal_only_stops_the_watch_it_arrives_during() {
+    let terminate = || {
+        let pid = std::process::id().to_string();
+        Command::new("kill").args(["-TERM", &pid]).status().unwrap();
+        Ok("1 job".to_string())
+    };
+    let stopped = watcher(terminate).run().unwrap();
+    assert_eq!(stopped.reason, ExitReason::Interrupted);
+
+    let next = watcher(|| Ok("1 job".to_string()))
+        .count(Some(2))
+        .run()
+        .unwrap();
+    assert_eq!(next.reason, ExitReason::Count);
+}
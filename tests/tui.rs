@@ -0,0 +1,121 @@
+//! Runs the TUI on a pseudo-terminal, in a process of its own since it takes over stdin and stdout.
+#![cfg(unix)]
+
+use std::{
+    fs::File,
+    io::{self, Read},
+    ptr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+    thread,
+    time::Duration,
+};
+
+use watch_rs::{ExitReason, Watcher};
+
+/// Held while stdin and stdout are on a pseudo-terminal, which the tests take turns with.
+static TERMINAL: Mutex<()> = Mutex::new(());
+
+/// Runs `f` with stdin and stdout on a pseudo-terminal, returning what it printed there too.
+fn on_a_terminal<T>(f: impl FnOnce() -> T) -> (T, String) {
+    let _turn = TERMINAL.lock().unwrap_or_else(PoisonError::into_inner);
+    let (mut leader, mut follower) = (0, 0);
+    let size = libc::winsize {
+        ws_row: 24,
+        ws_col: 80,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    // SAFETY: the pointers are to live locals, and the descriptors are only used below
+    let saved = unsafe {
+        assert_eq!(
+            libc::openpty(
+                &mut leader,
+                &mut follower,
+                ptr::null_mut(),
+                ptr::null(),
+                &size
+            ),
+            0,
+            "{}",
+            io::Error::last_os_error()
+        );
+        let saved = (libc::dup(0), libc::dup(1));
+        libc::dup2(follower, 0);
+        libc::dup2(follower, 1);
+        saved
+    };
+    // SAFETY: the descriptor was just opened, and nothing else owns it
+    let mut screen = unsafe { <File as std::os::fd::FromRawFd>::from_raw_fd(leader) };
+    let printed = thread::spawn(move || {
+        let mut printed = Vec::new();
+        // It fails rather than ending once the terminal is closed
+        let _ = screen.read_to_end(&mut printed);
+        String::from_utf8_lossy(&printed).into_owned()
+    });
+    let result = f();
+    // SAFETY: these are the descriptors saved above
+    unsafe {
+        libc::dup2(saved.0, 0);
+        libc::dup2(saved.1, 1);
+        libc::close(saved.0);
+        libc::close(saved.1);
+        libc::close(follower);
+    }
+    (result, printed.join().unwrap())
+}
+
+#[test]
+fn functions_are_called_even_when_streaming() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counted = Arc::clone(&calls);
+    let (outcome, _) = on_a_terminal(|| {
+        Watcher::from_fn("jobs", move || {
+            counted.fetch_add(1, Ordering::SeqCst);
+            Ok::<_, String>("1 job".to_string())
+        })
+        .interval(Duration::from_millis(100))
+        .count(Some(2))
+        .stream(true)
+        .interactive(true)
+        .draw_to(io::sink(), 80, 24)
+        .run()
+        .unwrap()
+    });
+
+    assert_eq!(outcome.reason, ExitReason::Count);
+    assert_eq!(outcome.last.unwrap().stdout, "1 job");
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+fn printing(show_control_chars: bool) -> String {
+    let (_, printed) = on_a_terminal(|| {
+        Watcher::from_fn("title", || {
+            Ok::<_, String>("\x1b]0;owned\x07\x1b[31mred\x1b[0m\rok".to_string())
+        })
+        .count(Some(1))
+        .show_control_chars(show_control_chars)
+        .draw_to(io::sink(), 80, 24)
+        .run()
+        .unwrap()
+    });
+    // What's printed once the terminal is restored
+    let (_, last) = printed.rsplit_once("> title").unwrap();
+    last.to_string()
+}
+
+#[test]
+fn the_last_output_is_printed_without_what_would_corrupt_the_screen() {
+    let printed = printing(false);
+    assert!(printed.contains("ok"), "{:?}", printed);
+    assert!(!printed.contains("red"), "{:?}", printed);
+    assert!(!printed.contains("owned"), "{:?}", printed);
+    assert!(!printed.contains('\x07'), "{:?}", printed);
+
+    let printed = printing(true);
+    assert!(printed.contains("^[]0;owned^G"), "{:?}", printed);
+    assert!(printed.contains("\x1b[38;5;1mred"), "{:?}", printed);
+    assert!(printed.contains("^M"), "{:?}", printed);
+}